@@ -0,0 +1,50 @@
+use serde::{Deserialize, Serialize};
+use crate::api::{Channel, NotApplicable};
+use crate::api::JsonEndpoint;
+
+impl JsonEndpoint for GetAiStateRequest {
+    const CMD: &'static str = "GetAiState";
+    type Response = GetAiStateResponse;
+    type Initial = NotApplicable;
+    type Range = NotApplicable;
+}
+
+/// Get the state of the AI detections of a channel.
+#[derive(Debug, Clone, Serialize)]
+pub struct GetAiStateRequest {
+    pub channel: Channel,
+}
+
+//----- Response
+
+/// Detection states. A detection type is missing if the firmware doesn't know it.
+#[derive(Debug, Clone, Deserialize)]
+pub struct GetAiStateResponse {
+    pub channel: Channel,
+    #[serde(default)]
+    pub people: Option<AiState>,
+    #[serde(default)]
+    pub vehicle: Option<AiState>,
+    /// Pets
+    #[serde(default)]
+    pub dog_cat: Option<AiState>,
+    #[serde(default)]
+    pub face: Option<AiState>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct AiState {
+    /// Is this type of object currently detected?
+    #[serde(with = "crate::serde::bool_as_number")]
+    pub alarm_state: bool,
+    /// Does the channel support detecting this type of object?
+    #[serde(with = "crate::serde::bool_as_number")]
+    pub support: bool,
+}
+
+impl AiState {
+    /// Is this type of object supported and currently detected?
+    pub fn is_active(&self) -> bool {
+        self.support && self.alarm_state
+    }
+}
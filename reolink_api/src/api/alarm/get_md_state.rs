@@ -0,0 +1,25 @@
+use serde::{Deserialize, Serialize};
+use crate::api::{Channel, NotApplicable};
+use crate::api::JsonEndpoint;
+
+impl JsonEndpoint for GetMdStateRequest {
+    const CMD: &'static str = "GetMdState";
+    type Response = GetMdStateResponse;
+    type Initial = NotApplicable;
+    type Range = NotApplicable;
+}
+
+/// Get the motion detection state of a channel.
+#[derive(Debug, Clone, Serialize)]
+pub struct GetMdStateRequest {
+    pub channel: Channel,
+}
+
+//----- Response
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct GetMdStateResponse {
+    /// Is motion currently detected?
+    #[serde(with = "crate::serde::bool_as_number")]
+    pub state: bool,
+}
@@ -1,7 +1,11 @@
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::fmt::{Debug, Display, Formatter};
+use std::time::Duration;
 use serde::de::DeserializeOwned;
 
+pub mod ai;
+pub mod alarm;
+pub mod network;
 pub mod security;
 pub mod record;
 pub mod system;
@@ -30,10 +34,187 @@ pub trait JsonEndpoint : Serialize + Debug {
     type Range: DeserializeOwned;
 }
 
+/// A `Get` endpoint for a configuration section that has a matching `Set` endpoint. The `Set`
+/// endpoint expects the full configuration object, so updates are read-modify-write operations.
+pub trait ConfigEndpoint : JsonEndpoint {
+    /// The configuration object, returned by this endpoint and sent back to the `Set` endpoint
+    type Config: Serialize + PartialEq + Clone + Debug;
+    /// The matching `Set` endpoint, whose values are validated against this endpoint's `range`
+    type Set: Validate<RangeEndpoint = Self>;
+
+    /// Creates a request to get the configuration of a channel
+    fn get_request(channel: Channel) -> Self;
+
+    /// Extracts the configuration object from a response
+    fn config(response: Self::Response) -> Self::Config;
+
+    /// Extracts the factory default configuration object from a response's `initial` value
+    fn initial_config(initial: Self::Initial) -> Self::Config;
+
+    /// Sets the channel of a configuration object. Factory defaults may hold another channel
+    /// than the one they were requested for, usually 0.
+    fn set_channel(config: &mut Self::Config, channel: Channel);
+
+    /// Creates a request to set the configuration of a channel
+    fn set_request(channel: Channel, config: Self::Config) -> Self::Set;
+}
+
+/// A request whose values can be checked against the `range` returned by another endpoint
+/// before being sent to the device.
+pub trait Validate : JsonEndpoint {
+    /// The endpoint whose `range` lists the values accepted by this request
+    type RangeEndpoint: JsonEndpoint;
+
+    /// Creates the request used to fetch the range
+    fn range_request(&self) -> Self::RangeEndpoint;
+
+    /// Checks this request against the range returned by the device
+    fn validate(&self, range: &<Self::RangeEndpoint as JsonEndpoint>::Range) -> Result<(), ValidationError>;
+}
+
+/// A request value that is outside of the range accepted by the device
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValidationError {
+    /// The value isn't in the list of allowed values
+    NotAllowed {
+        field: &'static str,
+        value: String,
+        allowed: Vec<String>,
+    },
+    /// The value's length is outside of the allowed bounds
+    Length {
+        field: &'static str,
+        len: usize,
+        min: usize,
+        max: usize,
+    },
+}
+
+impl ValidationError {
+    /// Checks that `value` is one of the `allowed` values
+    pub fn check_allowed<T: PartialEq + Display>(field: &'static str, value: &T, allowed: &[T]) -> Result<(), ValidationError> {
+        if allowed.contains(value) {
+            Ok(())
+        } else {
+            Err(ValidationError::NotAllowed {
+                field,
+                value: value.to_string(),
+                allowed: allowed.iter().map(T::to_string).collect(),
+            })
+        }
+    }
+
+    /// Checks that the length (in characters) of `value` is within `min..=max`
+    pub fn check_length(field: &'static str, value: &str, min: usize, max: usize) -> Result<(), ValidationError> {
+        let len = value.chars().count();
+        if (min..=max).contains(&len) {
+            Ok(())
+        } else {
+            Err(ValidationError::Length { field, len, min, max })
+        }
+    }
+}
+
+impl Display for ValidationError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ValidationError::NotAllowed { field, value, allowed } => {
+                write!(f, "invalid value '{}' for '{}', allowed values are: {}", value, field, allowed.join(", "))
+            },
+            ValidationError::Length { field, len, min, max } => {
+                write!(f, "invalid length {} for '{}', must be between {} and {}", len, field, min, max)
+            },
+        }
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+/// A duration, as the text used by devices for durations like `postRec` (e.g. `30 Seconds`
+/// or `1 Minute`).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum DeviceDuration {
+    Duration(Duration),
+    /// A value that isn't a number of seconds or minutes, kept as is
+    Other(String),
+}
+
+impl DeviceDuration {
+    pub fn seconds(secs: u64) -> Self {
+        DeviceDuration::Duration(Duration::from_secs(secs))
+    }
+
+    pub fn minutes(mins: u64) -> Self {
+        DeviceDuration::Duration(Duration::from_secs(mins * 60))
+    }
+
+    /// The duration, if it could be parsed.
+    pub fn as_duration(&self) -> Option<Duration> {
+        match self {
+            DeviceDuration::Duration(d) => Some(*d),
+            DeviceDuration::Other(_) => None,
+        }
+    }
+
+    fn parse(text: &str) -> Option<Duration> {
+        let (value, unit) = text.split_once(' ')?;
+        let value = value.parse::<u64>().ok()?;
+        match unit {
+            "Second" | "Seconds" => Some(Duration::from_secs(value)),
+            "Minute" | "Minutes" => Some(Duration::from_secs(value * 60)),
+            _ => None,
+        }
+    }
+}
+
+impl From<&str> for DeviceDuration {
+    fn from(value: &str) -> Self {
+        // Only keep parsed values that are formatted back to the same text, so that
+        // values sent back to the device are identical to those it returned.
+        match DeviceDuration::parse(value).map(DeviceDuration::Duration) {
+            Some(d) if d.to_string() == value => d,
+            _ => DeviceDuration::Other(value.to_string()),
+        }
+    }
+}
+
+impl Display for DeviceDuration {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DeviceDuration::Duration(d) => {
+                let secs = d.as_secs();
+                match secs {
+                    60 => f.write_str("1 Minute"),
+                    _ if secs > 0 && secs % 60 == 0 => write!(f, "{} Minutes", secs / 60),
+                    1 => f.write_str("1 Second"),
+                    _ => write!(f, "{} Seconds", secs),
+                }
+            },
+            DeviceDuration::Other(text) => f.write_str(text),
+        }
+    }
+}
+
+impl Serialize for DeviceDuration {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl <'de> Deserialize<'de> for DeviceDuration {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let text: std::borrow::Cow<'de, str> = Deserialize::deserialize(deserializer)?;
+        Ok(DeviceDuration::from(text.as_ref()))
+    }
+}
+
 /// A request for an API endpoint returning binary data
 pub trait BinaryEndpoint : Serialize + Debug {
     const CMD: &'static str;
     const AUTH: AuthenticationType = AuthenticationType::Any;
+    /// Maximum duration of the request, including reading the response body. Not applied to
+    /// streamed responses, which can last as long as needed.
+    const TIMEOUT: Duration = Duration::from_secs(5*60);
 }
 
 /// The authentication type an endpoint expects
@@ -64,6 +245,13 @@ impl Display for ApiError {
 
 impl std::error::Error for ApiError {}
 
+impl ApiError {
+    /// Is this error returned for a command that the device doesn't support?
+    pub fn is_not_supported(&self) -> bool {
+        self.error.rsp_code == -9
+    }
+}
+
 #[derive(Debug, Deserialize)]
 pub struct ApiErrorData {
     #[serde(rename = "rspCode")]
@@ -73,3 +261,27 @@ pub struct ApiErrorData {
 
 /// A channel. Reolink NVRs support up to 24 channels.
 pub type Channel = u8;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_device_duration() -> anyhow::Result<()> {
+        for (text, secs) in [("15 Seconds", 15), ("1 Minute", 60), ("2 Minutes", 120), ("60 Minutes", 3600)] {
+            let d = serde_json::from_str::<DeviceDuration>(&format!("\"{}\"", text))?;
+            assert_eq!(Some(Duration::from_secs(secs)), d.as_duration());
+            assert_eq!(text, d.to_string());
+        }
+
+        // Values that wouldn't be sent back as they were received
+        for text in ["120 Seconds", "1 Hour", "1 Minutes", "Forever"] {
+            let d = DeviceDuration::from(text);
+            assert_eq!(DeviceDuration::Other(text.to_string()), d);
+            assert_eq!(format!("\"{}\"", text), serde_json::to_string(&d)?);
+        }
+
+        assert_eq!(DeviceDuration::from("30 Minutes"), DeviceDuration::minutes(30));
+        Ok(())
+    }
+}
@@ -0,0 +1,55 @@
+use serde::{Deserialize, Serialize};
+use crate::api::NotApplicable;
+use crate::api::JsonEndpoint;
+
+impl JsonEndpoint for GetNetPortRequest {
+    const CMD: &'static str = "GetNetPort";
+    type Response = GetNetPortResponse;
+    type Initial = NotApplicable;
+    type Range = NotApplicable;
+}
+
+/// Get the ports of the network services.
+#[derive(Debug, Clone, Serialize)]
+pub struct GetNetPortRequest;
+
+//----- Response
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct GetNetPortResponse {
+    #[serde(rename = "NetPort")]
+    pub net_port: NetPort,
+}
+
+/// Ports of the network services. Older firmwares don't have the `*Enable` fields, and
+/// services are then considered enabled.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NetPort {
+    #[serde(default = "enabled", with = "crate::serde::bool_as_number")]
+    pub http_enable: bool,
+    pub http_port: u16,
+
+    #[serde(default = "enabled", with = "crate::serde::bool_as_number")]
+    pub https_enable: bool,
+    pub https_port: u16,
+
+    /// Port of the proprietary "Baichuan" protocol used by the Reolink apps
+    pub media_port: u16,
+
+    #[serde(default = "enabled", with = "crate::serde::bool_as_number")]
+    pub onvif_enable: bool,
+    pub onvif_port: u16,
+
+    #[serde(default = "enabled", with = "crate::serde::bool_as_number")]
+    pub rtmp_enable: bool,
+    pub rtmp_port: u16,
+
+    #[serde(default = "enabled", with = "crate::serde::bool_as_number")]
+    pub rtsp_enable: bool,
+    pub rtsp_port: u16,
+}
+
+fn enabled() -> bool {
+    true
+}
@@ -0,0 +1,33 @@
+use serde::{Deserialize, Serialize};
+use crate::api::{Channel, NotApplicable};
+use crate::api::JsonEndpoint;
+
+impl JsonEndpoint for GetRtspUrlRequest {
+    const CMD: &'static str = "GetRtspUrl";
+    type Response = GetRtspUrlResponse;
+    type Initial = NotApplicable;
+    type Range = NotApplicable;
+}
+
+/// Get the RTSP urls of a channel. Only supported by NVRs and recent firmwares.
+#[derive(Debug, Clone, Serialize)]
+pub struct GetRtspUrlRequest {
+    pub channel: Channel,
+}
+
+//----- Response
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct GetRtspUrlResponse {
+    #[serde(rename = "rtspUrl")]
+    pub rtsp_url: RtspUrl,
+}
+
+/// RTSP urls, using the device's LAN address and without credentials.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RtspUrl {
+    pub channel: Channel,
+    pub main_stream: String,
+    pub sub_stream: String,
+}
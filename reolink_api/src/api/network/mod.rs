@@ -0,0 +1,2 @@
+pub mod get_net_port;
+pub mod get_rtsp_url;
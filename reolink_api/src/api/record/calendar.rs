@@ -0,0 +1,122 @@
+//! Per-day recording availability, decoded from the statuses returned by `Search`.
+
+use std::collections::BTreeMap;
+use chrono::{Datelike, NaiveDate};
+use crate::api::record::search::SearchStatus;
+
+/// Whether recordings are available for each day of a date range.
+///
+/// Statuses are merged: a day has recordings if any of the statuses added to the calendar
+/// says so, which allows combining the searches of main and sub streams.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RecordingCalendar {
+    days: BTreeMap<NaiveDate, bool>,
+}
+
+impl RecordingCalendar {
+    /// Creates a calendar with no recordings for each day between `start` and `end` (inclusive).
+    pub fn new(start: NaiveDate, end: NaiveDate) -> Self {
+        let days = start.iter_days()
+            .take_while(|day| *day <= end)
+            .map(|day| (day, false))
+            .collect();
+        RecordingCalendar { days }
+    }
+
+    /// Adds the days of a month status. Days outside of the calendar's range are ignored.
+    pub fn add_status(&mut self, status: &SearchStatus) {
+        let Some(first) = NaiveDate::from_ymd_opt(status.year as i32, status.mon as u32, 1) else {
+            return;
+        };
+        for day in first.iter_days().take_while(|d| d.month() == first.month()) {
+            if let Some(value) = self.days.get_mut(&day) {
+                *value |= status.has_recordings(day.day() as u8);
+            }
+        }
+    }
+
+    /// Adds all statuses of a `Search` response, which can cover several months.
+    pub fn add_statuses<'a>(&mut self, statuses: impl IntoIterator<Item = &'a SearchStatus>) {
+        for status in statuses {
+            self.add_status(status);
+        }
+    }
+
+    /// Does `day` have recordings? Returns `None` if it's outside of the calendar's range.
+    pub fn get(&self, day: NaiveDate) -> Option<bool> {
+        self.days.get(&day).copied()
+    }
+
+    pub fn has_recordings(&self, day: NaiveDate) -> bool {
+        self.get(day).unwrap_or(false)
+    }
+
+    /// All days of the calendar, in chronological order.
+    pub fn iter(&self) -> impl Iterator<Item = (NaiveDate, bool)> + '_ {
+        self.days.iter().map(|(day, value)| (*day, *value))
+    }
+
+    /// Days that have recordings, in chronological order.
+    pub fn days_with_recordings(&self) -> impl Iterator<Item = NaiveDate> + '_ {
+        self.iter().filter(|(_, value)| *value).map(|(day, _)| day)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.days.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.days.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::record::ScheduleTable;
+
+    fn date(s: &str) -> NaiveDate {
+        s.parse().unwrap()
+    }
+
+    fn status(year: u16, mon: u8, table: &str) -> SearchStatus {
+        SearchStatus {
+            year,
+            mon,
+            table: ScheduleTable::new(table.bytes().map(|b| b - b'0').collect()),
+        }
+    }
+
+    #[test]
+    fn test_calendar() {
+        let mut calendar = RecordingCalendar::new(date("2025-01-30"), date("2025-02-02"));
+        assert_eq!(4, calendar.len());
+
+        calendar.add_statuses(&[
+            status(2025, 1, "0000000000000000000000000000011"),
+            status(2025, 2, "1000000000000000000000000001"),
+        ]);
+        // Sub stream
+        calendar.add_status(&status(2025, 2, "0100000000000000000000000000"));
+        // Outside of the range
+        calendar.add_status(&status(2024, 12, "1111111111111111111111111111111"));
+
+        assert_eq!(vec![
+            (date("2025-01-30"), true),
+            (date("2025-01-31"), true),
+            (date("2025-02-01"), true),
+            (date("2025-02-02"), true),
+        ], calendar.iter().collect::<Vec<_>>());
+
+        assert_eq!(None, calendar.get(date("2025-02-28")));
+        assert!(!calendar.has_recordings(date("2024-12-01")));
+    }
+
+    #[test]
+    fn test_missing_days() {
+        let mut calendar = RecordingCalendar::new(date("2025-02-01"), date("2025-02-28"));
+        // Short table
+        calendar.add_status(&status(2025, 2, "01"));
+        assert_eq!(vec![date("2025-02-02")], calendar.days_with_recordings().collect::<Vec<_>>());
+    }
+}
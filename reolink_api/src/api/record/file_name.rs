@@ -0,0 +1,260 @@
+//! Metadata encoded in the names of recording files.
+//!
+//! File names look like `Mp4Record/2025-02-09/RecM01_20250209_100000_100500_6D28808_1A468F9.mp4`:
+//! stream type (`M` for main, `S` for sub) and naming version, date, start and end times, a hex
+//! bit field describing the recording, and the hex file size. Variants include an additional
+//! field before the flags, a `DST` prefix on the date when daylight saving time was in effect,
+//! and an absolute path with the camera's UID on the Home Hub.
+
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use crate::api::record::{days_in_month, DateTime, DetectionType, StreamType};
+use crate::api::record::nvr_download::NvrFile;
+use crate::api::record::search::SearchFile;
+
+/// The parsed name of a recording file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordingFileName {
+    pub stream_type: StreamType,
+    /// Version of the naming scheme, the two digits following the stream type.
+    pub version: u8,
+    /// UID of the camera, for files stored on a Home Hub.
+    pub uid: Option<String>,
+    /// Was daylight saving time in effect when recording started?
+    pub dst: bool,
+    pub start_time: DateTime,
+    /// End time, on the next day if the recording spans midnight.
+    pub end_time: DateTime,
+    /// Decoded flags, if the file name uses a known layout.
+    pub flags: Option<RecordingFlags>,
+    pub size: u64,
+}
+
+/// Recording properties encoded as a bit field in file names.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordingFlags {
+    pub frame_rate: u8,
+    /// What caused the recording, as detection types.
+    pub triggers: Vec<DetectionType>,
+}
+
+/// Error returned when a file name doesn't follow the known naming schemes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InvalidFileName(pub String);
+
+impl Display for InvalidFileName {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "invalid recording file name '{}'", self.0)
+    }
+}
+
+impl std::error::Error for InvalidFileName {}
+
+impl RecordingFileName {
+    /// Was the recording triggered by this detection type? Always false if flags couldn't be
+    /// decoded.
+    pub fn has_trigger(&self, detection_type: &DetectionType) -> bool {
+        self.flags.as_ref().is_some_and(|f| f.triggers.contains(detection_type))
+    }
+}
+
+impl FromStr for RecordingFileName {
+    type Err = InvalidFileName;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        parse(name).ok_or_else(|| InvalidFileName(name.to_string()))
+    }
+}
+
+impl SearchFile {
+    /// Parses the metadata encoded in this file's name.
+    pub fn file_name(&self) -> Result<RecordingFileName, InvalidFileName> {
+        self.name.parse()
+    }
+}
+
+impl NvrFile {
+    /// Parses the metadata encoded in this file's name.
+    pub fn file_name(&self) -> Result<RecordingFileName, InvalidFileName> {
+        self.name.parse()
+    }
+}
+
+fn parse(name: &str) -> Option<RecordingFileName> {
+    let (dir, file) = name.rsplit_once('/').unwrap_or(("", name));
+    let file = file.strip_suffix(".mp4").unwrap_or(file);
+
+    // Home Hub: /mnt/sda/<uid>/Mp4Record/<date>/<file>
+    let uid = dir.strip_prefix("/mnt/sda/")
+        .and_then(|d| d.split('/').next())
+        .filter(|d| *d != "Mp4Record")
+        .map(str::to_string);
+
+    let parts = file.split('_').collect::<Vec<_>>();
+    if parts.len() < 6 {
+        return None;
+    }
+
+    let prefix = parts[0].strip_prefix("Rec")?;
+    let stream_type = match prefix.get(..1)? {
+        "M" => StreamType::Main,
+        "S" => StreamType::Sub,
+        _ => return None,
+    };
+    let version = number(&prefix[1..])? as u8;
+
+    let (dst, date) = match parts[1].strip_prefix("DST") {
+        Some(date) => (true, date),
+        None => (false, parts[1]),
+    };
+    // Checked for ASCII so that slicing can't split a character
+    if date.len() != 8 || !date.is_ascii() {
+        return None;
+    }
+    let (year, mon, day) = (number(&date[..4])?, number(&date[4..6])?, number(&date[6..])?);
+    let start_time = time(year, mon, day, parts[2])?;
+    let mut end_time = time(year, mon, day, parts[3])?;
+    if end_time < start_time {
+        end_time = next_day(end_time);
+    }
+
+    // Remaining fields are hex numbers, the last one being the size
+    let fields = &parts[4..];
+    if !fields.iter().all(|f| !f.is_empty() && f.chars().all(|c| c.is_ascii_hexdigit())) {
+        return None;
+    }
+    let size = u64::from_str_radix(fields[fields.len() - 1], 16).ok()?;
+    let flags = decode_flags(fields[fields.len() - 2]);
+
+    Some(RecordingFileName { stream_type, version, uid, dst, start_time, end_time, flags, size })
+}
+
+fn number(s: &str) -> Option<u16> {
+    if s.is_empty() || !s.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    s.parse().ok()
+}
+
+fn time(year: u16, mon: u16, day: u16, hms: &str) -> Option<DateTime> {
+    if hms.len() != 6 || !hms.is_ascii() {
+        return None;
+    }
+    DateTime::new(year, mon as u8, day as u8, number(&hms[..2])? as u8, number(&hms[2..4])? as u8, number(&hms[4..])? as u8).ok()
+}
+
+fn next_day(mut date: DateTime) -> DateTime {
+    date.day += 1;
+    if date.day > days_in_month(date.year, date.mon) {
+        date.day = 1;
+        date.mon += 1;
+        if date.mon > 12 {
+            date.mon = 1;
+            date.year += 1;
+        }
+    }
+    date
+}
+
+/// Decodes the camera flags layout. Bits are numbered from the left of the hex string, and
+/// multi-bit fields have their least significant bit first. Longer strings on newer firmware
+/// add fields at the end.
+fn decode_flags(hex: &str) -> Option<RecordingFlags> {
+    if hex.len() < 7 {
+        return None;
+    }
+    let bits = hex.chars()
+        .flat_map(|c| {
+            let v = c.to_digit(16).unwrap_or(0);
+            (0..4).rev().map(move |i| (v >> i) & 1 == 1)
+        })
+        .collect::<Vec<_>>();
+    let field = |pos: usize, len: usize| (0..len).fold(0u8, |acc, i| acc | ((bits[pos + i] as u8) << i));
+
+    let triggers = [
+        (23, DetectionType::Timer),
+        (24, DetectionType::Motion),
+        (17, DetectionType::Person),
+        (18, DetectionType::Face),
+        (19, DetectionType::Vehicle),
+        (20, DetectionType::Animal),
+        (26, DetectionType::Visitor),
+    ];
+
+    Some(RecordingFlags {
+        frame_rate: field(8, 7),
+        triggers: triggers.into_iter().filter(|(bit, _)| bits[*bit]).map(|(_, t)| t).collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(name: &str) -> RecordingFileName {
+        name.parse().unwrap()
+    }
+
+    #[test]
+    fn test_camera() {
+        let name = parse("Mp4Record/2024-12-25/RecM02_20241225_081352_081441_6D28808_1C3F1A.mp4");
+        assert_eq!(StreamType::Main, name.stream_type);
+        assert_eq!(2, name.version);
+        assert_eq!(None, name.uid);
+        assert!(!name.dst);
+        assert_eq!(DateTime::new(2024, 12, 25, 8, 13, 52).unwrap(), name.start_time);
+        assert_eq!(DateTime::new(2024, 12, 25, 8, 14, 41).unwrap(), name.end_time);
+        assert_eq!(0x1C3F1A, name.size);
+        assert_eq!(Some(RecordingFlags { frame_rate: 20, triggers: vec![DetectionType::Motion] }), name.flags);
+
+        // Person detection
+        let name = parse("Mp4Record/2024-12-25/RecM02_20241225_081352_081441_6D28C08_1C3F1A.mp4");
+        assert!(name.has_trigger(&DetectionType::Person));
+        assert!(name.has_trigger(&DetectionType::Motion));
+        assert!(!name.has_trigger(&DetectionType::Vehicle));
+    }
+
+    #[test]
+    fn test_across_midnight() {
+        let name = parse("Mp4Record/2023-03-04/RecS01_20230304_235802_000302_2D28808_52A1C.mp4");
+        assert_eq!(StreamType::Sub, name.stream_type);
+        assert_eq!(DateTime::new(2023, 3, 5, 0, 3, 2).unwrap(), name.end_time);
+
+        let name = parse("RecM01_20241231_235900_000100_6D28808_100");
+        assert_eq!(DateTime::new(2025, 1, 1, 0, 1, 0).unwrap(), name.end_time);
+    }
+
+    #[test]
+    fn test_nvr() {
+        let name = parse("Mp4Record/2025-02-09/RecM01_20250209_000000_002959_6D28808_4E1A333.mp4");
+        assert_eq!(1, name.version);
+        assert_eq!(0x4E1A333, name.size);
+        assert!(name.has_trigger(&DetectionType::Motion));
+    }
+
+    #[test]
+    fn test_home_hub() {
+        let name = parse("/mnt/sda/Mp4Record/2024-12-25/RecM02_20241225_081352_081441_0_6D28808000_1C3F1A.mp4");
+        assert_eq!(None, name.uid);
+        assert_eq!(0x1C3F1A, name.size);
+        assert!(name.has_trigger(&DetectionType::Motion));
+
+        // Unknown flags layout
+        let name = parse("/mnt/sda/95270000ABCDEFGH/Mp4Record/2025-01-18/RecM02_DST20250118_140211_140231_0_800_4A0_1B6C52.mp4");
+        assert_eq!(Some("95270000ABCDEFGH".to_string()), name.uid);
+        assert!(name.dst);
+        assert_eq!(DateTime::new(2025, 1, 18, 14, 2, 31).unwrap(), name.end_time);
+        assert_eq!(None, name.flags);
+        assert!(!name.has_trigger(&DetectionType::Motion));
+    }
+
+    #[test]
+    fn test_invalid() {
+        for name in ["", "RecM01.mp4", "Mp4Record/2025-02-09/RecM01.mp4", "RecX01_20250209_000000_002959_6D28808_4E1A2B3",
+                     "RecM01_20250230_000000_002959_6D28808_4E1A2B3", "RecM01_20250209_000000_002959_6D28808_ZZ",
+                     "RecM01_202é209_000000_002959_6D28808_4E1A2B3", "RecM01_20250209_0é000_002959_6D28808_4E1A2B3"] {
+            let err = name.parse::<RecordingFileName>().unwrap_err();
+            assert_eq!(name, err.0);
+        }
+    }
+}
@@ -1,6 +1,7 @@
 use serde::{Deserialize, Serialize};
-use crate::api::{Channel, JsonEndpoint};
+use crate::api::{Channel, ConfigEndpoint, DeviceDuration, JsonEndpoint};
 use crate::api::record::ScheduleTable;
+use crate::api::record::set_recording::SetRecordingRequest;
 
 impl JsonEndpoint for GetRecordingRequest {
     const CMD: &'static str = "GetRec";
@@ -9,6 +10,32 @@ impl JsonEndpoint for GetRecordingRequest {
     type Range = GetRecordingRange;
 }
 
+impl ConfigEndpoint for GetRecordingRequest {
+    type Config = RecordingConfig;
+    type Set = SetRecordingRequest;
+
+    fn get_request(channel: Channel) -> Self {
+        GetRecordingRequest { channel }
+    }
+
+    fn config(response: GetRecordingResponse) -> RecordingConfig {
+        response.rec
+    }
+
+    fn initial_config(initial: GetRecordingResponse) -> RecordingConfig {
+        initial.rec
+    }
+
+    fn set_channel(config: &mut RecordingConfig, channel: Channel) {
+        config.channel = channel;
+    }
+
+    fn set_request(channel: Channel, mut config: RecordingConfig) -> SetRecordingRequest {
+        Self::set_channel(&mut config, channel);
+        SetRecordingRequest { rec: config }
+    }
+}
+
 /// Get the recording configuration. Note: This command supports models 52X only. When
 /// `scheduleVersion=1` in the capability set, use `get_recording_v20`.
 #[derive(Debug, Clone, Serialize)]
@@ -25,7 +52,7 @@ pub struct GetRecordingResponse {
     pub rec: RecordingConfig,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct RecordingConfig {
     /// Channel number
     pub channel: Channel,
@@ -37,12 +64,12 @@ pub struct RecordingConfig {
     /// Packaging cycle period as a string, e.g. "30 Minutes".
     /// Possible values are listed in `RecordingRange`.
     #[serde(rename = "packTime")]
-    pub pack_time: String, // NVR
+    pub pack_time: DeviceDuration, // NVR
 
     /// Post record time as a string, e.g. "1 Minute".
     /// Possible values are listed in `RecordingRange`.
     #[serde(rename = "postRec")]
-    pub post_rec: String,
+    pub post_rec: DeviceDuration,
 
     /// Enable pre record
     #[serde(rename = "preRec", with = "crate::serde::bool_as_number")]
@@ -51,9 +78,13 @@ pub struct RecordingConfig {
     /// Weekly scheduling table: 7 days * 24 hours. Each byte indicates whether it’s recording.
     /// With the value of 0 the recording is off, otherwise the recording is on.
     pub schedule: RecordingSchedule,
+
+    /// Fields not modeled above, kept so that they are sent back when updating the configuration.
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct RecordingSchedule {
     /// Is this schedule enabled?
     #[serde(with = "crate::serde::bool_as_number")]
@@ -75,10 +106,10 @@ pub struct RecordingRange {
     pub overwrite: String, // Constant string "boolean"
 
     #[serde(rename = "packTime")]
-    pub pack_time: Vec<String>, // NVR
+    pub pack_time: Vec<DeviceDuration>, // NVR
 
     #[serde(rename = "postRec")]
-    pub post_rec: Vec<String>,
+    pub post_rec: Vec<DeviceDuration>,
 
     #[serde(rename = "preRec")]
     pub pre_rec: String, // "boolean",
@@ -1,7 +1,9 @@
 use std::collections::HashMap;
 use serde::{Deserialize, Serialize};
-use crate::api::{Channel, JsonEndpoint};
-use crate::api::record::ScheduleTable;
+use crate::api::{Channel, ConfigEndpoint, DeviceDuration, JsonEndpoint};
+use crate::api::record::{DetectionType, ScheduleTable};
+use crate::api::record::schedule::{ScheduleError, WeeklySchedule};
+use crate::api::record::set_recording_v20::SetRecordingRequest;
 
 impl JsonEndpoint for GetRecordingRequest {
     const CMD: &'static str = "GetRecV20";
@@ -10,6 +12,32 @@ impl JsonEndpoint for GetRecordingRequest {
     type Range = GetRecordingRange;
 }
 
+impl ConfigEndpoint for GetRecordingRequest {
+    type Config = RecordingConfig;
+    type Set = SetRecordingRequest;
+
+    fn get_request(channel: Channel) -> Self {
+        GetRecordingRequest { channel }
+    }
+
+    fn config(response: GetRecordingResponse) -> RecordingConfig {
+        response.rec
+    }
+
+    fn initial_config(initial: GetRecordingResponse) -> RecordingConfig {
+        initial.rec
+    }
+
+    fn set_channel(config: &mut RecordingConfig, channel: Channel) {
+        config.schedule.channel = channel;
+    }
+
+    fn set_request(channel: Channel, mut config: RecordingConfig) -> SetRecordingRequest {
+        Self::set_channel(&mut config, channel);
+        SetRecordingRequest { rec: config }
+    }
+}
+
 /// Get the recording configuration.
 #[derive(Debug, Clone, Serialize)]
 pub struct GetRecordingRequest {
@@ -25,9 +53,8 @@ pub struct GetRecordingResponse {
     pub rec: RecordingConfig,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct RecordingConfig {
-    // Note: Home Hub also returns "scheduleEnable"
     #[serde(with = "crate::serde::bool_as_number")]
     pub enable: bool,
 
@@ -35,15 +62,15 @@ pub struct RecordingConfig {
     #[serde(with = "crate::serde::bool_as_number")]
     pub overwrite: bool,
 
-    /// Packaging cycle period as a string, e.g. "30 Minutes".
+    /// Packaging cycle period, e.g. "30 Minutes".
     /// Possible values are listed in `RecordingRange`.
-    #[serde(rename = "packTime")]
-    pub pack_time: Option<String>,
+    #[serde(rename = "packTime", skip_serializing_if = "Option::is_none")]
+    pub pack_time: Option<DeviceDuration>,
 
-    /// Post record time as a string, e.g. "1 Minute".
+    /// Post record time, e.g. "1 Minute".
     /// Possible values are listed in `RecordingRange`.
     #[serde(rename = "postRec")]
-    pub post_rec: String,
+    pub post_rec: DeviceDuration,
 
     /// Enable pre record
     #[serde(rename = "preRec", with = "crate::serde::bool_as_number")]
@@ -56,9 +83,14 @@ pub struct RecordingConfig {
     /// Weekly scheduling table: 7 days * 24 hours. Each byte indicates whether it’s recording.
     /// With the value of 0 the recording is off, otherwise the recording is on.
     pub schedule: RecordingSchedule,
+
+    /// Fields not modeled above, e.g. `scheduleEnable` on Home Hubs, kept so that they are
+    /// sent back when updating the configuration.
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct RecordingSchedule {
     pub channel: Channel,
 
@@ -67,7 +99,18 @@ pub struct RecordingSchedule {
     ///
     /// The map keys are the various detection methods, e.g. `AI_PEOPLE`, `AI_VEHICLE`, `MD`.
     #[serde(default)]
-    pub table: HashMap<String, ScheduleTable>,
+    pub table: HashMap<DetectionType, ScheduleTable>,
+}
+
+impl RecordingSchedule {
+    /// The weekly schedule of a detection type, e.g. `DetectionType::Timer` for continuous
+    /// recording. A missing table means that this detection type never records.
+    pub fn weekly_schedule(&self, detection_type: &DetectionType) -> Result<WeeklySchedule, ScheduleError> {
+        match self.table.get(detection_type) {
+            Some(table) => WeeklySchedule::try_from(table),
+            None => Ok(WeeklySchedule::new()),
+        }
+    }
 }
 
 //----- Range
@@ -83,9 +126,9 @@ pub struct RecordingRange {
     pub enable: String, // Constant string "boolean"
     pub overwrite: String, // Constant string "boolean"
     #[serde(rename = "packTime")]
-    pub pack_time: Option<Vec<String>>,
+    pub pack_time: Option<Vec<DeviceDuration>>,
     #[serde(rename = "postRec")]
-    pub post_rec: Vec<String>,
+    pub post_rec: Vec<DeviceDuration>,
     #[serde(rename = "preRec")]
     pub pre_rec: String, // Constant string "boolean",
     #[serde(rename = "schedule")]
@@ -96,5 +139,5 @@ pub struct RecordingRange {
 pub struct ScheduleRange {
     pub channel: Channel,
     /// Values are all "boolean"
-    pub table: Option<HashMap<String,String>>,
+    pub table: Option<HashMap<DetectionType,String>>,
 }
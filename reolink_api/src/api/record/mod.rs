@@ -2,21 +2,68 @@ use std::fmt::Formatter;
 use std::ops::Deref;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use serde::de::{Error, Unexpected};
+use crate::serde::string_enum;
 
+#[cfg(feature = "chrono")]
+pub mod calendar;
 pub mod download;
+pub mod file_name;
 pub mod get_recording;
 pub mod get_recording_v20;
 pub mod nvr_download;
+pub mod playback;
+pub mod schedule;
 pub mod search;
+pub mod set_recording;
+pub mod set_recording_v20;
 pub mod snapshot;
+#[cfg(feature = "chrono")]
+pub mod timeline;
 
-#[derive(Clone, Debug)]
-pub struct ScheduleTable(Vec<u8>);
+string_enum! {
+    /// A video stream of a channel.
+    pub enum StreamType {
+        /// Main stream, in high resolution
+        Main = "main",
+        /// Sub stream, in low resolution
+        Sub = "sub",
+        /// Extended stream, with a resolution between the main and sub streams
+        Ext = "ext",
+    }
+}
+
+string_enum! {
+    /// The kind of detection that triggers recordings, used as keys of schedule tables.
+    pub enum DetectionType {
+        /// Motion detection
+        Motion = "MD",
+        /// Continuous recording
+        Timer = "TIMING",
+        Person = "AI_PEOPLE",
+        Vehicle = "AI_VEHICLE",
+        /// Pets
+        Animal = "AI_DOG_CAT",
+        Face = "AI_FACE",
+        Package = "AI_PACKAGE",
+        /// Doorbell button press
+        Visitor = "VISITOR",
+    }
+}
 
 /// A schedule table, with a single-digit value for every period in the table.
 /// They're used mostly for boolean values, for which the docs states that "0" means "disabled".
 /// We still use an `u8` for each entry, in case non-zero values are more than just "enabled"
 /// (the doc shows examples with `2` without explaining how it's different from `1`).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ScheduleTable(Vec<u8>);
+
+impl ScheduleTable {
+    /// Creates a table from the value of each period.
+    pub fn new(values: Vec<u8>) -> Self {
+        ScheduleTable(values)
+    }
+}
+
 impl Deref for ScheduleTable {
     type Target = Vec<u8>;
     fn deref(&self) -> &Self::Target { &self.0 }
@@ -35,7 +82,7 @@ impl <'de> Deserialize<'de> for ScheduleTable {
             fn visit_str<E: Error>(self, v: &str) -> Result<Self::Value, E> {
                 let vec = v.as_bytes().iter()
                     .map(|b| {
-                        if (b'0'..= b'9').contains(b) {
+                        if b.is_ascii_digit() {
                             Ok(b - b'0')
                         } else {
                             Err(Error::invalid_value(Unexpected::Str(v), &self))
@@ -78,6 +125,20 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_string_enums() -> anyhow::Result<()> {
+        assert_eq!(r#""sub""#, serde_json::to_string(&StreamType::Sub)?);
+        assert_eq!(StreamType::Main, serde_json::from_str(r#""main""#)?);
+        assert_eq!(StreamType::Other("autoplay".to_string()), serde_json::from_str(r#""autoplay""#)?);
+        assert_eq!(r#""autoplay""#, serde_json::to_string(&StreamType::Other("autoplay".to_string()))?);
+
+        // Used as map keys
+        let table = serde_json::from_str::<std::collections::HashMap<DetectionType, ScheduleTable>>(r#"{"MD":"01","AI_BIRD":"10"}"#)?;
+        assert_eq!(&[0, 1], table[&DetectionType::Motion].as_slice());
+        assert_eq!(&[1, 0], table[&DetectionType::Other("AI_BIRD".to_string())].as_slice());
+        Ok(())
+    }
+
     #[test]
     fn test_ser_schedule_table() -> anyhow::Result<()> {
         let table = ScheduleTable(vec![0, 1, 0, 2]);
@@ -90,7 +151,10 @@ mod tests {
 //-------------------------------------------------------------------------------------------------
 
 /// Date and time, expressed as a local date-time in the device's time zone.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+///
+/// Values returned by devices aren't checked, and may not be a valid date-time. Conversions
+/// to the date-time types of other crates are therefore fallible.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct DateTime {
     pub year: u16,
     pub mon: u8,
@@ -100,47 +164,113 @@ pub struct DateTime {
     pub sec: u8,
 }
 
+impl DateTime {
+    /// Creates a date-time, checking that it is valid.
+    pub fn new(year: u16, mon: u8, day: u8, hour: u8, min: u8, sec: u8) -> Result<Self, InvalidDateTime> {
+        let value = DateTime { year, mon, day, hour, min, sec };
+        if value.is_valid() {
+            Ok(value)
+        } else {
+            Err(InvalidDateTime(value))
+        }
+    }
+
+    /// Is this a valid date-time? Leap seconds aren't accepted.
+    pub fn is_valid(&self) -> bool {
+        (1..=12).contains(&self.mon) &&
+            self.day >= 1 && self.day <= days_in_month(self.year, self.mon) &&
+            self.hour < 24 && self.min < 60 && self.sec < 60
+    }
+}
+
+fn days_in_month(year: u16, mon: u8) -> u8 {
+    match mon {
+        2 if year.is_multiple_of(4) && (!year.is_multiple_of(100) || year.is_multiple_of(400)) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+impl std::fmt::Display for DateTime {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:04}-{:02}-{:02} {:02}:{:02}:{:02}", self.year, self.mon, self.day, self.hour, self.min, self.sec)
+    }
+}
+
+/// Error returned when converting a `DateTime` that isn't a valid date-time.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InvalidDateTime(pub DateTime);
+
+impl std::fmt::Display for InvalidDateTime {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "invalid date-time {}", self.0)
+    }
+}
+
+impl std::error::Error for InvalidDateTime {}
+
+/// Error returned when converting a date-time whose year isn't in the `0..=65535` range of
+/// `DateTime`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct YearOutOfRange(pub i32);
+
+impl std::fmt::Display for YearOutOfRange {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "year {} is out of range", self.0)
+    }
+}
+
+impl std::error::Error for YearOutOfRange {}
+
 #[cfg(feature = "chrono")]
 mod chrono_impl {
     use chrono::{Datelike, NaiveDate, NaiveDateTime, NaiveTime, Timelike};
-    use crate::api::record::DateTime;
+    use crate::api::record::{DateTime, InvalidDateTime, YearOutOfRange};
+
+    impl TryFrom<NaiveDateTime> for DateTime {
+        type Error = YearOutOfRange;
 
-    impl From<NaiveDateTime> for DateTime {
-        fn from(value: NaiveDateTime) -> Self {
-            DateTime {
-                year: value.year() as u16,
+        fn try_from(value: NaiveDateTime) -> Result<Self, Self::Error> {
+            Ok(DateTime {
+                year: u16::try_from(value.year()).map_err(|_| YearOutOfRange(value.year()))?,
                 mon: value.month() as u8,
                 day: value.day() as u8,
                 hour: value.hour() as u8,
                 min: value.minute() as u8,
                 sec: value.second() as u8,
+            })
+        }
+    }
+
+    impl TryFrom<&DateTime> for NaiveDateTime {
+        type Error = InvalidDateTime;
+
+        fn try_from(value: &DateTime) -> Result<Self, Self::Error> {
+            let day = NaiveDate::from_ymd_opt(value.year as i32, value.mon as u32, value.day as u32);
+            let time = NaiveTime::from_hms_opt(value.hour as u32, value.min as u32, value.sec as u32);
+            match (day, time) {
+                (Some(day), Some(time)) => Ok(NaiveDateTime::new(day, time)),
+                _ => Err(InvalidDateTime(value.clone())),
             }
         }
     }
 
-    impl From<DateTime> for NaiveDateTime {
-        fn from(value: DateTime) -> Self {
-            let day = NaiveDate::from_ymd_opt(value.year as i32, value.mon as u32, value.day as u32).expect("Invalid date");
-            let time = NaiveTime::from_hms_opt(value.hour as u32, value.min as u32, value.sec as u32).expect("Invalid time");
-            NaiveDateTime::new(day, time)
+    impl TryFrom<DateTime> for NaiveDateTime {
+        type Error = InvalidDateTime;
+
+        fn try_from(value: DateTime) -> Result<Self, Self::Error> {
+            NaiveDateTime::try_from(&value)
         }
     }
 
     #[cfg(test)]
     mod tests {
-        //use crate::api::record::ScheduleTable;
         use super::*;
 
         #[test]
-        fn test_into() {
-            let ndt: NaiveDateTime = DateTime {
-                year: 2024,
-                mon: 12,
-                day: 25,
-                hour: 1,
-                min: 2,
-                sec: 3,
-            }.into();
+        fn test_into() -> anyhow::Result<()> {
+            let ndt: NaiveDateTime = DateTime::new(2024, 12, 25, 1, 2, 3)?.try_into()?;
 
             assert_eq!(2024, ndt.year());
             assert_eq!(12, ndt.month());
@@ -148,12 +278,20 @@ mod chrono_impl {
             assert_eq!(1, ndt.hour());
             assert_eq!(2, ndt.minute());
             assert_eq!(3, ndt.second());
+            Ok(())
+        }
+
+        #[test]
+        fn test_invalid() {
+            let bogus = DateTime { year: 2023, mon: 2, day: 29, hour: 0, min: 0, sec: 0 };
+            let err = NaiveDateTime::try_from(&bogus).unwrap_err();
+            assert_eq!("invalid date-time 2023-02-29 00:00:00", err.to_string());
         }
 
         #[test]
-        fn test_from() {
+        fn test_from() -> anyhow::Result<()> {
             let ndt = NaiveDate::from_ymd_opt(2024, 12, 25).unwrap().and_hms_opt(1, 2, 3).unwrap();
-            let t: DateTime = ndt.into();
+            let t: DateTime = ndt.try_into()?;
 
             assert_eq!(2024, t.year);
             assert_eq!(12, t.mon);
@@ -161,6 +299,145 @@ mod chrono_impl {
             assert_eq!(1, t.hour);
             assert_eq!(2, t.min);
             assert_eq!(3, t.sec);
+
+            let ndt = NaiveDate::from_ymd_opt(-1, 1, 1).unwrap().and_time(NaiveTime::MIN);
+            assert_eq!(YearOutOfRange(-1), DateTime::try_from(ndt).unwrap_err());
+            let ndt = NaiveDate::from_ymd_opt(65536, 1, 1).unwrap().and_time(NaiveTime::MIN);
+            assert_eq!("year 65536 is out of range", DateTime::try_from(ndt).unwrap_err().to_string());
+            Ok(())
         }
     }
 }
+
+#[cfg(feature = "time")]
+mod time_impl {
+    use time::{Date, Month, PrimitiveDateTime, Time};
+    use crate::api::record::{DateTime, InvalidDateTime, YearOutOfRange};
+
+    impl TryFrom<PrimitiveDateTime> for DateTime {
+        type Error = YearOutOfRange;
+
+        fn try_from(value: PrimitiveDateTime) -> Result<Self, Self::Error> {
+            Ok(DateTime {
+                year: u16::try_from(value.year()).map_err(|_| YearOutOfRange(value.year()))?,
+                mon: value.month() as u8,
+                day: value.day(),
+                hour: value.hour(),
+                min: value.minute(),
+                sec: value.second(),
+            })
+        }
+    }
+
+    impl TryFrom<&DateTime> for PrimitiveDateTime {
+        type Error = InvalidDateTime;
+
+        fn try_from(value: &DateTime) -> Result<Self, Self::Error> {
+            let err = || InvalidDateTime(value.clone());
+            let month = Month::try_from(value.mon).map_err(|_| err())?;
+            let date = Date::from_calendar_date(value.year as i32, month, value.day).map_err(|_| err())?;
+            let time = Time::from_hms(value.hour, value.min, value.sec).map_err(|_| err())?;
+            Ok(PrimitiveDateTime::new(date, time))
+        }
+    }
+
+    impl TryFrom<DateTime> for PrimitiveDateTime {
+        type Error = InvalidDateTime;
+
+        fn try_from(value: DateTime) -> Result<Self, Self::Error> {
+            PrimitiveDateTime::try_from(&value)
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn test_round_trip() -> anyhow::Result<()> {
+            let t = DateTime::new(2024, 2, 29, 23, 59, 59)?;
+            let pdt = PrimitiveDateTime::try_from(&t)?;
+            assert_eq!(Month::February, pdt.month());
+            assert_eq!(t, DateTime::try_from(pdt)?);
+
+            assert!(PrimitiveDateTime::try_from(DateTime { mon: 13, ..t }).is_err());
+            assert_eq!(YearOutOfRange(-1), DateTime::try_from(PrimitiveDateTime::new(Date::from_calendar_date(-1, Month::January, 1)?, Time::MIDNIGHT)).unwrap_err());
+            Ok(())
+        }
+    }
+}
+
+#[cfg(feature = "jiff")]
+mod jiff_impl {
+    use crate::api::record::{DateTime, InvalidDateTime, YearOutOfRange};
+
+    impl TryFrom<jiff::civil::DateTime> for DateTime {
+        type Error = YearOutOfRange;
+
+        fn try_from(value: jiff::civil::DateTime) -> Result<Self, Self::Error> {
+            Ok(DateTime {
+                year: u16::try_from(value.year()).map_err(|_| YearOutOfRange(value.year() as i32))?,
+                mon: value.month() as u8,
+                day: value.day() as u8,
+                hour: value.hour() as u8,
+                min: value.minute() as u8,
+                sec: value.second() as u8,
+            })
+        }
+    }
+
+    impl TryFrom<&DateTime> for jiff::civil::DateTime {
+        type Error = InvalidDateTime;
+
+        fn try_from(value: &DateTime) -> Result<Self, Self::Error> {
+            let year = i16::try_from(value.year).map_err(|_| InvalidDateTime(value.clone()))?;
+            jiff::civil::DateTime::new(
+                year, value.mon as i8, value.day as i8,
+                value.hour as i8, value.min as i8, value.sec as i8, 0
+            ).map_err(|_| InvalidDateTime(value.clone()))
+        }
+    }
+
+    impl TryFrom<DateTime> for jiff::civil::DateTime {
+        type Error = InvalidDateTime;
+
+        fn try_from(value: DateTime) -> Result<Self, Self::Error> {
+            jiff::civil::DateTime::try_from(&value)
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn test_round_trip() -> anyhow::Result<()> {
+            let t = DateTime::new(2024, 2, 29, 23, 59, 59)?;
+            let jdt = jiff::civil::DateTime::try_from(&t)?;
+            assert_eq!(29, jdt.day());
+            assert_eq!(t, DateTime::try_from(jdt)?);
+
+            assert!(jiff::civil::DateTime::try_from(DateTime { hour: 24, ..t }).is_err());
+            assert!(jiff::civil::DateTime::try_from(DateTime { year: 65535, ..t }).is_err());
+            assert_eq!(YearOutOfRange(-1), DateTime::try_from(jiff::civil::date(-1, 1, 1).at(0, 0, 0, 0)).unwrap_err());
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod date_time_tests {
+    use super::*;
+
+    #[test]
+    fn test_new() {
+        assert!(DateTime::new(2024, 2, 29, 23, 59, 59).is_ok());
+        assert!(DateTime::new(2000, 2, 29, 0, 0, 0).is_ok());
+        assert!(DateTime::new(1900, 2, 29, 0, 0, 0).is_err());
+        assert!(DateTime::new(2024, 4, 31, 0, 0, 0).is_err());
+        assert!(DateTime::new(2024, 0, 1, 0, 0, 0).is_err());
+        assert!(DateTime::new(2024, 1, 0, 0, 0, 0).is_err());
+        assert!(DateTime::new(2024, 1, 1, 24, 0, 0).is_err());
+        assert!(DateTime::new(2024, 1, 1, 0, 0, 60).is_err());
+    }
+}
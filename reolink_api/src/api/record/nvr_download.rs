@@ -1,6 +1,6 @@
 use serde::{Deserialize, Serialize};
 use crate::api::{Channel, JsonEndpoint, NotApplicable};
-use crate::api::record::DateTime;
+use crate::api::record::{DateTime, StreamType};
 
 impl JsonEndpoint for NvrDownloadRequest {
     const CMD: &'static str = "NvrDownload";
@@ -20,7 +20,7 @@ pub struct NvrDownload {
     pub channel: Channel,
     /// The bitstream type of the file to download, `"main"` or `"sub"`.
     #[serde(rename = "streamType")]
-    pub stream_type: String,
+    pub stream_type: StreamType,
     #[serde(rename = "StartTime")]
     pub start_time: DateTime,
     #[serde(rename = "EndTime")]
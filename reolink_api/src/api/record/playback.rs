@@ -0,0 +1,87 @@
+use serde::{Serialize, Serializer};
+use crate::api::{AuthenticationType, BinaryEndpoint, Channel};
+use crate::api::record::{DateTime, StreamType};
+
+impl BinaryEndpoint for PlaybackRequest {
+    const CMD: &'static str = "Playback";
+    const AUTH: AuthenticationType = AuthenticationType::Token;
+}
+
+/// Play back a recording file, as a FLV stream. Use `ReolinkClient::stream` to read the response
+/// as it arrives.
+#[derive(Debug, Serialize)]
+pub struct PlaybackRequest {
+    pub channel: Channel,
+
+    /// Name of the recording file, as returned by `Search`
+    pub source: String,
+
+    /// File storage name, will be returned as the `Content-Disposition: attachment;filename=` header
+    pub output: Option<String>,
+
+    /// Start time of the recording file
+    #[serde(serialize_with = "serialize_compact")]
+    pub start: DateTime,
+
+    /// Stream of the recording, sent as a number (0 for main, 1 for sub). Other streams can't be
+    /// played back and fail to serialize.
+    #[serde(rename = "type", serialize_with = "serialize_stream_type")]
+    pub stream_type: StreamType,
+
+    /// Offset from `start` where playback begins, in seconds
+    pub seek: u32,
+}
+
+impl PlaybackRequest {
+    /// Plays a file found with `Search` from its beginning.
+    pub fn new(channel: Channel, file: &crate::api::record::search::SearchFile) -> Self {
+        PlaybackRequest {
+            channel,
+            source: file.name.clone(),
+            output: None,
+            start: file.start_time.clone(),
+            stream_type: file.stream_type.clone(),
+            seek: 0,
+        }
+    }
+}
+
+/// Date-times are sent as `YYYYMMDDhhmmss`.
+fn serialize_compact<S: Serializer>(dt: &DateTime, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(&format_args!(
+        "{:04}{:02}{:02}{:02}{:02}{:02}", dt.year, dt.mon, dt.day, dt.hour, dt.min, dt.sec
+    ))
+}
+
+fn serialize_stream_type<S: Serializer>(stream_type: &StreamType, serializer: S) -> Result<S::Ok, S::Error> {
+    match stream_type {
+        StreamType::Main => serializer.serialize_u8(0),
+        StreamType::Sub => serializer.serialize_u8(1),
+        other => Err(serde::ser::Error::custom(format_args!("stream type '{}' can't be played back", other))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_serialize() -> anyhow::Result<()> {
+        let request = PlaybackRequest {
+            channel: 1,
+            source: "RecS01_20250209_100000_100500_6D28808_100.mp4".to_string(),
+            output: None,
+            start: DateTime::new(2025, 2, 9, 10, 0, 0)?,
+            stream_type: StreamType::Sub,
+            seek: 90,
+        };
+        let json = serde_json::to_value(&request)?;
+        assert_eq!("20250209100000", json["start"]);
+        assert_eq!(1, json["type"]);
+        assert_eq!(90, json["seek"]);
+
+        let request = PlaybackRequest { stream_type: StreamType::Ext, ..request };
+        assert!(serde_json::to_value(&request).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,360 @@
+//! Weekly schedules, stored by devices as a `ScheduleTable` of 7×24 hourly slots starting on
+//! Sunday at midnight.
+//!
+//! A schedule also has a compact text form, used by `Display`, `FromStr` and serde: days are
+//! separated by `;`, and each day lists its enabled hours or inclusive hour ranges, with an
+//! optional value if it isn't `1`. Days with the same hours can be grouped. For example
+//! `Sun,Sat:0-23;Mon-Fri:8-17,20=2`.
+
+use std::fmt::{Display, Formatter};
+use std::ops::RangeBounds;
+use std::str::FromStr;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use crate::api::record::ScheduleTable;
+
+/// A day of the week, in the order of schedule tables.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Weekday {
+    Sunday,
+    Monday,
+    Tuesday,
+    Wednesday,
+    Thursday,
+    Friday,
+    Saturday,
+}
+
+impl Weekday {
+    pub const ALL: [Weekday; 7] = [
+        Weekday::Sunday, Weekday::Monday, Weekday::Tuesday, Weekday::Wednesday,
+        Weekday::Thursday, Weekday::Friday, Weekday::Saturday,
+    ];
+
+    /// Days from Monday to Friday
+    pub const WORKDAYS: [Weekday; 5] = [
+        Weekday::Monday, Weekday::Tuesday, Weekday::Wednesday, Weekday::Thursday, Weekday::Friday,
+    ];
+
+    /// Position of this day in the week, starting at 0 for Sunday.
+    pub fn index(self) -> usize {
+        self as usize
+    }
+
+    /// Three letter English name, e.g. `Mon`.
+    pub fn short_name(self) -> &'static str {
+        ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"][self.index()]
+    }
+
+    fn from_short_name(name: &str) -> Option<Weekday> {
+        Weekday::ALL.into_iter().find(|d| d.short_name().eq_ignore_ascii_case(name))
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl From<chrono::Weekday> for Weekday {
+    fn from(value: chrono::Weekday) -> Self {
+        Weekday::ALL[value.num_days_from_sunday() as usize]
+    }
+}
+
+/// A weekly schedule, with a value for every hour of the week. Values are usually `0` for
+/// disabled and `1` for enabled, and at most `MAX_VALUE` since tables store them as digits.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WeeklySchedule {
+    slots: [[u8; 24]; 7],
+}
+
+/// Error returned when a schedule table or text can't be converted to a `WeeklySchedule`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ScheduleError {
+    /// The schedule table doesn't have 7×24 entries
+    Length(usize),
+    /// Invalid text form
+    Syntax(String),
+    /// A slot value greater than `WeeklySchedule::MAX_VALUE`
+    Value(u8),
+}
+
+impl Display for ScheduleError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ScheduleError::Length(len) => write!(f, "a weekly schedule table has 168 entries, found {}", len),
+            ScheduleError::Syntax(text) => write!(f, "invalid weekly schedule '{}'", text),
+            ScheduleError::Value(value) => write!(f, "invalid schedule value {}, the maximum is {}", value, WeeklySchedule::MAX_VALUE),
+        }
+    }
+}
+
+impl std::error::Error for ScheduleError {}
+
+impl WeeklySchedule {
+    /// Number of entries in a weekly schedule table.
+    pub const LEN: usize = 7 * 24;
+
+    /// Maximum value of a slot.
+    pub const MAX_VALUE: u8 = 9;
+
+    /// A schedule where all slots are disabled.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A schedule where all slots are enabled.
+    pub fn always() -> Self {
+        WeeklySchedule { slots: [[1; 24]; 7] }
+    }
+
+    /// The value of a slot. Panics if `hour` isn't less than 24.
+    pub fn get(&self, weekday: Weekday, hour: u8) -> u8 {
+        self.slots[weekday.index()][hour as usize]
+    }
+
+    pub fn is_enabled(&self, weekday: Weekday, hour: u8) -> bool {
+        self.get(weekday, hour) != 0
+    }
+
+    /// Sets the value of a slot. Fails if `value` is greater than `MAX_VALUE`, and panics if
+    /// `hour` isn't less than 24.
+    pub fn set(&mut self, weekday: Weekday, hour: u8, value: u8) -> Result<(), ScheduleError> {
+        if value > Self::MAX_VALUE {
+            return Err(ScheduleError::Value(value));
+        }
+        self.slots[weekday.index()][hour as usize] = value;
+        Ok(())
+    }
+
+    /// Sets the value of a range of hours, on some days of the week.
+    ///
+    /// Fails if `value` is greater than `MAX_VALUE`.
+    ///
+    /// Example: `schedule.set_range(Weekday::WORKDAYS, 8..18, 1)?`
+    pub fn set_range(&mut self, weekdays: impl IntoIterator<Item = Weekday>, hours: impl RangeBounds<u8>, value: u8) -> Result<(), ScheduleError> {
+        if value > Self::MAX_VALUE {
+            return Err(ScheduleError::Value(value));
+        }
+        for weekday in weekdays {
+            for hour in (0..24).filter(|h| hours.contains(h)) {
+                self.slots[weekday.index()][hour as usize] = value;
+            }
+        }
+        Ok(())
+    }
+
+    /// Iterates over all slots, in table order.
+    pub fn iter(&self) -> impl Iterator<Item = (Weekday, u8, u8)> + '_ {
+        Weekday::ALL.into_iter()
+            .flat_map(move |day| (0..24).map(move |hour| (day, hour, self.get(day, hour))))
+    }
+
+    /// Iterates over slots that have a non-zero value.
+    pub fn enabled(&self) -> impl Iterator<Item = (Weekday, u8, u8)> + '_ {
+        self.iter().filter(|(_, _, value)| *value != 0)
+    }
+
+    /// Text form of a day's slots: hour ranges with their value.
+    fn day_text(&self, weekday: Weekday) -> String {
+        let hours = &self.slots[weekday.index()];
+        let mut ranges = Vec::new();
+        let mut hour = 0;
+        while hour < 24 {
+            let value = hours[hour];
+            let start = hour;
+            while hour < 24 && hours[hour] == value {
+                hour += 1;
+            }
+            if value != 0 {
+                let mut range = if hour - 1 == start { start.to_string() } else { format!("{}-{}", start, hour - 1) };
+                if value != 1 {
+                    range.push_str(&format!("={}", value));
+                }
+                ranges.push(range);
+            }
+        }
+        ranges.join(",")
+    }
+}
+
+impl TryFrom<&ScheduleTable> for WeeklySchedule {
+    type Error = ScheduleError;
+
+    fn try_from(value: &ScheduleTable) -> Result<Self, Self::Error> {
+        if value.len() != Self::LEN {
+            return Err(ScheduleError::Length(value.len()));
+        }
+        let mut schedule = WeeklySchedule::new();
+        for (i, v) in value.iter().enumerate() {
+            schedule.slots[i / 24][i % 24] = *v;
+        }
+        Ok(schedule)
+    }
+}
+
+impl From<&WeeklySchedule> for ScheduleTable {
+    fn from(value: &WeeklySchedule) -> Self {
+        ScheduleTable::new(value.slots.iter().flatten().copied().collect())
+    }
+}
+
+impl From<WeeklySchedule> for ScheduleTable {
+    fn from(value: WeeklySchedule) -> Self {
+        ScheduleTable::from(&value)
+    }
+}
+
+impl Display for WeeklySchedule {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        // Group days that have the same hours
+        let mut groups: Vec<(Vec<Weekday>, String)> = Vec::new();
+        for day in Weekday::ALL {
+            let text = self.day_text(day);
+            if text.is_empty() {
+                continue;
+            }
+            match groups.iter_mut().find(|(_, t)| *t == text) {
+                Some((days, _)) => days.push(day),
+                None => groups.push((vec![day], text)),
+            }
+        }
+
+        let groups = groups.iter().map(|(days, text)| {
+            // Consecutive days are written as a range
+            let days = if days.len() > 2 && days.windows(2).all(|w| w[1].index() == w[0].index() + 1) {
+                format!("{}-{}", days[0].short_name(), days[days.len() - 1].short_name())
+            } else {
+                days.iter().map(|d| d.short_name()).collect::<Vec<_>>().join(",")
+            };
+            format!("{}:{}", days, text)
+        }).collect::<Vec<_>>();
+
+        f.write_str(&groups.join(";"))
+    }
+}
+
+impl FromStr for WeeklySchedule {
+    type Err = ScheduleError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || ScheduleError::Syntax(s.to_string());
+        let mut schedule = WeeklySchedule::new();
+
+        for group in s.split(';').map(str::trim).filter(|g| !g.is_empty()) {
+            let (days, hours) = group.split_once(':').ok_or_else(err)?;
+
+            let mut weekdays = Vec::new();
+            for days in days.split(',').map(str::trim) {
+                let (first, last) = days.split_once('-').unwrap_or((days, days));
+                let first = Weekday::from_short_name(first.trim()).ok_or_else(err)?;
+                let last = Weekday::from_short_name(last.trim()).ok_or_else(err)?;
+                if last < first {
+                    return Err(err());
+                }
+                weekdays.extend(&Weekday::ALL[first.index()..=last.index()]);
+            }
+
+            for range in hours.split(',').map(str::trim) {
+                let (range, value) = range.split_once('=').unwrap_or((range, "1"));
+                let value = value.trim().parse::<u8>().ok().filter(|v| *v <= Self::MAX_VALUE).ok_or_else(err)?;
+                let (first, last) = range.split_once('-').unwrap_or((range, range));
+                let first = first.trim().parse::<u8>().map_err(|_| err())?;
+                let last = last.trim().parse::<u8>().map_err(|_| err())?;
+                if first > last || last > 23 {
+                    return Err(err());
+                }
+                schedule.set_range(weekdays.iter().copied(), first..=last, value)?;
+            }
+        }
+        Ok(schedule)
+    }
+}
+
+impl Serialize for WeeklySchedule {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl <'de> Deserialize<'de> for WeeklySchedule {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        text.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_set() -> anyhow::Result<()> {
+        let mut schedule = WeeklySchedule::new();
+        schedule.set_range(Weekday::WORKDAYS, 8..18, 1)?;
+        schedule.set(Weekday::Sunday, 23, 2)?;
+
+        assert!(schedule.is_enabled(Weekday::Monday, 8));
+        assert!(schedule.is_enabled(Weekday::Friday, 17));
+        assert!(!schedule.is_enabled(Weekday::Friday, 18));
+        assert!(!schedule.is_enabled(Weekday::Saturday, 10));
+        assert_eq!(2, schedule.get(Weekday::Sunday, 23));
+
+        assert_eq!(5 * 10 + 1, schedule.enabled().count());
+        assert_eq!(Some((Weekday::Sunday, 23, 2)), schedule.enabled().next());
+        assert_eq!(WeeklySchedule::LEN, schedule.iter().count());
+
+        // Values are stored as digits in tables
+        assert_eq!(ScheduleError::Value(10), schedule.set(Weekday::Monday, 8, 10).unwrap_err());
+        assert_eq!(ScheduleError::Value(208), schedule.set_range(Weekday::ALL, .., 208).unwrap_err());
+        assert_eq!(1, schedule.get(Weekday::Monday, 8));
+        Ok(())
+    }
+
+    #[test]
+    fn test_table() -> anyhow::Result<()> {
+        let mut schedule = WeeklySchedule::new();
+        schedule.set(Weekday::Monday, 1, 1)?;
+        let table = ScheduleTable::from(&schedule);
+        assert_eq!(168, table.len());
+        assert_eq!(1, table[24 + 1]);
+        assert_eq!(1, table.iter().filter(|v| **v != 0).count());
+
+        assert_eq!(schedule, WeeklySchedule::try_from(&table)?);
+
+        // Search status tables have a value per day of the month
+        let table = ScheduleTable::new(vec![0; 31]);
+        assert_eq!(ScheduleError::Length(31), WeeklySchedule::try_from(&table).unwrap_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_text() -> anyhow::Result<()> {
+        let mut schedule = WeeklySchedule::new();
+        assert_eq!("", schedule.to_string());
+
+        schedule.set_range([Weekday::Sunday, Weekday::Saturday], .., 1)?;
+        schedule.set_range(Weekday::WORKDAYS, 8..=17, 1)?;
+        schedule.set(Weekday::Monday, 20, 2)?;
+        schedule.set(Weekday::Wednesday, 20, 2)?;
+        let text = schedule.to_string();
+        assert_eq!("Sun,Sat:0-23;Mon,Wed:8-17,20=2;Tue,Thu,Fri:8-17", text);
+
+        assert_eq!(schedule, text.parse()?);
+        assert_eq!(WeeklySchedule::always(), "Sun-Sat:0-23".parse()?);
+        assert_eq!(WeeklySchedule::always().to_string(), "Sun-Sat:0-23");
+
+        let parsed: WeeklySchedule = " mon-fri : 8-17 ; sun:3 ".parse()?;
+        assert_eq!(5 * 10 + 1, parsed.enabled().count());
+
+        for bad in ["Mon", "Mon:24", "Fri-Mon:1", "Mon:5-3", "Xyz:1", "Mon:1=10"] {
+            assert!(bad.parse::<WeeklySchedule>().is_err(), "{}", bad);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_serde() -> anyhow::Result<()> {
+        let schedule: WeeklySchedule = "Mon-Fri:8-17".parse()?;
+        let json = serde_json::to_string(&schedule)?;
+        assert_eq!(r#""Mon-Fri:8-17""#, json);
+        assert_eq!(schedule, serde_json::from_str(&json)?);
+        Ok(())
+    }
+}
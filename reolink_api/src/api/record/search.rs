@@ -1,7 +1,7 @@
 use serde::{Deserialize, Serialize};
 use crate::api::{Channel, NotApplicable};
 use crate::api::JsonEndpoint;
-use crate::api::record::{DateTime, ScheduleTable};
+use crate::api::record::{DateTime, ScheduleTable, StreamType};
 
 impl JsonEndpoint for SearchRequest {
     const CMD: &'static str = "Search";
@@ -30,7 +30,7 @@ pub struct Search {
     /// The stream type of the recordings, `main` is for searching main stream,
     /// otherwise is for searching sub stream.
     #[serde(rename = "streamType")]
-    pub stream_type: String,
+    pub stream_type: StreamType,
 
     #[serde(rename = "StartTime")]
     pub start_time: DateTime,
@@ -70,7 +70,7 @@ pub struct SearchStatus {
 #[derive(Debug, Clone, Deserialize)]
 pub struct SearchFile {
     #[serde(rename = "type")]
-    pub stream_type: String,
+    pub stream_type: StreamType,
     #[serde(rename = "StartTime")]
     pub start_time: DateTime,
     #[serde(rename = "EndTime")]
@@ -83,3 +83,51 @@ pub struct SearchFile {
     pub size: usize,
     pub name: String,
 }
+
+impl SearchStatus {
+    /// Does this month have recordings on `day` (starting at 1)?
+    pub fn has_recordings(&self, day: u8) -> bool {
+        day >= 1 && self.table.get(day as usize - 1).is_some_and(|v| *v != 0)
+    }
+}
+
+/// Splits a time range in ranges that are each within a single day, since the file list is
+/// only returned by `Search` for such ranges.
+#[cfg(feature = "chrono")]
+pub fn day_intervals(start: chrono::NaiveDateTime, end: chrono::NaiveDateTime) -> Vec<(chrono::NaiveDateTime, chrono::NaiveDateTime)> {
+    use chrono::{NaiveTime, TimeDelta};
+
+    let mut result = Vec::new();
+    let mut start = start;
+    while start <= end {
+        let next_day = start.date().and_time(NaiveTime::MIN) + TimeDelta::days(1);
+        let day_end = next_day - TimeDelta::seconds(1);
+        result.push((start, day_end.min(end)));
+        start = next_day;
+    }
+    result
+}
+
+#[cfg(all(test, feature = "chrono"))]
+mod tests {
+    use super::*;
+    use chrono::NaiveDateTime;
+
+    fn parse(s: &str) -> NaiveDateTime {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_day_intervals() {
+        let intervals = day_intervals(parse("2025-02-09T10:21:32"), parse("2025-02-11T08:00:00"));
+        let intervals = intervals.iter().map(|(s, e)| (s.to_string(), e.to_string())).collect::<Vec<_>>();
+        assert_eq!(vec![
+            ("2025-02-09 10:21:32".to_string(), "2025-02-09 23:59:59".to_string()),
+            ("2025-02-10 00:00:00".to_string(), "2025-02-10 23:59:59".to_string()),
+            ("2025-02-11 00:00:00".to_string(), "2025-02-11 08:00:00".to_string()),
+        ], intervals);
+
+        assert_eq!(1, day_intervals(parse("2025-02-09T10:00:00"), parse("2025-02-09T11:00:00")).len());
+        assert!(day_intervals(parse("2025-02-09T10:00:00"), parse("2025-02-09T09:00:00")).is_empty());
+    }
+}
@@ -0,0 +1,33 @@
+use serde::Serialize;
+use crate::api::{NotApplicable, SimpleResult, Validate, ValidationError};
+use crate::api::JsonEndpoint;
+use crate::api::record::get_recording::{GetRecordingRange, GetRecordingRequest, RecordingConfig};
+
+impl JsonEndpoint for SetRecordingRequest {
+    const CMD: &'static str = "SetRec";
+    type Response = SimpleResult;
+    type Initial = NotApplicable;
+    type Range = NotApplicable;
+}
+
+impl Validate for SetRecordingRequest {
+    type RangeEndpoint = GetRecordingRequest;
+
+    fn range_request(&self) -> GetRecordingRequest {
+        GetRecordingRequest { channel: self.rec.channel }
+    }
+
+    fn validate(&self, range: &GetRecordingRange) -> Result<(), ValidationError> {
+        ValidationError::check_allowed("packTime", &self.rec.pack_time, &range.rec.pack_time)?;
+        ValidationError::check_allowed("postRec", &self.rec.post_rec, &range.rec.post_rec)?;
+        Ok(())
+    }
+}
+
+/// Set the recording configuration. Note: This command supports models 52X only. When
+/// `scheduleVersion=1` in the capability set, use `set_recording_v20`.
+#[derive(Debug, Clone, Serialize)]
+pub struct SetRecordingRequest {
+    #[serde(rename = "Rec")]
+    pub rec: RecordingConfig,
+}
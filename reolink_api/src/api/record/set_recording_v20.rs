@@ -0,0 +1,78 @@
+use serde::Serialize;
+use crate::api::{NotApplicable, SimpleResult, Validate, ValidationError};
+use crate::api::JsonEndpoint;
+use crate::api::record::get_recording_v20::{GetRecordingRange, GetRecordingRequest, RecordingConfig};
+
+impl JsonEndpoint for SetRecordingRequest {
+    const CMD: &'static str = "SetRecV20";
+    type Response = SimpleResult;
+    type Initial = NotApplicable;
+    type Range = NotApplicable;
+}
+
+impl Validate for SetRecordingRequest {
+    type RangeEndpoint = GetRecordingRequest;
+
+    fn range_request(&self) -> GetRecordingRequest {
+        GetRecordingRequest { channel: self.rec.schedule.channel }
+    }
+
+    fn validate(&self, range: &GetRecordingRange) -> Result<(), ValidationError> {
+        if let (Some(pack_time), Some(allowed)) = (&self.rec.pack_time, &range.rec.pack_time) {
+            ValidationError::check_allowed("packTime", pack_time, allowed)?;
+        }
+        ValidationError::check_allowed("postRec", &self.rec.post_rec, &range.rec.post_rec)?;
+        Ok(())
+    }
+}
+
+/// Set the recording configuration. The configuration must be complete, and is usually
+/// obtained with `get_recording_v20`.
+#[derive(Debug, Clone, Serialize)]
+pub struct SetRecordingRequest {
+    #[serde(rename = "Rec")]
+    pub rec: RecordingConfig,
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::api::{ConfigEndpoint, DeviceDuration, Validate, ValidationError};
+    use crate::api::record::get_recording_v20::{GetRecordingRange, GetRecordingRequest, GetRecordingResponse};
+
+    #[test]
+    fn test_round_trip() -> anyhow::Result<()> {
+        let json = r#"{"Rec":{"enable":1,"overwrite":1,"postRec":"1 Minute","preRec":0,"saveDay":30,
+            "schedule":{"channel":0,"table":{"MD":"0101"}}}}"#;
+        let response = serde_json::from_str::<GetRecordingResponse>(json)?;
+
+        let mut config = GetRecordingRequest::config(response);
+        config.overwrite = false;
+        let req = GetRecordingRequest::set_request(2, config);
+
+        let json = serde_json::to_value(&req)?;
+        assert_eq!(json, serde_json::json!({"Rec":{"enable":1,"overwrite":0,"postRec":"1 Minute","preRec":0,"saveDay":30,
+            "schedule":{"channel":2,"table":{"MD":"0101"}}}}));
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate() -> anyhow::Result<()> {
+        let json = r#"{"Rec":{"enable":"boolean","overwrite":"boolean","postRec":["30 Seconds","1 Minute"],
+            "preRec":"boolean","schedule":{"channel":0}}}"#;
+        let range = serde_json::from_str::<GetRecordingRange>(json)?;
+
+        let json = r#"{"Rec":{"enable":1,"overwrite":1,"postRec":"1 Minute","preRec":0,"saveDay":30,
+            "schedule":{"channel":0,"table":{}}}}"#;
+        let mut config = GetRecordingRequest::config(serde_json::from_str(json)?);
+        assert!(GetRecordingRequest::set_request(0, config.clone()).validate(&range).is_ok());
+
+        config.post_rec = DeviceDuration::minutes(2);
+        let err = GetRecordingRequest::set_request(0, config).validate(&range).unwrap_err();
+        assert!(matches!(err, ValidationError::NotAllowed { field: "postRec", .. }));
+        assert_eq!(
+            "invalid value '2 Minutes' for 'postRec', allowed values are: 30 Seconds, 1 Minute",
+            err.to_string()
+        );
+        Ok(())
+    }
+}
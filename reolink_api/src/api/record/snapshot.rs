@@ -1,3 +1,7 @@
+use std::fmt::{Display, Formatter};
+use std::hash::{BuildHasher, RandomState};
+use std::time::SystemTime;
+use bytes::Bytes;
 use serde::Serialize;
 use crate::api::{BinaryEndpoint, Channel};
 
@@ -11,4 +15,152 @@ pub struct SnapshotRequest {
     pub channel: Channel,
     /// Random string with fixed length. It's used to prevent browser caching.
     pub rs: String,
+    /// Width of the image, scaled by the device. Defaults to the main stream's width.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub width: Option<u32>,
+    /// Height of the image, scaled by the device. Defaults to the main stream's height.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub height: Option<u32>,
+}
+
+impl SnapshotRequest {
+    /// A full size snapshot of a channel, with a random `rs`.
+    pub fn new(channel: Channel) -> Self {
+        let rs = RandomState::new().hash_one(SystemTime::now());
+        SnapshotRequest {
+            channel,
+            rs: format!("{rs:016x}"),
+            width: None,
+            height: None,
+        }
+    }
+
+    /// Requests an image scaled to `width` x `height`.
+    pub fn with_size(mut self, width: u32, height: u32) -> Self {
+        self.width = Some(width);
+        self.height = Some(height);
+        self
+    }
+}
+
+//----- Result
+
+/// An image captured with `ReolinkClient::snapshot`.
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    pub jpeg: Bytes,
+    /// Width, read from the JPEG header
+    pub width: u16,
+    /// Height, read from the JPEG header
+    pub height: u16,
+    /// Time when the response was received
+    pub captured_at: SystemTime,
+}
+
+/// Error returned when a snapshot response isn't a valid JPEG image.
+#[derive(Debug, Clone)]
+pub enum SnapshotError {
+    /// The response isn't a JPEG image, e.g. an error message
+    NotJpeg(Bytes),
+    /// The JPEG header has no frame size
+    NoDimensions,
+}
+
+impl Display for SnapshotError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SnapshotError::NotJpeg(body) => {
+                let start = &body[..body.len().min(100)];
+                write!(f, "response is not a JPEG image: '{}'", String::from_utf8_lossy(start))
+            },
+            SnapshotError::NoDimensions => f.write_str("JPEG image has no frame header"),
+        }
+    }
+}
+
+impl std::error::Error for SnapshotError {}
+
+impl Snapshot {
+    pub fn new(jpeg: Bytes, captured_at: SystemTime) -> Result<Self, SnapshotError> {
+        if !jpeg.starts_with(&[0xFF, 0xD8]) {
+            return Err(SnapshotError::NotJpeg(jpeg));
+        }
+        let (width, height) = jpeg_dimensions(&jpeg).ok_or(SnapshotError::NoDimensions)?;
+        Ok(Snapshot { jpeg, width, height, captured_at })
+    }
+}
+
+/// Reads the image dimensions from the first start of frame (SOF) segment of a JPEG image.
+fn jpeg_dimensions(data: &[u8]) -> Option<(u16, u16)> {
+    let mut pos = 2; // After SOI
+    loop {
+        // Markers can be preceded by fill bytes
+        while *data.get(pos)? == 0xFF && *data.get(pos + 1)? == 0xFF {
+            pos += 1;
+        }
+        if *data.get(pos)? != 0xFF {
+            return None;
+        }
+        let marker = *data.get(pos + 1)?;
+        pos += 2;
+        match marker {
+            // Standalone markers
+            0x01 | 0xD0..=0xD7 => continue,
+            // Start of scan or end of image: no frame header before image data
+            0xDA | 0xD9 => return None,
+            _ => (),
+        }
+        let len = u16::from_be_bytes([*data.get(pos)?, *data.get(pos + 1)?]) as usize;
+        // SOF0 to SOF15, except DHT, JPG and DAC
+        if (0xC0..=0xCF).contains(&marker) && !matches!(marker, 0xC4 | 0xC8 | 0xCC) {
+            let segment = data.get(pos + 2..pos + 7)?;
+            let height = u16::from_be_bytes([segment[1], segment[2]]);
+            let width = u16::from_be_bytes([segment[3], segment[4]]);
+            return Some((width, height));
+        }
+        pos += len;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dimensions() {
+        let jpeg = [
+            0xFF, 0xD8,
+            0xFF, 0xE0, 0x00, 0x04, 0x00, 0x00, // APP0
+            0xFF, 0xFF, 0xC4, 0x00, 0x03, 0x00, // Fill byte, DHT
+            0xFF, 0xC2, 0x00, 0x0B, 0x08, 0x01, 0x68, 0x02, 0x80, 0x01, 0x01, 0x11, 0x00, // SOF2
+            0xFF, 0xD9,
+        ];
+        let snapshot = Snapshot::new(Bytes::copy_from_slice(&jpeg), SystemTime::now()).unwrap();
+        assert_eq!((640, 360), (snapshot.width, snapshot.height));
+
+        let truncated = Snapshot::new(Bytes::copy_from_slice(&jpeg[..20]), SystemTime::now());
+        assert!(matches!(truncated, Err(SnapshotError::NoDimensions)));
+    }
+
+    #[test]
+    fn test_not_jpeg() {
+        let body = Bytes::from_static(br#"[{"cmd":"Snap","code":1,"error":{"detail":"please login first","rspCode":-6}}]"#);
+        let err = Snapshot::new(body, SystemTime::now()).unwrap_err();
+        assert!(matches!(err, SnapshotError::NotJpeg(_)));
+        assert!(err.to_string().contains("please login first"));
+    }
+
+    #[test]
+    fn test_request() -> anyhow::Result<()> {
+        let req = SnapshotRequest::new(1);
+        assert_eq!(16, req.rs.len());
+        assert_ne!(req.rs, SnapshotRequest::new(1).rs);
+
+        let json = serde_json::to_value(&req)?;
+        assert!(json.get("width").is_none());
+        let json = serde_json::to_value(req.with_size(640, 360))?;
+        assert_eq!(640, json["width"]);
+        assert_eq!(360, json["height"]);
+        Ok(())
+    }
 }
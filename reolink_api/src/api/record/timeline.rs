@@ -0,0 +1,247 @@
+//! Recording timelines: continuous segments of recordings built from the files returned by
+//! `Search`, with the gaps between them and their coverage of a recording schedule.
+//!
+//! All date-times are device local times.
+
+use std::collections::BTreeMap;
+use chrono::{Datelike, NaiveDate, NaiveDateTime, NaiveTime, TimeDelta, Timelike};
+use crate::api::record::InvalidDateTime;
+use crate::api::record::schedule::{Weekday, WeeklySchedule};
+use crate::api::record::search::SearchFile;
+
+/// A continuous recording period, made of one or more adjacent files.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Segment {
+    pub start: NaiveDateTime,
+    pub end: NaiveDateTime,
+    /// Number of files merged in this segment
+    pub files: usize,
+}
+
+impl Segment {
+    pub fn duration(&self) -> TimeDelta {
+        self.end - self.start
+    }
+}
+
+/// A period without recordings.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Gap {
+    pub start: NaiveDateTime,
+    pub end: NaiveDateTime,
+}
+
+impl Gap {
+    pub fn duration(&self) -> TimeDelta {
+        self.end - self.start
+    }
+}
+
+/// Scheduled and recorded durations over a period.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Coverage {
+    /// Time where recording is enabled in the schedule
+    pub scheduled: TimeDelta,
+    /// Part of the scheduled time that was actually recorded
+    pub recorded: TimeDelta,
+}
+
+impl Coverage {
+    /// Percentage of the scheduled time that was recorded, 100 if nothing was scheduled.
+    pub fn percent(&self) -> f64 {
+        if self.scheduled.is_zero() {
+            100.0
+        } else {
+            self.recorded.num_milliseconds() as f64 * 100.0 / self.scheduled.num_milliseconds() as f64
+        }
+    }
+}
+
+/// The recordings of a channel, as sorted and non-overlapping segments.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Timeline {
+    segments: Vec<Segment>,
+}
+
+impl Timeline {
+    /// Maximum gap between two files for them to be merged in a segment. Files of continuous
+    /// recordings usually end one second before the next one starts.
+    pub const DEFAULT_MAX_GAP: TimeDelta = TimeDelta::seconds(2);
+
+    /// Builds a timeline from files, merging those that are separated by at most `max_gap`.
+    pub fn from_files<'a>(files: impl IntoIterator<Item = &'a SearchFile>, max_gap: TimeDelta) -> Result<Timeline, InvalidDateTime> {
+        let ranges = files.into_iter()
+            .map(|f| Ok((NaiveDateTime::try_from(&f.start_time)?, NaiveDateTime::try_from(&f.end_time)?)))
+            .collect::<Result<Vec<_>, InvalidDateTime>>()?;
+        Ok(Self::from_ranges(ranges, max_gap))
+    }
+
+    /// Builds a timeline from time ranges, merging those that are separated by at most `max_gap`.
+    pub fn from_ranges(ranges: impl IntoIterator<Item = (NaiveDateTime, NaiveDateTime)>, max_gap: TimeDelta) -> Timeline {
+        let mut ranges = ranges.into_iter().filter(|(start, end)| start <= end).collect::<Vec<_>>();
+        ranges.sort();
+
+        let mut segments: Vec<Segment> = Vec::new();
+        for (start, end) in ranges {
+            match segments.last_mut() {
+                Some(last) if start <= last.end + max_gap => {
+                    last.end = last.end.max(end);
+                    last.files += 1;
+                }
+                _ => segments.push(Segment { start, end, files: 1 }),
+            }
+        }
+        Timeline { segments }
+    }
+
+    pub fn segments(&self) -> &[Segment] {
+        &self.segments
+    }
+
+    pub fn total_duration(&self) -> TimeDelta {
+        self.segments.iter().map(Segment::duration).sum()
+    }
+
+    /// Gaps between `start` and `end` that are longer than `min_duration`, including those
+    /// before the first segment and after the last one.
+    pub fn gaps(&self, start: NaiveDateTime, end: NaiveDateTime, min_duration: TimeDelta) -> Vec<Gap> {
+        let mut gaps = Vec::new();
+        let mut current = start;
+        for segment in &self.segments {
+            if segment.start > current {
+                gaps.push(Gap { start: current, end: segment.start.min(end) });
+            }
+            current = current.max(segment.end);
+            if current >= end {
+                break;
+            }
+        }
+        if current < end {
+            gaps.push(Gap { start: current, end });
+        }
+        gaps.retain(|gap| gap.duration() > min_duration);
+        gaps
+    }
+
+    /// Recorded duration of each day, with segments that span midnight split across days.
+    pub fn daily_durations(&self) -> BTreeMap<NaiveDate, TimeDelta> {
+        let mut result = BTreeMap::new();
+        for segment in &self.segments {
+            let mut start = segment.start;
+            while start < segment.end {
+                let next_day = start.date().and_time(NaiveTime::MIN) + TimeDelta::days(1);
+                let end = next_day.min(segment.end);
+                *result.entry(start.date()).or_insert(TimeDelta::zero()) += end - start;
+                start = end;
+            }
+        }
+        result
+    }
+
+    /// Recorded duration between `start` and `end`.
+    pub fn recorded_between(&self, start: NaiveDateTime, end: NaiveDateTime) -> TimeDelta {
+        // Segments are sorted and don't overlap: skip those that end before `start`
+        let first = self.segments.partition_point(|s| s.end <= start);
+        self.segments[first..].iter()
+            .take_while(|s| s.start < end)
+            .map(|s| s.end.min(end) - s.start.max(start))
+            .filter(|d| *d > TimeDelta::zero())
+            .sum()
+    }
+
+    /// Coverage between `start` and `end` of the hours that are enabled in `schedule`, e.g. the
+    /// `TIMING` table of `GetRecV20` for continuous recording.
+    pub fn schedule_coverage(&self, schedule: &WeeklySchedule, start: NaiveDateTime, end: NaiveDateTime) -> Coverage {
+        let mut scheduled = TimeDelta::zero();
+        let mut recorded = TimeDelta::zero();
+
+        let mut hour = start.with_minute(0).unwrap().with_second(0).unwrap().with_nanosecond(0).unwrap();
+        while hour < end {
+            let next = hour + TimeDelta::hours(1);
+            let weekday = Weekday::from(hour.weekday());
+            if schedule.is_enabled(weekday, hour.hour() as u8) {
+                let (slot_start, slot_end) = (hour.max(start), next.min(end));
+                scheduled += slot_end - slot_start;
+                recorded += self.recorded_between(slot_start, slot_end);
+            }
+            hour = next;
+        }
+
+        Coverage { scheduled, recorded }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn t(s: &str) -> NaiveDateTime {
+        s.parse().unwrap()
+    }
+
+    fn timeline() -> Timeline {
+        Timeline::from_ranges([
+            (t("2025-02-09T10:00:00"), t("2025-02-09T10:29:59")),
+            (t("2025-02-09T10:30:00"), t("2025-02-09T10:59:59")),
+            // Overlapping
+            (t("2025-02-09T10:50:00"), t("2025-02-09T11:10:00")),
+            // After a gap, across midnight
+            (t("2025-02-09T23:30:00"), t("2025-02-10T00:30:00")),
+        ], Timeline::DEFAULT_MAX_GAP)
+    }
+
+    #[test]
+    fn test_segments() {
+        let timeline = timeline();
+        assert_eq!(vec![
+            Segment { start: t("2025-02-09T10:00:00"), end: t("2025-02-09T11:10:00"), files: 3 },
+            Segment { start: t("2025-02-09T23:30:00"), end: t("2025-02-10T00:30:00"), files: 1 },
+        ], timeline.segments());
+        assert_eq!(TimeDelta::minutes(70 + 60), timeline.total_duration());
+    }
+
+    #[test]
+    fn test_gaps() {
+        let timeline = timeline();
+        let gaps = timeline.gaps(t("2025-02-09T00:00:00"), t("2025-02-10T23:59:59"), TimeDelta::minutes(5));
+        assert_eq!(vec![
+            Gap { start: t("2025-02-09T00:00:00"), end: t("2025-02-09T10:00:00") },
+            Gap { start: t("2025-02-09T11:10:00"), end: t("2025-02-09T23:30:00") },
+            Gap { start: t("2025-02-10T00:30:00"), end: t("2025-02-10T23:59:59") },
+        ], gaps);
+
+        // Short gaps are ignored, and the range is within a segment
+        let gaps = timeline.gaps(t("2025-02-09T10:05:00"), t("2025-02-09T11:00:00"), TimeDelta::zero());
+        assert!(gaps.is_empty());
+        let gaps = timeline.gaps(t("2025-02-09T11:00:00"), t("2025-02-09T11:12:00"), TimeDelta::minutes(5));
+        assert!(gaps.is_empty());
+    }
+
+    #[test]
+    fn test_daily_durations() {
+        let days = timeline().daily_durations();
+        let day = |s: &str| s.parse::<NaiveDate>().unwrap();
+        assert_eq!(Some(&TimeDelta::minutes(70 + 30)), days.get(&day("2025-02-09")));
+        assert_eq!(Some(&TimeDelta::minutes(30)), days.get(&day("2025-02-10")));
+    }
+
+    #[test]
+    fn test_schedule_coverage() {
+        let timeline = timeline();
+
+        // 10:00 to 12:00 on Sundays: 70 minutes recorded out of 120
+        let mut schedule = WeeklySchedule::new();
+        schedule.set_range([Weekday::Sunday], 10..12, 1).unwrap();
+        let coverage = timeline.schedule_coverage(&schedule, t("2025-02-09T00:00:00"), t("2025-02-16T00:00:00"));
+        assert_eq!(TimeDelta::hours(2), coverage.scheduled);
+        assert_eq!(TimeDelta::minutes(70), coverage.recorded);
+        assert!((coverage.percent() - 58.33).abs() < 0.01);
+
+        // Partial hours at the bounds
+        let coverage = timeline.schedule_coverage(&WeeklySchedule::always(), t("2025-02-09T10:30:00"), t("2025-02-09T11:30:00"));
+        assert_eq!(TimeDelta::hours(1), coverage.scheduled);
+        assert_eq!(TimeDelta::minutes(40), coverage.recorded);
+
+        assert_eq!(100.0, timeline.schedule_coverage(&WeeklySchedule::new(), t("2025-02-09T00:00:00"), t("2025-02-10T00:00:00")).percent());
+    }
+}
@@ -1,6 +1,8 @@
 use serde::Serialize;
-use crate::api::{NotApplicable, SimpleResult};
+use crate::api::{NotApplicable, SimpleResult, Validate, ValidationError};
 use crate::api::JsonEndpoint;
+use crate::api::security::get_user::{GetUserRange, GetUserRequest};
+use crate::api::security::UserLevel;
 
 impl JsonEndpoint for AddUserRequest {
     const CMD: &'static str = "AddUser";
@@ -9,6 +11,23 @@ impl JsonEndpoint for AddUserRequest {
     type Range = NotApplicable;
 }
 
+impl Validate for AddUserRequest {
+    type RangeEndpoint = GetUserRequest;
+
+    fn range_request(&self) -> GetUserRequest {
+        GetUserRequest
+    }
+
+    fn validate(&self, range: &GetUserRange) -> Result<(), ValidationError> {
+        let user = &self.user;
+        let range = &range.user;
+        ValidationError::check_length("userName", &user.username, range.user_name.min_len, range.user_name.max_len)?;
+        ValidationError::check_length("password", &user.password, range.password.min_len, range.password.max_len)?;
+        ValidationError::check_allowed("level", &user.level, &range.level)?;
+        Ok(())
+    }
+}
+
 /// Used to set the configuration of a user.
 #[derive(Debug, Clone, Serialize)]
 pub struct AddUserRequest {
@@ -23,7 +42,39 @@ pub struct AddUser {
     pub username: String,
     /// Password
     pub password: String,
-    /// Access level (`admin` or `guest`)
-    pub level: String,
+    /// Access level
+    pub level: UserLevel,
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate() -> anyhow::Result<()> {
+        let json = r#"{"User":{"level":["guest","admin"],"password":{"maxLen":31,"minLen":6},
+            "userName":{"maxLen":31,"minLen":1}}}"#;
+        let range = serde_json::from_str::<GetUserRange>(json)?;
+
+        let mut req = AddUserRequest {
+            user: AddUser {
+                username: "newuser".to_string(),
+                password: "s3cr3t-p4ssw0rd".to_string(),
+                level: UserLevel::Guest,
+            }
+        };
+        assert!(req.validate(&range).is_ok());
+
+        req.user.password = "123".to_string();
+        assert_eq!(
+            Err(ValidationError::Length { field: "password", len: 3, min: 6, max: 31 }),
+            req.validate(&range)
+        );
+
+        req.user.password = "s3cr3t-p4ssw0rd".to_string();
+        req.user.level = UserLevel::Other("root".to_string());
+        assert!(matches!(req.validate(&range), Err(ValidationError::NotAllowed { field: "level", .. })));
+        Ok(())
+    }
+}
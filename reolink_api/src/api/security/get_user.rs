@@ -1,5 +1,6 @@
 use serde::{Deserialize, Serialize};
 use crate::api::JsonEndpoint;
+use crate::api::security::UserLevel;
 
 impl JsonEndpoint for GetUserRequest {
     const CMD: &'static str = "GetUser";
@@ -27,8 +28,8 @@ pub struct UserInfo {
     /// User name
     #[serde(rename = "userName")]
     pub user_name: String,
-    /// Access level (`admin` or `guest`)
-    pub level: String,
+    /// Access level
+    pub level: UserLevel,
 }
 
 //----- Initial
@@ -41,7 +42,7 @@ pub struct GetUserInitial {
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct UserInitial {
-    pub level: String,
+    pub level: UserLevel,
 }
 
 //----- Range
@@ -55,7 +56,7 @@ pub struct GetUserRange {
 #[derive(Debug, Clone, Deserialize)]
 pub struct UserRange {
     /// Available access levels
-    pub level: Vec<String>,
+    pub level: Vec<UserLevel>,
     #[serde(rename = "userName")]
     /// Min and max length of a username
     pub user_name: LengthRange,
@@ -2,3 +2,13 @@ pub mod login;
 pub mod logout;
 pub mod get_user;
 pub mod add_user;
+
+use crate::serde::string_enum;
+
+string_enum! {
+    /// Access level of a user.
+    pub enum UserLevel {
+        Admin = "admin",
+        Guest = "guest",
+    }
+}
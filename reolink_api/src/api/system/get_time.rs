@@ -0,0 +1,287 @@
+use serde::{Deserialize, Serialize};
+use crate::api::NotApplicable;
+use crate::api::JsonEndpoint;
+
+impl JsonEndpoint for GetTimeRequest {
+    const CMD: &'static str = "GetTime";
+    type Response = GetTimeResponse;
+    type Initial = NotApplicable;
+    type Range = NotApplicable;
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GetTimeRequest;
+
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GetTimeResponse {
+    #[serde(rename = "Dst")]
+    pub dst: Dst,
+
+    #[serde(rename = "Time")]
+    pub time: Time,
+}
+
+/// The device's clock, as a local date-time.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Time {
+    pub year: u16,
+    pub mon: u8,
+    pub day: u8,
+    pub hour: u8,
+    pub min: u8,
+    pub sec: u8,
+
+    /// 0 for 24 hours, 1 for AM/PM
+    pub hour_fmt: u8,
+
+    /// Date format in the device's UI, e.g. `DD/MM/YYYY`
+    pub time_fmt: String,
+
+    /// Offset from UTC in seconds, when DST isn't in effect. Beware that the sign is the
+    /// opposite of the usual convention: UTC+1 is `-3600`.
+    pub time_zone: i32,
+}
+
+/// Daylight saving time rules. DST starts and ends on a week day of a given week of a month.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Dst {
+    #[serde(with = "crate::serde::bool_as_number")]
+    pub enable: bool,
+
+    /// Offset added to the time zone, in hours
+    pub offset: u8,
+
+    pub start_mon: u8,
+    /// Week of the month, from 1 to 5. The 5th week is the last one of the month.
+    pub start_week: u8,
+    /// Day of the week, 0 is Sunday
+    pub start_weekday: u8,
+    /// Start time, in standard time
+    pub start_hour: u8,
+    pub start_min: u8,
+    pub start_sec: u8,
+
+    pub end_mon: u8,
+    pub end_week: u8,
+    pub end_weekday: u8,
+    /// End time, in daylight saving time
+    pub end_hour: u8,
+    pub end_min: u8,
+    pub end_sec: u8,
+}
+
+#[cfg(feature = "chrono")]
+pub use chrono_impl::DeviceTimeZone;
+
+#[cfg(feature = "chrono")]
+mod chrono_impl {
+    use chrono::{Datelike, Duration, FixedOffset, NaiveDate, NaiveDateTime, NaiveTime, Utc, Weekday};
+    use crate::api::record::{DateTime, YearOutOfRange};
+    use super::{Dst, GetTimeResponse};
+
+    /// A device's time zone, used to convert the local date-times of the API to and from
+    /// absolute instants.
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct DeviceTimeZone {
+        /// Offset from UTC when daylight saving time isn't in effect
+        pub standard: FixedOffset,
+        /// Daylight saving time rules, if enabled
+        pub dst: Option<Dst>,
+    }
+
+    impl From<&GetTimeResponse> for DeviceTimeZone {
+        fn from(value: &GetTimeResponse) -> Self {
+            DeviceTimeZone::new(value.time.time_zone, &value.dst)
+        }
+    }
+
+    impl DeviceTimeZone {
+        /// Creates a time zone from the `timeZone` value and DST rules returned by `GetTime`.
+        pub fn new(time_zone: i32, dst: &Dst) -> Self {
+            DeviceTimeZone {
+                standard: FixedOffset::west_opt(time_zone).unwrap_or(FixedOffset::east_opt(0).unwrap()),
+                dst: if dst.enable { Some(dst.clone()) } else { None },
+            }
+        }
+
+        /// The offset from UTC of a local date-time.
+        ///
+        /// Local times in the hour skipped when DST starts don't exist, and those in the hour
+        /// repeated when DST ends are ambiguous. They're both considered to be in DST.
+        pub fn offset_from_local(&self, local: &NaiveDateTime) -> FixedOffset {
+            let Some(dst) = &self.dst else {
+                return self.standard;
+            };
+            let (start, end) = transitions(dst, local.year());
+            if in_range(local, &start, &end) { self.dst_offset(dst) } else { self.standard }
+        }
+
+        /// The offset from UTC of an instant.
+        pub fn offset_from_utc(&self, utc: &NaiveDateTime) -> FixedOffset {
+            let Some(dst) = &self.dst else {
+                return self.standard;
+            };
+            // Compare in standard time: the end time is in DST, shift it back to standard time
+            let local = *utc + self.standard;
+            let (start, end) = transitions(dst, local.year());
+            let end = end - dst_delta(dst);
+            if in_range(&local, &start, &end) { self.dst_offset(dst) } else { self.standard }
+        }
+
+        /// Converts a device local date-time to a date-time with its offset from UTC.
+        pub fn to_fixed(&self, value: &DateTime) -> anyhow::Result<chrono::DateTime<FixedOffset>> {
+            let local = NaiveDateTime::try_from(value)?;
+            let offset = self.offset_from_local(&local);
+            local.and_local_timezone(offset).single()
+                .ok_or_else(|| anyhow::anyhow!("Invalid date-time {}", value))
+        }
+
+        /// Converts a device local date-time to UTC.
+        pub fn to_utc(&self, value: &DateTime) -> anyhow::Result<chrono::DateTime<Utc>> {
+            Ok(self.to_fixed(value)?.with_timezone(&Utc))
+        }
+
+        /// Converts an instant to a device local date-time.
+        pub fn from_utc(&self, value: &chrono::DateTime<Utc>) -> Result<DateTime, YearOutOfRange> {
+            let utc = value.naive_utc();
+            (utc + self.offset_from_utc(&utc)).try_into()
+        }
+
+        fn dst_offset(&self, dst: &Dst) -> FixedOffset {
+            FixedOffset::east_opt(self.standard.local_minus_utc() + dst.offset as i32 * 3600).unwrap_or(self.standard)
+        }
+    }
+
+    fn dst_delta(dst: &Dst) -> Duration {
+        Duration::hours(dst.offset as i64)
+    }
+
+    /// Is `value` in DST, given the start and end of DST in its year? In the southern
+    /// hemisphere, DST starts at the end of the year and ends at the beginning of the next one.
+    fn in_range(value: &NaiveDateTime, start: &NaiveDateTime, end: &NaiveDateTime) -> bool {
+        if start < end {
+            start <= value && value < end
+        } else {
+            value >= start || value < end
+        }
+    }
+
+    /// Start and end of DST in a year.
+    fn transitions(dst: &Dst, year: i32) -> (NaiveDateTime, NaiveDateTime) {
+        (
+            transition(year, dst.start_mon, dst.start_week, dst.start_weekday, dst.start_hour, dst.start_min, dst.start_sec),
+            transition(year, dst.end_mon, dst.end_week, dst.end_weekday, dst.end_hour, dst.end_min, dst.end_sec),
+        )
+    }
+
+    fn transition(year: i32, mon: u8, week: u8, weekday: u8, hour: u8, min: u8, sec: u8) -> NaiveDateTime {
+        let mon = (mon as u32).clamp(1, 12);
+        let weekday = Weekday::try_from(((weekday as u32 + 6) % 7) as u8).unwrap(); // 0 is Sunday
+        let week = week.clamp(1, 5);
+
+        // The 5th week is the last one, even if the month has only 4 such days
+        let date = NaiveDate::from_weekday_of_month_opt(year, mon, weekday, week)
+            .or_else(|| NaiveDate::from_weekday_of_month_opt(year, mon, weekday, week - 1))
+            .unwrap();
+        let time = NaiveTime::from_hms_opt(hour as u32, min as u32, sec as u32).unwrap_or_default();
+        NaiveDateTime::new(date, time)
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use chrono::TimeZone;
+        use super::*;
+
+        fn europe() -> DeviceTimeZone {
+            // Last Sunday of March at 2:00 to last Sunday of October at 3:00
+            DeviceTimeZone::new(-3600, &Dst {
+                enable: true, offset: 1,
+                start_mon: 3, start_week: 5, start_weekday: 0, start_hour: 2, start_min: 0, start_sec: 0,
+                end_mon: 10, end_week: 5, end_weekday: 0, end_hour: 3, end_min: 0, end_sec: 0,
+            })
+        }
+
+        fn australia() -> DeviceTimeZone {
+            // First Sunday of October at 2:00 to first Sunday of April at 3:00
+            DeviceTimeZone::new(-36000, &Dst {
+                enable: true, offset: 1,
+                start_mon: 10, start_week: 1, start_weekday: 0, start_hour: 2, start_min: 0, start_sec: 0,
+                end_mon: 4, end_week: 1, end_weekday: 0, end_hour: 3, end_min: 0, end_sec: 0,
+            })
+        }
+
+        fn dt(year: u16, mon: u8, day: u8, hour: u8, min: u8) -> DateTime {
+            DateTime { year, mon, day, hour, min, sec: 0 }
+        }
+
+        #[test]
+        fn test_transitions() {
+            let dst = europe().dst.unwrap();
+            let (start, end) = transitions(&dst, 2024);
+            assert_eq!("2024-03-31 02:00:00", start.to_string());
+            assert_eq!("2024-10-27 03:00:00", end.to_string());
+
+            let dst = australia().dst.unwrap();
+            let (start, end) = transitions(&dst, 2024);
+            assert_eq!("2024-10-06 02:00:00", start.to_string());
+            assert_eq!("2024-04-07 03:00:00", end.to_string());
+        }
+
+        #[test]
+        fn test_to_utc() -> anyhow::Result<()> {
+            let tz = europe();
+            assert_eq!(Utc.with_ymd_and_hms(2024, 1, 15, 11, 0, 0).unwrap(), tz.to_utc(&dt(2024, 1, 15, 12, 0))?);
+            assert_eq!(Utc.with_ymd_and_hms(2024, 7, 15, 10, 0, 0).unwrap(), tz.to_utc(&dt(2024, 7, 15, 12, 0))?);
+            assert_eq!(7200, tz.to_fixed(&dt(2024, 7, 15, 12, 0))?.offset().local_minus_utc());
+
+            // Around the transitions
+            assert_eq!(Utc.with_ymd_and_hms(2024, 3, 31, 0, 59, 0).unwrap(), tz.to_utc(&dt(2024, 3, 31, 1, 59))?);
+            assert_eq!(Utc.with_ymd_and_hms(2024, 3, 31, 1, 0, 0).unwrap(), tz.to_utc(&dt(2024, 3, 31, 3, 0))?);
+            assert_eq!(Utc.with_ymd_and_hms(2024, 10, 27, 2, 0, 0).unwrap(), tz.to_utc(&dt(2024, 10, 27, 3, 0))?);
+
+            let tz = australia();
+            assert_eq!(Utc.with_ymd_and_hms(2024, 1, 15, 1, 0, 0).unwrap(), tz.to_utc(&dt(2024, 1, 15, 12, 0))?);
+            assert_eq!(Utc.with_ymd_and_hms(2024, 7, 15, 2, 0, 0).unwrap(), tz.to_utc(&dt(2024, 7, 15, 12, 0))?);
+
+            assert!(tz.to_utc(&dt(2024, 2, 30, 12, 0)).is_err());
+            Ok(())
+        }
+
+        #[test]
+        fn test_from_utc() -> anyhow::Result<()> {
+            let tz = europe();
+            // Every 10 minutes across the end of DST: local time goes 2:50, 2:00 (again), 2:10
+            let local = [0, 50, 60, 70].iter()
+                .map(|min| tz.from_utc(&(Utc.with_ymd_and_hms(2024, 10, 27, 0, 0, 0).unwrap() + Duration::minutes(*min))))
+                .map(|t| t.map(|t| (t.hour, t.min)))
+                .collect::<Result<Vec<_>, _>>()?;
+            assert_eq!(vec![(2, 0), (2, 50), (2, 0), (2, 10)], local);
+
+            // Round trip outside of ambiguous times
+            let utc = Utc.with_ymd_and_hms(2024, 12, 25, 18, 30, 0).unwrap();
+            assert_eq!(utc, tz.to_utc(&tz.from_utc(&utc)?)?);
+            assert_eq!(19, tz.from_utc(&utc)?.hour);
+
+            let tz = australia();
+            assert_eq!(5, tz.from_utc(&utc)?.hour);
+            assert_eq!(utc, tz.to_utc(&tz.from_utc(&utc)?)?);
+
+            // Years before 0 can't be sent to devices
+            assert_eq!(YearOutOfRange(-1), tz.from_utc(&Utc.with_ymd_and_hms(-1, 6, 1, 0, 0, 0).unwrap()).unwrap_err());
+            Ok(())
+        }
+
+        #[test]
+        fn test_no_dst() -> anyhow::Result<()> {
+            let mut dst = europe().dst.unwrap();
+            dst.enable = false;
+            let tz = DeviceTimeZone::new(18000, &dst);
+            assert_eq!(None, tz.dst);
+            assert_eq!(Utc.with_ymd_and_hms(2024, 7, 15, 17, 0, 0).unwrap(), tz.to_utc(&dt(2024, 7, 15, 12, 0))?);
+            Ok(())
+        }
+    }
+}
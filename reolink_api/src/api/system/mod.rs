@@ -1,3 +1,4 @@
 pub mod get_ability;
 pub mod get_channel_status;
 pub mod get_dev_info;
+pub mod get_time;
@@ -0,0 +1,267 @@
+//! Framing and encryption of Baichuan messages.
+//!
+//! A message is a little-endian header followed by a body. Bodies are XML documents,
+//! encrypted with a XOR key, optionally followed by a binary payload at `payload_offset`.
+
+use std::io::{Error, ErrorKind, Write};
+use md5::{Digest, Md5};
+
+/// Magic number of messages, in both directions
+pub const MAGIC: u32 = 0x0abcdef0;
+/// Magic number sent by some devices in replies
+pub const MAGIC_REV: u32 = 0x0fedcba0;
+
+pub const MSG_LOGIN: u32 = 1;
+pub const MSG_LOGOUT: u32 = 2;
+pub const MSG_ALARM_SUBSCRIBE: u32 = 31;
+pub const MSG_ALARM_EVENT: u32 = 33;
+pub const MSG_PING: u32 = 93;
+
+/// Legacy messages, only used for the first login message
+pub const CLASS_LEGACY: u16 = 0x6514;
+/// Modern messages, without payload offset
+pub const CLASS_MODERN: u16 = 0x6614;
+/// Modern messages, with a payload offset
+pub const CLASS_MODERN_OFFSET: u16 = 0x6414;
+/// Modern messages, with a payload offset, used in some replies
+pub const CLASS_MODERN_OFFSET_ALT: u16 = 0x0000;
+
+/// Response code of the legacy login requesting the XOR encryption
+pub const ENCRYPTION_REQUEST_BC: u16 = 0xdc01;
+/// Response codes of the legacy login reply, with the encryption chosen by the device
+pub const ENCRYPTION_NONE: u16 = 0xdd00;
+pub const ENCRYPTION_BC: u16 = 0xdd01;
+/// AES encryption of XML bodies
+pub const ENCRYPTION_AES: u16 = 0xdd02;
+/// AES encryption of XML bodies and binary payloads
+pub const ENCRYPTION_FULL_AES: u16 = 0xdd12;
+
+/// Response code of successful modern requests
+pub const RESPONSE_OK: u16 = 200;
+
+/// Size of the legacy login body: user name and password hashes, followed by zeros
+pub const LEGACY_LOGIN_SIZE: usize = 1836;
+
+const XML_KEY: [u8; 8] = [0x1F, 0x2D, 0x3C, 0x4B, 0x5A, 0x69, 0x78, 0xFF];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Header {
+    pub msg_id: u32,
+    pub channel: u8,
+    pub stream: u8,
+    /// Sequence number, echoed in replies
+    pub msg_num: u16,
+    /// Status in replies, encryption negotiation in login messages
+    pub response_code: u16,
+    pub class: u16,
+    /// Offset of the binary payload in the body, for classes that have it
+    pub payload_offset: Option<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Message {
+    pub header: Header,
+    /// Body, as sent on the wire
+    pub body: Vec<u8>,
+}
+
+impl Header {
+    pub fn new(msg_id: u32, msg_num: u16, class: u16, response_code: u16) -> Self {
+        let has_offset = matches!(class, CLASS_MODERN_OFFSET | CLASS_MODERN_OFFSET_ALT);
+        Header {
+            msg_id,
+            channel: 0,
+            stream: 0,
+            msg_num,
+            response_code,
+            class,
+            payload_offset: has_offset.then_some(0),
+        }
+    }
+
+    fn len(class: u16) -> usize {
+        match class {
+            CLASS_MODERN_OFFSET | CLASS_MODERN_OFFSET_ALT => 24,
+            _ => 20,
+        }
+    }
+}
+
+impl Message {
+    /// A message whose body is an XML document, encrypted with the XOR key.
+    pub fn xml(header: Header, xml: &str) -> Self {
+        let body = xml_crypt(header.channel, xml.as_bytes());
+        Message { header, body }
+    }
+
+    pub fn write_to(&self, out: &mut impl Write) -> std::io::Result<()> {
+        let h = &self.header;
+        let mut data = Vec::with_capacity(Header::len(h.class) + self.body.len());
+        data.extend(MAGIC.to_le_bytes());
+        data.extend(h.msg_id.to_le_bytes());
+        data.extend((self.body.len() as u32).to_le_bytes());
+        data.push(h.channel);
+        data.push(h.stream);
+        data.extend(h.msg_num.to_le_bytes());
+        data.extend(h.response_code.to_le_bytes());
+        data.extend(h.class.to_le_bytes());
+        if let Some(offset) = h.payload_offset {
+            data.extend(offset.to_le_bytes());
+        }
+        data.extend(&self.body);
+        out.write_all(&data)?;
+        out.flush()
+    }
+
+    /// Parses a message at the start of `buf`, and returns it with the number of bytes it
+    /// used. Returns `None` if `buf` doesn't contain a full message yet.
+    pub fn parse(buf: &[u8]) -> std::io::Result<Option<(Message, usize)>> {
+        if buf.len() < 20 {
+            return Ok(None);
+        }
+        let u32_at = |pos: usize| u32::from_le_bytes(buf[pos..pos + 4].try_into().unwrap());
+        let u16_at = |pos: usize| u16::from_le_bytes(buf[pos..pos + 2].try_into().unwrap());
+
+        let magic = u32_at(0);
+        if magic != MAGIC && magic != MAGIC_REV {
+            return Err(Error::new(ErrorKind::InvalidData, format!("invalid magic number {magic:#x}")));
+        }
+        let class = u16_at(18);
+        let header_len = Header::len(class);
+        let body_len = u32_at(8) as usize;
+        if buf.len() < header_len + body_len {
+            return Ok(None);
+        }
+
+        let header = Header {
+            msg_id: u32_at(4),
+            channel: buf[12],
+            stream: buf[13],
+            msg_num: u16_at(14),
+            response_code: u16_at(16),
+            class,
+            payload_offset: (header_len == 24).then(|| u32_at(20)),
+        };
+        let body = buf[header_len..header_len + body_len].to_vec();
+        Ok(Some((Message { header, body }, header_len + body_len)))
+    }
+
+    /// The XML part of the body, decrypted.
+    pub fn xml_body(&self) -> String {
+        let end = match self.header.payload_offset {
+            Some(offset) if offset > 0 => (offset as usize).min(self.body.len()),
+            _ => self.body.len(),
+        };
+        String::from_utf8_lossy(&xml_crypt(self.header.channel, &self.body[..end])).into_owned()
+    }
+}
+
+/// Encrypts or decrypts a body with the XOR key, rotated and combined with `offset`.
+pub fn xml_crypt(offset: u8, data: &[u8]) -> Vec<u8> {
+    XML_KEY.iter().cycle().skip(offset as usize % 8)
+        .zip(data)
+        .map(|(key, b)| b ^ key ^ offset)
+        .collect()
+}
+
+/// Uppercase hexadecimal MD5 hash, truncated to 31 characters as devices expect.
+pub fn md5_hex(text: &str) -> String {
+    let hash = Md5::digest(text.as_bytes());
+    let mut hex = hash.iter().map(|b| format!("{b:02X}")).collect::<String>();
+    hex.truncate(31);
+    hex
+}
+
+/// Body of the legacy login message.
+pub fn legacy_login_body(login: &str, password: &str) -> Vec<u8> {
+    let mut body = vec![0; LEGACY_LOGIN_SIZE];
+    body[..31].copy_from_slice(md5_hex(login).as_bytes());
+    body[32..63].copy_from_slice(md5_hex(password).as_bytes());
+    body
+}
+
+/// Body of the modern login message, with credentials hashed with the device's nonce.
+pub fn login_xml(login: &str, password: &str, nonce: &str) -> String {
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\" ?>\n<body>\n<LoginUser version=\"1.1\">\n\
+        <userName>{}</userName>\n<password>{}</password>\n<userVer>1</userVer>\n</LoginUser>\n\
+        <LoginNet version=\"1.1\">\n<type>LAN</type>\n<udpPort>0</udpPort>\n</LoginNet>\n</body>\n",
+        md5_hex(&format!("{login}{nonce}")),
+        md5_hex(&format!("{password}{nonce}")),
+    )
+}
+
+/// Content of the first `tag` element in `xml`. Bodies are small and flat enough that a full
+/// XML parser isn't needed.
+pub fn xml_value<'a>(xml: &'a str, tag: &str) -> Option<&'a str> {
+    let open = format!("<{tag}>");
+    let start = xml.find(&open)? + open.len();
+    let end = start + xml[start..].find(&format!("</{tag}>"))?;
+    Some(xml[start..end].trim())
+}
+
+/// Contents of the `tag` elements in `xml`, which may have attributes.
+pub fn xml_elements<'a>(xml: &'a str, tag: &str) -> Vec<&'a str> {
+    let close = format!("</{tag}>");
+    xml.match_indices(&format!("<{tag}"))
+        .filter(|(pos, m)| matches!(xml[pos + m.len()..].chars().next(), Some('>' | ' ')))
+        .filter_map(|(pos, _)| {
+            let start = pos + xml[pos..].find('>')? + 1;
+            let end = start + xml[start..].find(&close)?;
+            Some(&xml[start..end])
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() -> std::io::Result<()> {
+        let mut header = Header::new(MSG_ALARM_EVENT, 3, CLASS_MODERN_OFFSET, RESPONSE_OK);
+        header.channel = 2;
+        let msg = Message::xml(header, "<body><a>1</a></body>");
+        let mut data = Vec::new();
+        msg.write_to(&mut data)?;
+        assert_eq!(24 + 21, data.len());
+        assert_eq!(&[0xF0, 0xDE, 0xBC, 0x0A], &data[..4]);
+
+        assert_eq!(None, Message::parse(&data[..30])?);
+        let (parsed, len) = Message::parse(&data)?.unwrap();
+        assert_eq!(data.len(), len);
+        assert_eq!(msg, parsed);
+        assert_eq!("<body><a>1</a></body>", parsed.xml_body());
+
+        assert!(Message::parse(&[0; 24]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_crypt() {
+        // Known encryption of "<?xml" at offset 0
+        assert_eq!(vec![0x23, 0x12, 0x44, 0x26, 0x36], xml_crypt(0, b"<?xml"));
+        assert_eq!(b"<?xml".to_vec(), xml_crypt(5, &xml_crypt(5, b"<?xml")));
+    }
+
+    #[test]
+    fn test_login() {
+        assert_eq!("21232F297A57A5A743894A0E4A801FC", md5_hex("admin"));
+        let body = legacy_login_body("admin", "");
+        assert_eq!(LEGACY_LOGIN_SIZE, body.len());
+        assert_eq!(&b"21232F29"[..], &body[..8]);
+        assert_eq!(0, body[31]);
+        assert_eq!(&b"D41D8CD9"[..], &body[32..40]);
+
+        let xml = login_xml("admin", "secret", "abc");
+        assert_eq!(Some(md5_hex("adminabc").as_str()), xml_value(&xml, "userName"));
+    }
+
+    #[test]
+    fn test_xml() {
+        let xml = r#"<body><AlarmEventList version="1.1"><AlarmEvent version="1.1"><channelId>0</channelId></AlarmEvent><AlarmEvent><channelId>1</channelId></AlarmEvent></AlarmEventList></body>"#;
+        let events = xml_elements(xml, "AlarmEvent");
+        assert_eq!(vec![Some("0"), Some("1")], events.iter().map(|e| xml_value(e, "channelId")).collect::<Vec<_>>());
+        assert_eq!(None, xml_value(xml, "status"));
+    }
+}
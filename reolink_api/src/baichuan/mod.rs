@@ -0,0 +1,244 @@
+//! Alarm events pushed by devices over their native TCP protocol, known as "Baichuan", which
+//! is used by the Reolink apps and listens on port 9000 (`mediaPort` in `GetNetPort`).
+//!
+//! Unlike polling `GetMdState`, short detections aren't missed, and battery cameras aren't
+//! woken up by requests. Only the XOR encryption of the protocol is supported: devices that
+//! require AES encryption are rejected at login.
+
+use std::collections::VecDeque;
+use std::io::{ErrorKind, Read};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+use crate::api::Channel;
+use crate::events::{DetectionState, EdgeDetector, Event};
+use codec::*;
+
+pub mod codec;
+
+pub const DEFAULT_PORT: u16 = 9000;
+
+/// An alarm reported by the device: the detections of a channel, replacing the previous ones.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AlarmEvent {
+    pub channel: Channel,
+    pub state: DetectionState,
+}
+
+impl AlarmEvent {
+    /// Parses the `AlarmEvent` elements of an `AlarmEventList` message. The `status` is `MD`
+    /// for motion, and `AItype` lists AI detections, e.g. `people,vehicle`.
+    pub fn parse_list(xml: &str) -> Vec<AlarmEvent> {
+        xml_elements(xml, "AlarmEvent").into_iter().filter_map(|event| {
+            let channel = xml_value(event, "channelId")?.parse().ok()?;
+            let status = xml_value(event, "status").unwrap_or("none");
+            let ai_types = xml_value(event, "AItype").unwrap_or("none");
+            let has_ai = |name: &str| ai_types.split(',').any(|t| t.trim() == name);
+            Some(AlarmEvent {
+                channel,
+                state: DetectionState {
+                    motion: status.split(',').any(|s| s.trim() == "MD"),
+                    person: has_ai("people"),
+                    vehicle: has_ai("vehicle"),
+                    animal: has_ai("dog_cat"),
+                },
+            })
+        }).collect()
+    }
+}
+
+/// A connection to a device, subscribed to its alarm events.
+///
+/// Iterating on the listener blocks until a detection starts or ends, and ends when the
+/// device closes the connection. Pings are sent when no message is received for the ping
+/// interval, to keep the connection open.
+pub struct AlarmListener {
+    stream: TcpStream,
+    /// Received data that isn't a full message yet
+    buffer: Vec<u8>,
+    msg_num: u16,
+    detector: EdgeDetector,
+    pending: VecDeque<Event>,
+    closed: bool,
+}
+
+impl AlarmListener {
+    pub const DEFAULT_PING_INTERVAL: Duration = Duration::from_secs(30);
+    /// Timeout of connection and replies
+    const TIMEOUT: Duration = Duration::from_secs(10);
+
+    /// Connects to a device, logs in and subscribes to alarm events.
+    pub fn connect(addr: impl ToSocketAddrs, login: &str, password: &str) -> anyhow::Result<Self> {
+        let addr = addr.to_socket_addrs()?.next()
+            .ok_or_else(|| anyhow::anyhow!("No address to connect to"))?;
+        let stream = TcpStream::connect_timeout(&addr, Self::TIMEOUT)?;
+        stream.set_nodelay(true)?;
+        stream.set_read_timeout(Some(Self::TIMEOUT))?;
+
+        let mut listener = AlarmListener {
+            stream,
+            buffer: Vec::new(),
+            msg_num: 0,
+            detector: EdgeDetector::new(),
+            pending: VecDeque::new(),
+            closed: false,
+        };
+        listener.login(login, password)?;
+
+        let reply = listener.request(MSG_ALARM_SUBSCRIBE, CLASS_MODERN_OFFSET, None)?;
+        if reply.header.response_code != RESPONSE_OK {
+            anyhow::bail!("Alarm subscription failed (code {})", reply.header.response_code);
+        }
+
+        listener.set_ping_interval(Self::DEFAULT_PING_INTERVAL)?;
+        Ok(listener)
+    }
+
+    /// Sets the delay without messages after which a ping is sent.
+    pub fn set_ping_interval(&mut self, interval: Duration) -> std::io::Result<()> {
+        self.stream.set_read_timeout(Some(interval))
+    }
+
+    /// Logs in with the legacy message, which returns the nonce to hash credentials with, then
+    /// with the modern message.
+    fn login(&mut self, login: &str, password: &str) -> anyhow::Result<()> {
+        let header = Header::new(MSG_LOGIN, self.msg_num, CLASS_LEGACY, ENCRYPTION_REQUEST_BC);
+        Message { header, body: legacy_login_body(login, password) }.write_to(&mut self.stream)?;
+        let reply = self.reply(MSG_LOGIN)?;
+
+        let xml = match reply.header.response_code {
+            ENCRYPTION_BC => reply.xml_body(),
+            ENCRYPTION_NONE => String::from_utf8_lossy(&reply.body).into_owned(),
+            ENCRYPTION_AES | ENCRYPTION_FULL_AES => anyhow::bail!("The device requires AES encryption, which isn't supported"),
+            code => anyhow::bail!("Unexpected login reply (code {:#x})", code),
+        };
+        let nonce = xml_value(&xml, "nonce").ok_or_else(|| anyhow::anyhow!("No nonce in login reply"))?;
+
+        let reply = self.request(MSG_LOGIN, CLASS_MODERN_OFFSET, Some(&login_xml(login, password, nonce)))?;
+        if reply.header.response_code != RESPONSE_OK {
+            anyhow::bail!("Login failed (code {})", reply.header.response_code);
+        }
+        Ok(())
+    }
+
+    fn send(&mut self, msg_id: u32, class: u16, xml: Option<&str>) -> std::io::Result<()> {
+        self.msg_num = self.msg_num.wrapping_add(1);
+        let header = Header::new(msg_id, self.msg_num, class, 0);
+        let message = match xml {
+            Some(xml) => Message::xml(header, xml),
+            None => Message { header, body: Vec::new() },
+        };
+        message.write_to(&mut self.stream)
+    }
+
+    fn request(&mut self, msg_id: u32, class: u16, xml: Option<&str>) -> anyhow::Result<Message> {
+        self.send(msg_id, class, xml)?;
+        self.reply(msg_id)
+    }
+
+    /// Reads messages until the reply to `msg_id`. Alarms received meanwhile are kept.
+    fn reply(&mut self, msg_id: u32) -> anyhow::Result<Message> {
+        loop {
+            let Some(message) = self.read_message()? else {
+                anyhow::bail!("Connection closed by the device");
+            };
+            if message.header.msg_id == msg_id {
+                return Ok(message);
+            }
+            self.handle(&message);
+        }
+    }
+
+    /// Reads the next message, or `None` if the connection was closed. Data received before
+    /// a read timeout is kept in the buffer.
+    fn read_message(&mut self) -> std::io::Result<Option<Message>> {
+        let mut chunk = [0; 4096];
+        loop {
+            if let Some((message, len)) = Message::parse(&self.buffer)? {
+                self.buffer.drain(..len);
+                return Ok(Some(message));
+            }
+            let len = self.stream.read(&mut chunk)?;
+            if len == 0 {
+                return Ok(None);
+            }
+            self.buffer.extend(&chunk[..len]);
+        }
+    }
+
+    fn handle(&mut self, message: &Message) {
+        if message.header.msg_id == MSG_ALARM_EVENT {
+            let now = std::time::SystemTime::now();
+            for alarm in AlarmEvent::parse_list(&message.xml_body()) {
+                self.pending.extend(self.detector.update(alarm.channel, &alarm.state, now));
+            }
+        }
+    }
+}
+
+impl Iterator for AlarmListener {
+    type Item = anyhow::Result<Event>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                return Some(Ok(event));
+            }
+            if self.closed {
+                return None;
+            }
+            match self.read_message() {
+                Ok(Some(message)) => self.handle(&message),
+                Ok(None) => self.closed = true,
+                Err(err) if matches!(err.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                    if let Err(err) = self.send(MSG_PING, CLASS_MODERN_OFFSET, None) {
+                        self.closed = true;
+                        return Some(Err(err.into()));
+                    }
+                },
+                Err(err) => {
+                    self.closed = true;
+                    return Some(Err(err.into()));
+                },
+            }
+        }
+    }
+}
+
+impl Drop for AlarmListener {
+    fn drop(&mut self) {
+        if !self.closed {
+            let _ = self.send(MSG_LOGOUT, CLASS_MODERN_OFFSET, None);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_alarms() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8" ?>
+<body>
+<AlarmEventList version="1.1">
+<AlarmEvent version="1.1">
+<channelId>0</channelId>
+<status>MD</status>
+<AItype>people,vehicle</AItype>
+<recording>1</recording>
+<timeStamp>0</timeStamp>
+</AlarmEvent>
+<AlarmEvent version="1.1">
+<channelId>3</channelId>
+<status>none</status>
+<recording>0</recording>
+<timeStamp>0</timeStamp>
+</AlarmEvent>
+</AlarmEventList>
+</body>"#;
+        let events = AlarmEvent::parse_list(xml);
+        assert_eq!(2, events.len());
+        assert_eq!(DetectionState { motion: true, person: true, vehicle: true, animal: false }, events[0].state);
+        assert_eq!(AlarmEvent { channel: 3, state: DetectionState::default() }, events[1]);
+    }
+}
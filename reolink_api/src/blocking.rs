@@ -1,16 +1,47 @@
 use std::fmt::Debug;
+use std::io::Read;
 use std::ops::Deref;
-use std::sync::Arc;
+use std::sync::{Arc, OnceLock};
 use std::time::Duration;
-use reqwest::{Method, Url};
+use reqwest::{Method, StatusCode, Url};
+use reqwest::header::{HeaderMap, CONTENT_TYPE};
 use bytes::Bytes;
 use serde::Serialize;
 use tracing::info;
-use crate::api::{AuthenticationType, BinaryEndpoint, JsonEndpoint};
+use crate::api::{ApiError, AuthenticationType, BinaryEndpoint, Channel, ConfigEndpoint, JsonEndpoint, Validate};
+use crate::api::record::snapshot::{Snapshot, SnapshotRequest};
 use crate::api::security::login::LoginRequest;
 use crate::api::security::logout::LogoutRequest;
 use crate::common;
+use crate::diff;
+use crate::diff::FieldDiff;
+use crate::live::flv::{FlvReader, FlvTag, VideoData};
+use crate::live::mp4::Mp4Writer;
+use events::EventWatcher;
+use crate::live::urls::{DeviceStreams, StreamUrls, UrlAuth};
+use crate::api::network::get_net_port::{GetNetPortRequest, NetPort};
+use crate::api::network::get_rtsp_url::GetRtspUrlRequest;
+use crate::api::system::get_dev_info::GetDevInfoRequest;
 use crate::common::{Credentials, Token};
+#[cfg(feature = "chrono")]
+use crate::api::record::nvr_download::{NvrDownload, NvrDownloadRequest, NvrDownloadResponse};
+#[cfg(feature = "chrono")]
+use crate::api::record::search::{Search, SearchRequest, SearchResponse};
+use crate::api::record::StreamType;
+#[cfg(feature = "chrono")]
+use crate::api::record::timeline::Timeline;
+#[cfg(feature = "chrono")]
+use crate::api::record::calendar::RecordingCalendar;
+#[cfg(feature = "chrono")]
+use crate::api::system::get_time::{DeviceTimeZone, GetTimeRequest};
+
+#[cfg(feature = "chrono")]
+pub mod export;
+pub mod events;
+pub mod fleet;
+pub mod replay;
+#[cfg(feature = "chrono")]
+pub mod search;
 
 /// A blocking client for the Reolink API.
 ///
@@ -23,22 +54,86 @@ pub struct ReolinkClient {
 impl Debug for ReolinkClient {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("ReolinkClient")
-            .field("url", &self.inner.url)
+            .field("address", &self.inner.address)
+            .field("url", &self.inner.url.get())
             .field("login", &self.inner.credentials.login)
             .finish()
     }
 }
 
+/// Executes the http requests of a client. Implemented by `reqwest::blocking::Client`, and
+/// by the recording and replay transports of the `replay` module.
+pub trait Transport: Send + Sync {
+    fn execute(&self, request: reqwest::blocking::Request) -> anyhow::Result<TransportResponse>;
+}
+
+/// An http response returned by a `Transport`.
+pub struct TransportResponse {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: Box<dyn Read + Send>,
+}
+
+impl TransportResponse {
+    /// Turns an http error status into an error.
+    pub fn error_for_status(self) -> anyhow::Result<Self> {
+        if self.status.is_client_error() || self.status.is_server_error() {
+            Err(anyhow::anyhow!("HTTP status {}", self.status))
+        } else {
+            Ok(self)
+        }
+    }
+
+    /// Reads the whole response body.
+    pub fn bytes(mut self) -> anyhow::Result<Bytes> {
+        let mut buf = Vec::new();
+        self.body.read_to_end(&mut buf)?;
+        Ok(buf.into())
+    }
+}
+
+impl Transport for reqwest::blocking::Client {
+    fn execute(&self, request: reqwest::blocking::Request) -> anyhow::Result<TransportResponse> {
+        let response = reqwest::blocking::Client::execute(self, request)?;
+        Ok(TransportResponse {
+            status: response.status(),
+            headers: response.headers().clone(),
+            body: Box::new(response),
+        })
+    }
+}
+
+impl <T: Transport> Transport for Arc<T> {
+    fn execute(&self, request: reqwest::blocking::Request) -> anyhow::Result<TransportResponse> {
+        self.deref().execute(request)
+    }
+}
+
 struct InnerClient {
+    /// Used to build requests
     client: reqwest::blocking::Client,
-    url: Url,
+    /// Used to execute requests
+    transport: Box<dyn Transport>,
+    /// Device address, as provided by the user
+    address: String,
+    /// API url, resolved on first use if the address has no scheme
+    url: OnceLock<Url>,
     credentials: Credentials,
+    /// Device time zone, fetched on first use
+    #[cfg(feature = "chrono")]
+    time_zone: OnceLock<DeviceTimeZone>,
+    /// Ports of the network services, fetched on first use
+    net_port: OnceLock<NetPort>,
 }
 
 impl ReolinkClient {
 
     /// Creates a new client with default settings.
     ///
+    /// `url` is either the device's base URL (e.g. `https://192.168.0.42/`) or only its host
+    /// and an optional port (e.g. `192.168.0.42`). In the latter case, HTTPS and HTTP are probed
+    /// when the client is first used, and the url of the API endpoint that answered is kept.
+    ///
     /// **Warning**: TLS certificate validation is disabled. Even if dangerous, this is often
     /// acceptable in home network environments.
     pub fn new(url: &str, login: String, password: String) -> anyhow::Result<Self> {
@@ -54,15 +149,42 @@ impl ReolinkClient {
     pub fn new_with_client(
         client: reqwest::blocking::Client, url: &str, login: String, password: String
     ) -> anyhow::Result<Self> {
+        Self::new_with_transport(client.clone(), client, url, login, password)
+    }
+
+    /// Creates a client whose requests are executed by `transport`, e.g. to record or replay
+    /// exchanges with a device.
+    pub fn new_with_transport(
+        client: reqwest::blocking::Client, transport: impl Transport + 'static, url: &str, login: String, password: String
+    ) -> anyhow::Result<Self> {
+        let resolved = OnceLock::new();
+        if common::has_scheme(url) {
+            resolved.set(common::get_api_url(url)?).unwrap();
+        } else {
+            // Validate the address early
+            common::candidate_api_urls(url)?;
+        }
+
         Ok(ReolinkClient {
             inner: Arc::new(InnerClient {
                 client,
-                url: common::get_api_url(url)?,
+                transport: Box::new(transport),
+                address: url.to_string(),
+                url: resolved,
                 credentials: Credentials::new(login, password),
+                #[cfg(feature = "chrono")]
+                time_zone: OnceLock::new(),
+                net_port: OnceLock::new(),
             })
         })
     }
 
+    /// The url of the device's API endpoint, which is detected if the client was created
+    /// with a device address that has no scheme.
+    pub fn api_url(&self) -> anyhow::Result<&Url> {
+        self.inner.url()
+    }
+
     /// Authenticate and make sure this client has a valid token.
     pub fn login(&self) -> anyhow::Result<()> {
         self.inner.login()
@@ -81,12 +203,292 @@ impl ReolinkClient {
         self.inner.exec_with_details::<Req>(req)
     }
 
+    /// Executes a request after checking it against the range returned by the device. Fails
+    /// with a `ValidationError` listing the allowed values if the request is invalid.
+    pub fn exec_validated<Req: Validate>(&self, req: &Req) -> anyhow::Result<Req::Response> {
+        let (_, _, range) = self.exec_with_details(&req.range_request())?;
+        req.validate(&range)?;
+        self.exec(req)
+    }
+
     pub fn download<Req: BinaryEndpoint>(&self, req: &Req) -> anyhow::Result<Bytes> {
         self.inner.download::<Req>(req)
     }
+
+    /// Captures an image, checking that the response is a JPEG image.
+    pub fn snapshot(&self, req: &SnapshotRequest) -> anyhow::Result<Snapshot> {
+        let jpeg = self.download(req)?;
+        Ok(Snapshot::new(jpeg, std::time::SystemTime::now())?)
+    }
+
+    /// Sends a binary request and returns a reader over the response body, which is streamed
+    /// from the device rather than loaded in memory. Useful for long responses like `Playback`.
+    /// There's no total timeout, only each read is limited by the HTTP client's timeout.
+    pub fn stream<Req: BinaryEndpoint>(&self, req: &Req) -> anyhow::Result<Box<dyn Read + Send>> {
+        Ok(self.inner.open(req, true)?.body)
+    }
+
+    /// Opens the live stream of a channel over HTTP-FLV, authenticated with the client's token.
+    /// The stream is read as it arrives, and never ends unless the device closes it.
+    pub fn live(&self, channel: Channel, stream_type: StreamType) -> anyhow::Result<FlvReader<Box<dyn Read + Send>>> {
+        self.login()?;
+        let port = self.net_port()?.rtmp_port;
+        let url = crate::live::flv_url(self.api_url()?, port, channel, &stream_type, &self.inner.credentials)?;
+        let request = self.inner.client.get(url).build()?;
+        let response = self.inner.transport.execute(request)?.error_for_status()?;
+        Ok(FlvReader::new(response.body)?)
+    }
+
+    /// The RTSP, RTMP and HTTP-FLV urls of a channel's streams, built from the device's ports,
+    /// its kind and the urls returned by `GetRtspUrl` when supported. With `UrlAuth::Token`,
+    /// the client logs in and the urls are only valid until the token expires.
+    pub fn stream_urls(&self, channel: Channel, auth: UrlAuth) -> anyhow::Result<StreamUrls> {
+        if auth == UrlAuth::Token {
+            self.login()?;
+        }
+        let dev_info = self.exec(&GetDevInfoRequest)?.dev_info;
+        let ports = self.net_port()?;
+        let rtsp = match self.exec(&GetRtspUrlRequest { channel }) {
+            Ok(response) => Some(response.rtsp_url),
+            Err(err) if err.downcast_ref::<ApiError>().is_some_and(ApiError::is_not_supported) => None,
+            Err(err) => return Err(err),
+        };
+
+        let device = DeviceStreams {
+            api_url: self.api_url()?,
+            device_type: &dev_info.exact_type,
+            ports,
+            rtsp: rtsp.as_ref(),
+        };
+        device.urls(channel, auth, &self.inner.credentials)
+    }
+
+    /// Watches the motion and AI detections of channels. See `EventWatcher`.
+    pub fn events(&self, channels: impl IntoIterator<Item = Channel>) -> EventWatcher {
+        EventWatcher::new(self.clone(), channels)
+    }
+
+    /// Connects to the device's native protocol port (`mediaPort` in `GetNetPort`) with the
+    /// client's credentials, to receive alarm events as they happen. See `AlarmListener`.
+    #[cfg(feature = "baichuan")]
+    pub fn alarm_listener(&self) -> anyhow::Result<crate::baichuan::AlarmListener> {
+        let port = self.net_port()?.media_port;
+        let url = self.api_url()?;
+        let host = url.host_str().ok_or_else(|| anyhow::anyhow!("No host in '{}'", url))?;
+        let creds = &self.inner.credentials;
+        crate::baichuan::AlarmListener::connect((host, port), &creds.login, &creds.password)
+    }
+
+    /// Records the main stream of a channel to an MP4 file at `path`, for `duration` of stream
+    /// time or until the device ends the stream. Returns the recorded duration.
+    pub fn record_live(&self, channel: Channel, duration: Duration, path: impl AsRef<std::path::Path>) -> anyhow::Result<Duration> {
+        let reader = self.live(channel, StreamType::Main)?;
+        let out = std::io::BufWriter::new(std::fs::File::create(path)?);
+        let mut writer = Mp4Writer::new(out);
+
+        for tag in reader {
+            let tag = tag?;
+            // Elapsed time is tracked by the muxer, as timestamps may be reset by the device
+            if let FlvTag::Video(video) = &tag {
+                if matches!(video.data, VideoData::NalUnits(_)) && writer.duration_at(video.timestamp) as u128 >= duration.as_millis() {
+                    break;
+                }
+            }
+            writer.write_tag(&tag)?;
+        }
+
+        let recorded = Duration::from_millis(writer.duration() as u64);
+        writer.finish()?.into_inner()?.sync_all()?;
+        Ok(recorded)
+    }
+
+    /// Updates the configuration of a channel: fetches it, applies `f` and sends it back with
+    /// the matching `Set` command, after validating it against the range returned by the device.
+    /// The configuration is then read again to check that the change was applied, and the
+    /// updated configuration is returned.
+    ///
+    /// Example: `client.update::<GetRecordingRequest>(0, |cfg| cfg.overwrite = true)?`
+    pub fn update<E: ConfigEndpoint>(&self, channel: Channel, f: impl FnOnce(&mut E::Config)) -> anyhow::Result<E::Config> {
+        let (response, _, range) = self.exec_with_details(&E::get_request(channel))?;
+        let mut config = E::config(response);
+        f(&mut config);
+        self.set_config::<E>(channel, config, &range)
+    }
+
+    /// Resets the configuration of a channel to the factory defaults returned by the device,
+    /// and returns the updated configuration.
+    pub fn reset_to_default<E: ConfigEndpoint>(&self, channel: Channel) -> anyhow::Result<E::Config> {
+        let (_, initial, range) = self.exec_with_details(&E::get_request(channel))?;
+        self.set_config::<E>(channel, E::initial_config(initial), &range)
+    }
+
+    /// Lists the fields of a channel's configuration that differ from the factory defaults.
+    pub fn diff_from_default<E: ConfigEndpoint>(&self, channel: Channel) -> anyhow::Result<Vec<FieldDiff>> {
+        let (response, initial, _) = self.exec_with_details(&E::get_request(channel))?;
+        let mut default = E::initial_config(initial);
+        E::set_channel(&mut default, channel);
+        diff::diff(&E::config(response), &default)
+    }
+
+    /// The device's time zone and DST rules, fetched with `GetTime` on first use.
+    #[cfg(feature = "chrono")]
+    pub fn time_zone(&self) -> anyhow::Result<&DeviceTimeZone> {
+        if let Some(tz) = self.inner.time_zone.get() {
+            return Ok(tz);
+        }
+        let tz = DeviceTimeZone::from(&self.exec(&GetTimeRequest)?);
+        Ok(self.inner.time_zone.get_or_init(|| tz))
+    }
+
+    /// The ports of the device's network services, fetched with `GetNetPort` on first use.
+    pub fn net_port(&self) -> anyhow::Result<&NetPort> {
+        if let Some(ports) = self.inner.net_port.get() {
+            return Ok(ports);
+        }
+        let ports = self.exec(&GetNetPortRequest)?.net_port;
+        Ok(self.inner.net_port.get_or_init(|| ports))
+    }
+
+    /// Searches the recordings of a channel between two instants, which are converted to the
+    /// device's local time. Date-times in the response are device local times, and can be
+    /// converted with `time_zone()`.
+    #[cfg(feature = "chrono")]
+    pub fn search_utc(
+        &self, channel: Channel, stream_type: StreamType, start: chrono::DateTime<chrono::Utc>, end: chrono::DateTime<chrono::Utc>, only_status: bool
+    ) -> anyhow::Result<SearchResponse> {
+        let tz = self.time_zone()?;
+        self.exec(&SearchRequest {
+            search: Search {
+                channel,
+                only_status,
+                stream_type,
+                start_time: tz.from_utc(&start)?,
+                end_time: tz.from_utc(&end)?,
+            }
+        })
+    }
+
+    /// Lists the recording files of a channel between two device local date-times, which can
+    /// span several days. Days are searched lazily while iterating, skipping those that have
+    /// no recordings.
+    #[cfg(feature = "chrono")]
+    pub fn search_files(
+        &self, channel: Channel, stream_type: StreamType, start: chrono::NaiveDateTime, end: chrono::NaiveDateTime
+    ) -> search::SearchFiles {
+        search::SearchFiles::new(self.clone(), channel, stream_type, start, end)
+    }
+
+    /// Days of a month that have recordings on a channel, on either the main or sub stream.
+    #[cfg(feature = "chrono")]
+    pub fn recording_calendar(&self, channel: Channel, year: i32, month: u32) -> anyhow::Result<RecordingCalendar> {
+        let start = chrono::NaiveDate::from_ymd_opt(year, month, 1)
+            .ok_or_else(|| anyhow::anyhow!("invalid month {year}-{month}"))?;
+        let end = start + chrono::Months::new(1) - chrono::TimeDelta::days(1);
+        self.recording_calendar_range(channel, start, end)
+    }
+
+    /// Days between `start` and `end` (inclusive) that have recordings on a channel, on either
+    /// the main or sub stream. The range can span several months.
+    #[cfg(feature = "chrono")]
+    pub fn recording_calendar_range(&self, channel: Channel, start: chrono::NaiveDate, end: chrono::NaiveDate) -> anyhow::Result<RecordingCalendar> {
+        let mut calendar = RecordingCalendar::new(start, end);
+        for stream_type in [StreamType::Main, StreamType::Sub] {
+            let response = self.exec(&SearchRequest {
+                search: Search {
+                    channel,
+                    only_status: true,
+                    stream_type,
+                    start_time: start.and_time(chrono::NaiveTime::MIN).try_into()?,
+                    end_time: end.and_hms_opt(23, 59, 59).unwrap().try_into()?,
+                }
+            })?;
+            calendar.add_statuses(response.search_result.status.iter().flatten());
+        }
+        Ok(calendar)
+    }
+
+    /// Builds the recording timeline of a channel between two device local date-times, merging
+    /// adjacent files into continuous segments.
+    #[cfg(feature = "chrono")]
+    pub fn timeline(
+        &self, channel: Channel, stream_type: StreamType, start: chrono::NaiveDateTime, end: chrono::NaiveDateTime
+    ) -> anyhow::Result<Timeline> {
+        let files = self.search_files(channel, stream_type, start, end).collect::<anyhow::Result<Vec<_>>>()?;
+        Ok(Timeline::from_files(&files, Timeline::DEFAULT_MAX_GAP)?)
+    }
+
+    /// Lists the NVR recording files of a channel between two instants, which are converted
+    /// to the device's local time.
+    #[cfg(feature = "chrono")]
+    pub fn nvr_download_utc(
+        &self, channel: Channel, stream_type: StreamType, start: chrono::DateTime<chrono::Utc>, end: chrono::DateTime<chrono::Utc>
+    ) -> anyhow::Result<NvrDownloadResponse> {
+        let tz = self.time_zone()?;
+        self.exec(&NvrDownloadRequest {
+            nvr_download: NvrDownload {
+                channel,
+                stream_type,
+                start_time: tz.from_utc(&start)?,
+                end_time: tz.from_utc(&end)?,
+            }
+        })
+    }
+
+    /// Exports the NVR recordings of a channel between two device local date-times to `dir`.
+    /// Files are listed with `NvrDownload` and fetched with `Download`, checking their size and
+    /// retrying failed downloads. Files already present with the expected size are skipped,
+    /// so that an interrupted export can be resumed by running it again.
+    ///
+    /// The returned manifest is also written to `manifest.json` in `dir`. Files that could not be
+    /// downloaded are listed with their error rather than failing the whole export.
+    #[cfg(feature = "chrono")]
+    pub fn export_range(
+        &self, channel: Channel, stream_type: StreamType, start: chrono::NaiveDateTime, end: chrono::NaiveDateTime, dir: impl AsRef<std::path::Path>
+    ) -> anyhow::Result<export::ExportManifest> {
+        export::export_range(self, channel, stream_type, start, end, dir.as_ref())
+    }
+
+    /// Validates and sends a configuration, and checks that it was applied.
+    fn set_config<E: ConfigEndpoint>(&self, channel: Channel, mut config: E::Config, range: &E::Range) -> anyhow::Result<E::Config> {
+        E::set_channel(&mut config, channel);
+        let set_request = E::set_request(channel, config.clone());
+        set_request.validate(range)?;
+        self.exec(&set_request)?;
+
+        let updated = E::config(self.exec(&E::get_request(channel))?);
+        if updated != config {
+            return Err(anyhow::anyhow!("The configuration change was not applied by the '{}' API", <E::Set as JsonEndpoint>::CMD));
+        }
+        Ok(updated)
+    }
 }
 
 impl InnerClient {
+    fn url(&self) -> anyhow::Result<&Url> {
+        if let Some(url) = self.url.get() {
+            return Ok(url);
+        }
+        // Concurrent calls may probe in parallel, but will resolve the same url.
+        let url = self.detect_url()?;
+        Ok(self.url.get_or_init(|| url))
+    }
+
+    /// Probes candidate urls, and returns the first one that returns an API response.
+    fn detect_url(&self) -> anyhow::Result<Url> {
+        for url in common::candidate_api_urls(&self.address)? {
+            let request = common::prepare_probe_request(&self.client, &url)?;
+            match self.transport.execute(request).and_then(|r| r.bytes()) {
+                Ok(bytes) if common::is_api_response(&bytes) => {
+                    info!("Found API endpoint at {}", url);
+                    return Ok(url);
+                },
+                Ok(_) => info!("No API endpoint at {}", url),
+                Err(err) => info!("No API endpoint at {}: {}", url, err),
+            }
+        }
+        Err(anyhow::anyhow!("Could not find an API endpoint for '{}' using HTTPS or HTTP", self.address))
+    }
+
     fn logout(&self) -> anyhow::Result<()> {
         let creds = &self.credentials;
         let token = creds.token.read().unwrap();
@@ -138,9 +540,9 @@ impl InnerClient {
 
     fn exec<Req: JsonEndpoint>(&self, req: &Req) -> anyhow::Result<Req::Response> {
         self.ensure_token_if_needed(Req::AUTH)?;
-        let request = common::prepare_json_request(&self.client, &self.url, req, &self.credentials, false)?;
+        let request = common::prepare_json_request(&self.client, self.url()?, req, &self.credentials, false)?;
 
-        let response = self.client
+        let response = self.transport
             .execute(request)?
             .error_for_status()?
             .bytes()?;
@@ -149,9 +551,9 @@ impl InnerClient {
 
     fn exec_with_details<Req: JsonEndpoint>(&self, req: &Req) -> anyhow::Result<(Req::Response, Req::Initial, Req::Range)> {
         self.ensure_token_if_needed(Req::AUTH)?;
-        let request = common::prepare_json_request(&self.client, &self.url, req, &self.credentials, true)?;
+        let request = common::prepare_json_request(&self.client, self.url()?, req, &self.credentials, true)?;
 
-        let response = self.client
+        let response = self.transport
             .execute(request)?
             .error_for_status()?
             .bytes()?;
@@ -159,12 +561,25 @@ impl InnerClient {
     }
 
     fn download<Req: BinaryEndpoint>(&self, req: &Req) -> anyhow::Result<Bytes> {
+        self.open(req, false)?.bytes()
+    }
+
+    /// Sends a binary request, returning the response before its body is read. Streamed
+    /// requests have no total timeout, see `common::prepare_download_request`.
+    fn open<Req: BinaryEndpoint>(&self, req: &Req, streamed: bool) -> anyhow::Result<TransportResponse> {
         self.ensure_token_if_needed(Req::AUTH)?;
-        let req = common::prepare_download_request(&self.client, &self.url, req, &self.credentials)?;
-        let resp = self.client
+        let req = common::prepare_download_request(&self.client, self.url()?, req, &self.credentials, streamed)?;
+        let resp = self.transport
             .execute(req)?
             .error_for_status()?;
-        Ok(resp.bytes()?)
+
+        let is_json = resp.headers.get(CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|v| v.starts_with("application/json"));
+        if is_json {
+            return Err(common::parse_binary_error(&resp.bytes()?));
+        }
+        Ok(resp)
     }
 }
 
@@ -0,0 +1,177 @@
+//! Polling of motion and AI detection states into events.
+
+use std::collections::{BTreeSet, VecDeque};
+use std::ops::ControlFlow;
+use std::time::{Duration, Instant, SystemTime};
+use crate::api::{ApiError, Channel};
+use crate::api::ai::get_ai_state::GetAiStateRequest;
+use crate::api::alarm::get_md_state::GetMdStateRequest;
+use crate::api::system::get_channel_status::GetChannelStatusRequest;
+use crate::blocking::ReolinkClient;
+use crate::events::{DetectionState, EdgeDetector, Event};
+
+/// Polls the detection states of channels and emits an event when a detection starts or ends.
+///
+/// Events are read with `poll`, by iterating on the watcher (which blocks between polls and
+/// never ends), or with a callback using `run`. Channels whose battery camera is sleeping
+/// aren't polled, to avoid waking them up: they're checked again with an exponential backoff.
+pub struct EventWatcher {
+    client: ReolinkClient,
+    channels: Vec<WatchedChannel>,
+    interval: Duration,
+    max_backoff: Duration,
+    detector: EdgeDetector,
+    pending: VecDeque<Event>,
+    /// Cleared if the device doesn't support `GetAiState`
+    ai_supported: bool,
+    /// Cleared if the device doesn't support `GetChannelstatus`
+    channel_status_supported: bool,
+}
+
+struct WatchedChannel {
+    channel: Channel,
+    next_poll: Instant,
+    /// Delay before the next check, if the channel is sleeping
+    backoff: Option<Duration>,
+}
+
+impl EventWatcher {
+    pub const DEFAULT_INTERVAL: Duration = Duration::from_secs(1);
+    pub const DEFAULT_MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+    pub fn new(client: ReolinkClient, channels: impl IntoIterator<Item = Channel>) -> Self {
+        let now = Instant::now();
+        let channels = channels.into_iter()
+            .collect::<BTreeSet<_>>().into_iter()
+            .map(|channel| WatchedChannel { channel, next_poll: now, backoff: None })
+            .collect();
+
+        EventWatcher {
+            client,
+            channels,
+            interval: Self::DEFAULT_INTERVAL,
+            max_backoff: Self::DEFAULT_MAX_BACKOFF,
+            detector: EdgeDetector::new(),
+            pending: VecDeque::new(),
+            ai_supported: true,
+            channel_status_supported: true,
+        }
+    }
+
+    /// Sets the delay between two polls of a channel.
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Sets the maximum delay between two checks of a sleeping channel.
+    pub fn with_max_backoff(mut self, max_backoff: Duration) -> Self {
+        self.max_backoff = max_backoff;
+        self
+    }
+
+    /// Polls the channels that are due, without waiting, and returns the events that started
+    /// or ended. If a request fails, events already detected are returned by the next call.
+    pub fn poll(&mut self) -> anyhow::Result<Vec<Event>> {
+        self.poll_due()?;
+        Ok(self.pending.drain(..).collect())
+    }
+
+    /// Reads events until `f` breaks or a request fails.
+    pub fn run(&mut self, mut f: impl FnMut(Event) -> ControlFlow<()>) -> anyhow::Result<()> {
+        for event in self.by_ref() {
+            if f(event?).is_break() {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    fn poll_due(&mut self) -> anyhow::Result<()> {
+        let now = Instant::now();
+        let due = self.channels.iter().enumerate()
+            .filter(|(_, c)| c.next_poll <= now)
+            .map(|(i, _)| i)
+            .collect::<Vec<_>>();
+        if due.is_empty() {
+            return Ok(());
+        }
+
+        let sleeping = self.sleeping_channels()?;
+        for i in due {
+            let watched = &mut self.channels[i];
+            let channel = watched.channel;
+
+            if sleeping.contains(&channel) {
+                let backoff = watched.backoff.map_or(self.interval * 2, |b| b * 2).min(self.max_backoff);
+                watched.backoff = Some(backoff);
+                watched.next_poll = now + backoff;
+                // A sleeping camera doesn't detect anything
+                self.pending.extend(self.detector.end_all(channel, SystemTime::now()));
+                continue;
+            }
+
+            // Scheduled before the requests, so that failures don't cause a busy loop
+            watched.backoff = None;
+            watched.next_poll = now + self.interval;
+            let state = self.state(channel)?;
+            self.pending.extend(self.detector.update(channel, &state, SystemTime::now()));
+        }
+        Ok(())
+    }
+
+    /// Channels whose camera is sleeping. Devices without battery cameras may not support
+    /// `GetChannelstatus`.
+    fn sleeping_channels(&mut self) -> anyhow::Result<BTreeSet<Channel>> {
+        if !self.channel_status_supported {
+            return Ok(BTreeSet::new());
+        }
+        match self.client.exec(&GetChannelStatusRequest) {
+            Ok(response) => Ok(response.status.iter().filter(|s| s.sleep).map(|s| s.channel).collect()),
+            Err(err) if is_not_supported(&err) => {
+                self.channel_status_supported = false;
+                Ok(BTreeSet::new())
+            },
+            Err(err) => Err(err),
+        }
+    }
+
+    fn state(&mut self, channel: Channel) -> anyhow::Result<DetectionState> {
+        let md = self.client.exec(&GetMdStateRequest { channel })?;
+        let ai = if self.ai_supported {
+            match self.client.exec(&GetAiStateRequest { channel }) {
+                Ok(ai) => Some(ai),
+                Err(err) if is_not_supported(&err) => {
+                    self.ai_supported = false;
+                    None
+                },
+                Err(err) => return Err(err),
+            }
+        } else {
+            None
+        };
+        Ok(DetectionState::new(&md, ai.as_ref()))
+    }
+}
+
+fn is_not_supported(err: &anyhow::Error) -> bool {
+    err.downcast_ref::<ApiError>().is_some_and(ApiError::is_not_supported)
+}
+
+/// Blocks until a detection starts or ends. Ends only if no channel is watched.
+impl Iterator for EventWatcher {
+    type Item = anyhow::Result<Event>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                return Some(Ok(event));
+            }
+            let next_poll = self.channels.iter().map(|c| c.next_poll).min()?;
+            std::thread::sleep(next_poll.saturating_duration_since(Instant::now()));
+            if let Err(err) = self.poll_due() {
+                return Some(Err(err));
+            }
+        }
+    }
+}
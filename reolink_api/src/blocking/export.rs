@@ -0,0 +1,171 @@
+//! Export of NVR recordings to a local directory.
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use tracing::info;
+use crate::api::Channel;
+use crate::api::record::{DateTime, StreamType};
+use crate::api::record::download::DownloadRequest;
+use crate::api::record::nvr_download::{NvrDownload, NvrDownloadRequest, NvrFile};
+use crate::blocking::ReolinkClient;
+
+/// Number of times a failed download is retried.
+const RETRIES: u32 = 2;
+
+const MANIFEST: &str = "manifest.json";
+
+/// The result of `ReolinkClient::export_range`, also written to `manifest.json`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportManifest {
+    pub channel: Channel,
+    pub stream_type: StreamType,
+    pub start_time: DateTime,
+    pub end_time: DateTime,
+    pub files: Vec<ExportedFile>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportedFile {
+    /// Name of the file on the device
+    pub source: String,
+    /// Name of the local file, in the export directory
+    pub name: String,
+    /// Size announced by the device
+    pub size: u64,
+    pub status: ExportStatus,
+    /// Number of download attempts, zero if the file was skipped
+    pub attempts: u32,
+    /// Error of the last attempt, if the download failed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportStatus {
+    Downloaded,
+    /// Already present with the expected size
+    Skipped,
+    Failed,
+}
+
+impl ExportManifest {
+    /// Have all files been exported?
+    pub fn is_complete(&self) -> bool {
+        self.files.iter().all(|f| f.status != ExportStatus::Failed)
+    }
+
+    pub fn failed(&self) -> impl Iterator<Item = &ExportedFile> {
+        self.files.iter().filter(|f| f.status == ExportStatus::Failed)
+    }
+}
+
+pub(crate) fn export_range(
+    client: &ReolinkClient, channel: Channel, stream_type: StreamType, start: NaiveDateTime, end: NaiveDateTime, dir: &Path
+) -> anyhow::Result<ExportManifest> {
+    std::fs::create_dir_all(dir)?;
+
+    let (start_time, end_time) = (DateTime::try_from(start)?, DateTime::try_from(end)?);
+    let response = client.exec(&NvrDownloadRequest {
+        nvr_download: NvrDownload {
+            channel,
+            stream_type: stream_type.clone(),
+            start_time: start_time.clone(),
+            end_time: end_time.clone(),
+        }
+    })?;
+
+    let mut manifest = ExportManifest {
+        channel,
+        stream_type,
+        start_time,
+        end_time,
+        files: Vec::new(),
+    };
+
+    // Written after each file, so that an interrupted export still has a manifest
+    for file in &response.file_list {
+        manifest.files.push(export_file(client, file, dir));
+        write_manifest(&manifest, dir)?;
+    }
+    if response.file_list.is_empty() {
+        write_manifest(&manifest, dir)?;
+    }
+
+    Ok(manifest)
+}
+
+/// Exports a file. Errors are recorded in the result rather than returned.
+fn export_file(client: &ReolinkClient, file: &NvrFile, dir: &Path) -> ExportedFile {
+    // Home Hub names are absolute paths
+    let name = file.name.rsplit('/').next().unwrap_or(&file.name).to_string();
+
+    let mut result = ExportedFile {
+        source: file.name.clone(),
+        name: name.clone(),
+        size: file.size,
+        status: ExportStatus::Skipped,
+        attempts: 0,
+        error: None,
+    };
+
+    if name.is_empty() || name == MANIFEST {
+        result.status = ExportStatus::Failed;
+        result.error = Some(format!("Invalid file name '{}'", file.name));
+        return result;
+    }
+    let path = dir.join(&name);
+
+    if std::fs::metadata(&path).is_ok_and(|m| m.len() == file.size) {
+        return result;
+    }
+
+    let part = dir.join(format!("{name}.part"));
+    while result.attempts <= RETRIES {
+        result.attempts += 1;
+        let downloaded = download(client, file, &name, &part)
+            .and_then(|_| Ok(std::fs::rename(&part, &path)?));
+        match downloaded {
+            Ok(()) => {
+                result.status = ExportStatus::Downloaded;
+                result.error = None;
+                return result;
+            },
+            Err(err) => {
+                info!("Download of {} failed (attempt {}): {}", file.name, result.attempts, err);
+                result.error = Some(err.to_string());
+            }
+        }
+    }
+
+    let _ = std::fs::remove_file(&part);
+    result.status = ExportStatus::Failed;
+    result
+}
+
+/// Downloads a file to `path`, checking its size.
+fn download(client: &ReolinkClient, file: &NvrFile, name: &str, path: &Path) -> anyhow::Result<()> {
+    let mut response = client.inner.open(&DownloadRequest {
+        source: file.name.clone(),
+        output: Some(name.to_string()),
+    }, false)?;
+
+    let mut out = BufWriter::new(File::create(path)?);
+    let size = std::io::copy(&mut response.body, &mut out)?;
+    out.into_inner()?.sync_all()?;
+
+    if size != file.size {
+        return Err(anyhow::anyhow!("Expected {} bytes, got {}", file.size, size));
+    }
+    Ok(())
+}
+
+fn write_manifest(manifest: &ExportManifest, dir: &Path) -> anyhow::Result<()> {
+    let mut out = BufWriter::new(File::create(dir.join(MANIFEST))?);
+    serde_json::to_writer_pretty(&mut out, manifest)?;
+    out.flush()?;
+    Ok(())
+}
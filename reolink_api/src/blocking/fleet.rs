@@ -0,0 +1,197 @@
+//! Operations on a fleet of devices.
+
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use crate::api::system::get_channel_status::{GetChannelStatusRequest, GetChannelStatusResponse};
+use crate::api::system::get_dev_info::{DevInfo, GetDevInfoRequest};
+use crate::blocking::ReolinkClient;
+
+/// A set of named devices, each with its own client and credentials, on which operations can
+/// be run in parallel.
+///
+/// Operations never stop on the first failure: the result of each device is collected, in the
+/// order the devices were added to the fleet.
+pub struct Fleet {
+    devices: Vec<(String, ReolinkClient)>,
+    parallelism: usize,
+}
+
+/// The result of an operation on a device of a fleet.
+#[derive(Debug)]
+pub struct DeviceResult<T> {
+    /// Name of the device in the fleet
+    pub name: String,
+    pub result: anyhow::Result<T>,
+}
+
+impl Default for Fleet {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Fleet {
+    /// Default number of devices that are accessed concurrently.
+    pub const DEFAULT_PARALLELISM: usize = 8;
+
+    /// Creates an empty fleet.
+    pub fn new() -> Self {
+        Fleet {
+            devices: Vec::new(),
+            parallelism: Self::DEFAULT_PARALLELISM,
+        }
+    }
+
+    /// Sets the maximum number of devices that are accessed concurrently.
+    pub fn with_parallelism(mut self, parallelism: usize) -> Self {
+        self.parallelism = parallelism.max(1);
+        self
+    }
+
+    /// Adds a device to the fleet. Fails if a device with the same name already exists.
+    pub fn add(&mut self, name: impl Into<String>, client: ReolinkClient) -> anyhow::Result<()> {
+        let name = name.into();
+        if self.get(&name).is_some() {
+            return Err(anyhow::anyhow!("Device '{}' is already part of the fleet", name));
+        }
+        self.devices.push((name, client));
+        Ok(())
+    }
+
+    /// Creates a client with default settings and adds it to the fleet.
+    pub fn add_device(&mut self, name: impl Into<String>, url: &str, login: String, password: String) -> anyhow::Result<()> {
+        self.add(name, ReolinkClient::new(url, login, password)?)
+    }
+
+    /// Returns the client of a device.
+    pub fn get(&self, name: &str) -> Option<&ReolinkClient> {
+        self.devices.iter().find(|(n, _)| n == name).map(|(_, c)| c)
+    }
+
+    /// Names of the devices in the fleet.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.devices.iter().map(|(n, _)| n.as_str())
+    }
+
+    pub fn len(&self) -> usize {
+        self.devices.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.devices.is_empty()
+    }
+
+    /// Runs an operation on all devices of the fleet.
+    pub fn run<T: Send>(
+        &self, op: impl Fn(&str, &ReolinkClient) -> anyhow::Result<T> + Sync
+    ) -> Vec<DeviceResult<T>> {
+        self.run_filtered(|_, _| true, op)
+    }
+
+    /// Runs an operation on the devices of the fleet selected by `filter`.
+    pub fn run_filtered<T: Send>(
+        &self,
+        filter: impl Fn(&str, &ReolinkClient) -> bool,
+        op: impl Fn(&str, &ReolinkClient) -> anyhow::Result<T> + Sync
+    ) -> Vec<DeviceResult<T>> {
+        let devices = self.devices.iter()
+            .filter(|(name, client)| filter(name, client))
+            .collect::<Vec<_>>();
+
+        let next = AtomicUsize::new(0);
+        let results = Mutex::new((0..devices.len()).map(|_| None).collect::<Vec<_>>());
+
+        std::thread::scope(|scope| {
+            for _ in 0..self.parallelism.min(devices.len()) {
+                scope.spawn(|| {
+                    loop {
+                        let i = next.fetch_add(1, Ordering::Relaxed);
+                        let Some((name, client)) = devices.get(i) else {
+                            break;
+                        };
+                        let result = op(name, client);
+                        results.lock().unwrap()[i] = Some(DeviceResult { name: name.clone(), result });
+                    }
+                });
+            }
+        });
+
+        results.into_inner().unwrap().into_iter()
+            // All slots have been filled once the scope has ended
+            .map(|r| r.expect("missing device result"))
+            .collect()
+    }
+
+    /// Gets the device information of all devices of the fleet.
+    pub fn inventory(&self) -> Vec<DeviceResult<DevInfo>> {
+        self.run(|_, client| Ok(client.exec(&GetDevInfoRequest)?.dev_info))
+    }
+
+    /// Gets the channel statuses of all devices of the fleet.
+    pub fn health(&self) -> Vec<DeviceResult<GetChannelStatusResponse>> {
+        self.run(|_, client| client.exec(&GetChannelStatusRequest))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn fleet(count: usize) -> anyhow::Result<Fleet> {
+        let mut fleet = Fleet::new().with_parallelism(3);
+        for i in 0..count {
+            fleet.add_device(format!("cam-{}", i), "http://127.0.0.1:9/", "admin".to_string(), "pass".to_string())?;
+        }
+        Ok(fleet)
+    }
+
+    #[test]
+    fn test_results_in_order() -> anyhow::Result<()> {
+        let fleet = fleet(10)?;
+        let results = fleet.run(|name, _| {
+            if name == "cam-4" {
+                Err(anyhow::anyhow!("unreachable"))
+            } else {
+                Ok(name.len())
+            }
+        });
+
+        assert_eq!(10, results.len());
+        for (i, r) in results.iter().enumerate() {
+            assert_eq!(format!("cam-{}", i), r.name);
+            assert_eq!(i == 4, r.result.is_err());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_bounded_parallelism() -> anyhow::Result<()> {
+        let fleet = fleet(10)?;
+        let running = AtomicUsize::new(0);
+        let max_running = AtomicUsize::new(0);
+
+        let results = fleet.run(|_, _| {
+            let count = running.fetch_add(1, Ordering::SeqCst) + 1;
+            max_running.fetch_max(count, Ordering::SeqCst);
+            std::thread::sleep(Duration::from_millis(20));
+            running.fetch_sub(1, Ordering::SeqCst);
+            Ok(())
+        });
+
+        assert_eq!(10, results.len());
+        assert!(max_running.load(Ordering::SeqCst) <= 3);
+        Ok(())
+    }
+
+    #[test]
+    fn test_filter() -> anyhow::Result<()> {
+        let mut fleet = fleet(5)?;
+        assert!(fleet.add_device("cam-1", "http://127.0.0.1:9/", "admin".to_string(), "pass".to_string()).is_err());
+
+        let results = fleet.run_filtered(|name, _| name != "cam-1", |name, _| Ok(name.to_string()));
+        let names = results.into_iter().map(|r| r.result.unwrap()).collect::<Vec<_>>();
+        assert_eq!(vec!["cam-0", "cam-2", "cam-3", "cam-4"], names);
+        Ok(())
+    }
+}
@@ -0,0 +1,304 @@
+//! Record and replay exchanges with a device.
+//!
+//! `RecordingTransport` wraps another transport and keeps the requests and responses it
+//! executes, with credentials redacted, so that they can be saved to a fixture file.
+//! `ReplayTransport` serves the responses of a fixture file, to test code that uses a client
+//! deterministically and without a device.
+//!
+//! ```no_run
+//! # use std::sync::Arc;
+//! # use reolink_api::blocking::ReolinkClient;
+//! # use reolink_api::blocking::replay::*;
+//! // Record
+//! let http = reolink_api::reqwest::blocking::Client::new();
+//! let recorder = Arc::new(RecordingTransport::new(http.clone()));
+//! let client = ReolinkClient::new_with_transport(http, recorder.clone(), "https://192.168.0.42/", "admin".to_string(), "pass".to_string())?;
+//! // ... use the client
+//! recorder.save("tests/fixtures/replay/my-test.json")?;
+//!
+//! // Replay
+//! let replay = ReplayTransport::load("tests/fixtures/replay/my-test.json")?;
+//! let client = ReolinkClient::new_with_transport(reolink_api::reqwest::blocking::Client::new(), replay, "https://192.168.0.42/", "admin".to_string(), "pass".to_string())?;
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+use std::collections::BTreeMap;
+use std::io::Cursor;
+use std::path::Path;
+use std::sync::Mutex;
+use reqwest::StatusCode;
+use reqwest::header::{HeaderMap, HeaderValue, CONTENT_TYPE};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use crate::blocking::{Transport, TransportResponse};
+
+/// Query parameters that hold credentials. They're not recorded, and are ignored when
+/// matching requests.
+const CREDENTIAL_PARAMS: [&str; 3] = ["user", "password", "token"];
+
+/// Query parameters that change on every request, like the random `rs` of `Snap`. They're not
+/// recorded either, so that requests can be matched.
+const VOLATILE_PARAMS: [&str; 1] = ["rs"];
+
+/// JSON fields that hold credentials, in requests (`Login`, `AddUser`) and responses (`GetUser`).
+const CREDENTIAL_FIELDS: [&str; 2] = ["userName", "password"];
+
+const REDACTED: &str = "REDACTED";
+
+/// The content of a fixture file.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Fixture {
+    pub exchanges: Vec<Exchange>,
+}
+
+/// A request and its response.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Exchange {
+    /// Query parameters, without credentials and volatile parameters
+    pub query: BTreeMap<String, String>,
+    /// JSON request body, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request: Option<JsonValue>,
+    pub status: u16,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<String>,
+    /// Response body, if it's JSON
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response: Option<JsonValue>,
+    /// Response body as a hex string, if it's not JSON
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response_hex: Option<String>,
+}
+
+impl Fixture {
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Fixture> {
+        let path = path.as_ref();
+        let file = std::fs::File::open(path)
+            .map_err(|e| anyhow::anyhow!("Cannot open fixture '{}': {}", path.display(), e))?;
+        Ok(serde_json::from_reader(std::io::BufReader::new(file))?)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let mut json = serde_json::to_string_pretty(self)?;
+        json.push('\n');
+        Ok(std::fs::write(path, json)?)
+    }
+}
+
+//-------------------------------------------------------------------------------------------------
+
+/// A transport that records the exchanges executed by another transport.
+pub struct RecordingTransport<T: Transport> {
+    inner: T,
+    exchanges: Mutex<Vec<Exchange>>,
+}
+
+impl <T: Transport> RecordingTransport<T> {
+    pub fn new(inner: T) -> Self {
+        RecordingTransport {
+            inner,
+            exchanges: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// The exchanges recorded so far.
+    pub fn fixture(&self) -> Fixture {
+        Fixture { exchanges: self.exchanges.lock().unwrap().clone() }
+    }
+
+    /// Saves the exchanges recorded so far to a fixture file.
+    pub fn save(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        self.fixture().save(path)
+    }
+}
+
+impl <T: Transport> Transport for RecordingTransport<T> {
+    fn execute(&self, request: reqwest::blocking::Request) -> anyhow::Result<TransportResponse> {
+        let (query, body) = request_key(&request);
+        let response = self.inner.execute(request)?;
+
+        let status = response.status;
+        let headers = response.headers.clone();
+        let content_type = headers.get(CONTENT_TYPE).and_then(|v| v.to_str().ok()).map(str::to_string);
+        let bytes = response.bytes()?;
+
+        let (json, hex) = match serde_json::from_slice::<JsonValue>(&bytes) {
+            Ok(mut json) => {
+                redact(&mut json);
+                (Some(json), None)
+            },
+            Err(_) => (None, Some(to_hex(&bytes))),
+        };
+
+        self.exchanges.lock().unwrap().push(Exchange {
+            query,
+            request: body,
+            status: status.as_u16(),
+            content_type,
+            response: json,
+            response_hex: hex,
+        });
+
+        Ok(TransportResponse {
+            status,
+            headers,
+            body: Box::new(Cursor::new(bytes)),
+        })
+    }
+}
+
+//-------------------------------------------------------------------------------------------------
+
+/// A transport that serves the responses of a fixture.
+///
+/// Requests are matched on their query parameters (ignoring credentials and volatile parameters)
+/// and JSON body. Matching exchanges are served in the order they were recorded, the last one
+/// being repeated if a request is executed more times than it was recorded.
+pub struct ReplayTransport {
+    exchanges: Vec<Exchange>,
+    /// Number of times each exchange has been served
+    served: Mutex<Vec<usize>>,
+}
+
+impl ReplayTransport {
+    pub fn new(fixture: Fixture) -> Self {
+        let served = vec![0; fixture.exchanges.len()];
+        ReplayTransport {
+            exchanges: fixture.exchanges,
+            served: Mutex::new(served),
+        }
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        Ok(Self::new(Fixture::load(path)?))
+    }
+}
+
+impl Transport for ReplayTransport {
+    fn execute(&self, request: reqwest::blocking::Request) -> anyhow::Result<TransportResponse> {
+        let (query, body) = request_key(&request);
+        let matches = self.exchanges.iter().enumerate()
+            .filter(|(_, e)| e.query == query && e.request == body)
+            .map(|(i, _)| i)
+            .collect::<Vec<_>>();
+
+        let index = {
+            let mut served = self.served.lock().unwrap();
+            let Some(index) = matches.iter().find(|i| served[**i] == 0).or(matches.last()).copied() else {
+                return Err(anyhow::anyhow!(
+                    "No recorded exchange for '{}' request {}",
+                    query.get("cmd").map(String::as_str).unwrap_or(""),
+                    body.map(|b| b.to_string()).unwrap_or_default()
+                ));
+            };
+            served[index] += 1;
+            index
+        };
+
+        let exchange = &self.exchanges[index];
+        let bytes = match (&exchange.response, &exchange.response_hex) {
+            (Some(json), _) => serde_json::to_vec(json)?,
+            (None, Some(hex)) => from_hex(hex)?,
+            (None, None) => Vec::new(),
+        };
+
+        let mut headers = HeaderMap::new();
+        if let Some(content_type) = &exchange.content_type {
+            headers.insert(CONTENT_TYPE, HeaderValue::from_str(content_type)?);
+        }
+
+        Ok(TransportResponse {
+            status: StatusCode::from_u16(exchange.status)?,
+            headers,
+            body: Box::new(Cursor::new(bytes)),
+        })
+    }
+}
+
+//-------------------------------------------------------------------------------------------------
+
+/// The parts of a request used to match it with recorded exchanges, credentials and volatile
+/// parameters removed.
+fn request_key(request: &reqwest::blocking::Request) -> (BTreeMap<String, String>, Option<JsonValue>) {
+    let query = request.url().query_pairs()
+        .filter(|(k, _)| !CREDENTIAL_PARAMS.contains(&k.as_ref()) && !VOLATILE_PARAMS.contains(&k.as_ref()))
+        .map(|(k, v)| (k.into_owned(), v.into_owned()))
+        .collect();
+
+    let body = request.body()
+        .and_then(|b| b.as_bytes())
+        .and_then(|b| serde_json::from_slice::<JsonValue>(b).ok())
+        .map(|mut json| {
+            redact(&mut json);
+            json
+        });
+
+    (query, body)
+}
+
+/// Replaces the values of credential fields, and of the token returned by `Login`.
+fn redact(json: &mut JsonValue) {
+    match json {
+        JsonValue::Object(obj) => {
+            for (k, v) in obj.iter_mut() {
+                if CREDENTIAL_FIELDS.contains(&k.as_str()) && v.is_string() {
+                    *v = JsonValue::String(REDACTED.to_string());
+                } else if k == "Token" && v["name"].is_string() {
+                    v["name"] = JsonValue::String(REDACTED.to_string());
+                } else {
+                    redact(v);
+                }
+            }
+        },
+        JsonValue::Array(array) => array.iter_mut().for_each(redact),
+        _ => (),
+    }
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn from_hex(hex: &str) -> anyhow::Result<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return Err(anyhow::anyhow!("Invalid hex string"));
+    }
+    (0..hex.len()).step_by(2)
+        .map(|i| Ok(u8::from_str_radix(&hex[i..i + 2], 16)?))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_redact() {
+        let mut json = json!([{"cmd": "Login", "param": {"User": {"Version": "0", "userName": "admin", "password": "secret"}}}]);
+        redact(&mut json);
+        assert_eq!(json!([{"cmd": "Login", "param": {"User": {"Version": "0", "userName": "REDACTED", "password": "REDACTED"}}}]), json);
+
+        let mut json = json!([{"cmd": "Login", "code": 0, "value": {"Token": {"leaseTime": 3600, "name": "d2b7f3a1c0ffee"}}}]);
+        redact(&mut json);
+        assert_eq!(json!([{"cmd": "Login", "code": 0, "value": {"Token": {"leaseTime": 3600, "name": "REDACTED"}}}]), json);
+
+        // Other name fields are kept
+        let mut json = json!({"File": [{"name": "Mp4Record/2025-02-09/RecM01.mp4"}]});
+        redact(&mut json);
+        assert_eq!(json!({"File": [{"name": "Mp4Record/2025-02-09/RecM01.mp4"}]}), json);
+    }
+
+    #[test]
+    fn test_hex() -> anyhow::Result<()> {
+        let bytes = [0u8, 1, 0x7f, 0xff];
+        assert_eq!("00017fff", to_hex(&bytes));
+        assert_eq!(bytes.to_vec(), from_hex("00017fff")?);
+        assert!(from_hex("123").is_err());
+        Ok(())
+    }
+}
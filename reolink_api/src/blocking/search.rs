@@ -0,0 +1,108 @@
+//! Search of recording files over time ranges that span several days.
+
+use std::collections::{HashSet, VecDeque};
+use chrono::{Datelike, NaiveDateTime};
+use crate::api::Channel;
+use crate::api::record::StreamType;
+use crate::api::record::search::{day_intervals, Search, SearchFile, SearchRequest, SearchStatus};
+use crate::blocking::ReolinkClient;
+
+/// An iterator over the recording files of a time range, returned by
+/// `ReolinkClient::search_files`.
+///
+/// A status search is first done to find the days that have recordings, and the files of each
+/// of these days are then fetched when the iterator reaches them. Files that span midnight are
+/// returned only once. Iteration stops after the first error.
+pub struct SearchFiles {
+    client: ReolinkClient,
+    channel: Channel,
+    stream_type: StreamType,
+    start: NaiveDateTime,
+    end: NaiveDateTime,
+    /// Days left to search, `None` until the status search is done
+    days: Option<VecDeque<(NaiveDateTime, NaiveDateTime)>>,
+    /// Files of the current day
+    files: VecDeque<SearchFile>,
+    /// Names of the files already returned
+    seen: HashSet<String>,
+    failed: bool,
+}
+
+impl SearchFiles {
+    pub(crate) fn new(client: ReolinkClient, channel: Channel, stream_type: StreamType, start: NaiveDateTime, end: NaiveDateTime) -> Self {
+        SearchFiles {
+            client,
+            channel,
+            stream_type,
+            start,
+            end,
+            days: None,
+            files: VecDeque::new(),
+            seen: HashSet::new(),
+            failed: false,
+        }
+    }
+
+    fn search(&self, start: NaiveDateTime, end: NaiveDateTime, only_status: bool) -> anyhow::Result<(Vec<SearchStatus>, Vec<SearchFile>)> {
+        let response = self.client.exec(&SearchRequest {
+            search: Search {
+                channel: self.channel,
+                only_status,
+                stream_type: self.stream_type.clone(),
+                start_time: start.try_into()?,
+                end_time: end.try_into()?,
+            }
+        })?;
+        let result = response.search_result;
+        Ok((result.status.unwrap_or_default(), result.file.unwrap_or_default()))
+    }
+
+    /// Days of the range that have recordings, according to a status search.
+    fn days_with_recordings(&self) -> anyhow::Result<VecDeque<(NaiveDateTime, NaiveDateTime)>> {
+        let (status, _) = self.search(self.start, self.end, true)?;
+        Ok(day_intervals(self.start, self.end).into_iter()
+            .filter(|(day, _)| {
+                status.iter().any(|s| {
+                    s.year as i32 == day.year() && s.mon as u32 == day.month() && s.has_recordings(day.day() as u8)
+                })
+            })
+            .collect())
+    }
+
+    fn next_file(&mut self) -> anyhow::Result<Option<SearchFile>> {
+        if self.days.is_none() {
+            self.days = Some(self.days_with_recordings()?);
+        }
+
+        loop {
+            while let Some(file) = self.files.pop_front() {
+                if self.seen.insert(file.name.clone()) {
+                    return Ok(Some(file));
+                }
+            }
+
+            let Some((start, end)) = self.days.as_mut().and_then(|days| days.pop_front()) else {
+                return Ok(None);
+            };
+            let (_, files) = self.search(start, end, false)?;
+            self.files = files.into();
+        }
+    }
+}
+
+impl Iterator for SearchFiles {
+    type Item = anyhow::Result<SearchFile>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        match self.next_file() {
+            Ok(file) => file.map(Ok),
+            Err(err) => {
+                self.failed = true;
+                Some(Err(err))
+            }
+        }
+    }
+}
@@ -0,0 +1,305 @@
+//! Parses the responses in `tests/fixtures/responses`, captured from different device kinds
+//! and firmware versions. Each directory holds the responses of a device, and `errors` holds
+//! error envelopes. Fixtures are dispatched to their endpoint using their `cmd` field.
+
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use bytes::Bytes;
+use serde_json::Value as JsonValue;
+use crate::api::{ApiError, ConfigEndpoint, DeviceDuration, JsonEndpoint};
+use crate::api::record::{DetectionType, StreamType};
+use crate::api::security::UserLevel;
+use crate::api::ai::get_ai_state::GetAiStateRequest;
+use crate::api::alarm::get_md_state::GetMdStateRequest;
+use crate::api::network::get_net_port::GetNetPortRequest;
+use crate::api::network::get_rtsp_url::GetRtspUrlRequest;
+use crate::api::record::get_recording::GetRecordingRequest;
+use crate::api::record::get_recording_v20::GetRecordingRequest as GetRecordingV20Request;
+use crate::api::record::nvr_download::NvrDownloadRequest;
+use crate::api::record::search::SearchRequest;
+use crate::api::record::set_recording::SetRecordingRequest;
+use crate::api::record::set_recording_v20::SetRecordingRequest as SetRecordingV20Request;
+use crate::api::security::add_user::AddUserRequest;
+use crate::api::security::get_user::GetUserRequest;
+use crate::api::security::login::LoginRequest;
+use crate::api::security::logout::LogoutRequest;
+use crate::api::system::get_ability::GetAbilityRequest;
+use crate::api::system::get_channel_status::GetChannelStatusRequest;
+use crate::api::system::get_dev_info::GetDevInfoRequest;
+use crate::api::system::get_time::GetTimeRequest;
+use super::{parse_json_detailed_response, parse_json_response};
+
+type Parser = fn(&Bytes) -> anyhow::Result<()>;
+
+// Devices don't always echo the command with the same case, e.g. `GetDevinfo` returns `GetDevInfo`.
+fn find_parser(cmd: &str) -> Option<Parser> {
+    PARSERS.iter().find(|(c, _)| c.eq_ignore_ascii_case(cmd)).map(|(_, parser)| *parser)
+}
+
+const PARSERS: &[(&str, Parser)] = &[
+    (AddUserRequest::CMD, parse::<AddUserRequest>),
+    (GetAbilityRequest::CMD, parse::<GetAbilityRequest>),
+    (GetAiStateRequest::CMD, parse::<GetAiStateRequest>),
+    (GetChannelStatusRequest::CMD, parse::<GetChannelStatusRequest>),
+    (GetDevInfoRequest::CMD, parse::<GetDevInfoRequest>),
+    (GetMdStateRequest::CMD, parse::<GetMdStateRequest>),
+    (GetNetPortRequest::CMD, parse::<GetNetPortRequest>),
+    (GetTimeRequest::CMD, parse::<GetTimeRequest>),
+    (GetRecordingRequest::CMD, parse::<GetRecordingRequest>),
+    (GetRecordingV20Request::CMD, parse::<GetRecordingV20Request>),
+    (GetRtspUrlRequest::CMD, parse::<GetRtspUrlRequest>),
+    (GetUserRequest::CMD, parse::<GetUserRequest>),
+    (LoginRequest::CMD, parse::<LoginRequest>),
+    (LogoutRequest::CMD, parse::<LogoutRequest>),
+    (NvrDownloadRequest::CMD, parse::<NvrDownloadRequest>),
+    (SearchRequest::CMD, parse::<SearchRequest>),
+    (SetRecordingRequest::CMD, parse::<SetRecordingRequest>),
+    (SetRecordingV20Request::CMD, parse::<SetRecordingV20Request>),
+];
+
+/// Parses a response both as a plain and a detailed response. Endpoints that have no initial
+/// and range values accept a detailed response without them.
+fn parse<E: JsonEndpoint>(bytes: &Bytes) -> anyhow::Result<()> {
+    parse_json_response::<E>(bytes)?;
+    parse_json_detailed_response::<E>(bytes)?;
+    Ok(())
+}
+
+fn fixtures_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/responses")
+}
+
+/// Reads all fixtures in a directory, along with the command they're a response to.
+fn read_fixtures(dir: &Path) -> anyhow::Result<Vec<(PathBuf, String, Bytes)>> {
+    let mut paths = std::fs::read_dir(dir)?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    paths.sort();
+
+    let mut result = Vec::new();
+    for path in paths {
+        if path.extension().is_some_and(|ext| ext == "json") {
+            let bytes = Bytes::from(std::fs::read(&path)?);
+            let json = serde_json::from_slice::<JsonValue>(&bytes)?;
+            let Some(cmd) = json[0]["cmd"].as_str() else {
+                anyhow::bail!("{}: no 'cmd' in response", path.display());
+            };
+            result.push((path, cmd.to_string(), bytes));
+        }
+    }
+    Ok(result)
+}
+
+fn device_dirs() -> anyhow::Result<Vec<PathBuf>> {
+    let mut dirs = std::fs::read_dir(fixtures_dir())?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    dirs.retain(|dir| dir.is_dir() && !dir.ends_with("errors"));
+    dirs.sort();
+    Ok(dirs)
+}
+
+fn load<E: JsonEndpoint>(device: &str, name: &str) -> anyhow::Result<(E::Response, E::Initial, E::Range)> {
+    let path = fixtures_dir().join(device).join(name).with_extension("json");
+    let bytes = Bytes::from(std::fs::read(path)?);
+    parse_json_detailed_response::<E>(&bytes)
+}
+
+#[test]
+fn test_all_responses() -> anyhow::Result<()> {
+    let mut parsed = BTreeSet::new();
+    for dir in device_dirs()? {
+        for (path, cmd, bytes) in read_fixtures(&dir)? {
+            let Some(parser) = find_parser(&cmd) else {
+                panic!("{}: no parser for '{}'", path.display(), cmd);
+            };
+            if let Err(err) = parser(&bytes) {
+                panic!("{}: {:#}", path.display(), err);
+            }
+            parsed.insert(cmd.to_ascii_lowercase());
+        }
+    }
+
+    // All endpoints should have at least one fixture
+    let all = PARSERS.iter().map(|(cmd, _)| cmd.to_ascii_lowercase()).collect::<BTreeSet<_>>();
+    assert_eq!(all, parsed);
+    Ok(())
+}
+
+#[test]
+fn test_error_responses() -> anyhow::Result<()> {
+    let fixtures = read_fixtures(&fixtures_dir().join("errors"))?;
+    assert!(!fixtures.is_empty());
+
+    for (path, cmd, bytes) in fixtures {
+        let json = serde_json::from_slice::<JsonValue>(&bytes)?;
+        let expected = json[0]["error"]["rspCode"].as_i64().unwrap() as isize;
+
+        // Error envelopes don't depend on the endpoint
+        let Some(parser) = find_parser(&cmd) else {
+            panic!("{}: no parser for '{}'", path.display(), cmd);
+        };
+        for err in [
+            parser(&bytes).unwrap_err(),
+            parse_json_response::<GetDevInfoRequest>(&bytes).unwrap_err(),
+            parse_json_detailed_response::<GetRecordingV20Request>(&bytes).unwrap_err(),
+        ] {
+            let Some(err) = err.downcast_ref::<ApiError>() else {
+                panic!("{}: not an API error: {:#}", path.display(), err);
+            };
+            assert_ne!(0, err.code);
+            assert_eq!(expected, err.error.rsp_code, "{}", path.display());
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_device_kinds() -> anyhow::Result<()> {
+    let (camera, _, _) = load::<GetDevInfoRequest>("camera-rlc810a-v3.1", "GetDevInfo")?;
+    assert_eq!("IPC", camera.dev_info.exact_type);
+    assert_eq!(1, camera.dev_info.channel_num);
+
+    let (nvr, _, _) = load::<GetDevInfoRequest>("nvr-rln8-410", "GetDevInfo")?;
+    assert_eq!("NVR", nvr.dev_info.exact_type);
+    assert_eq!(8, nvr.dev_info.channel_num);
+
+    let (channels, _, _) = load::<GetChannelStatusRequest>("nvr-rln8-410", "GetChannelStatus")?;
+    assert_eq!(8, channels.status.len());
+    assert_eq!(3, channels.status.iter().filter(|s| s.online).count());
+
+    // Home Hub channels have a uid and no type info
+    let (channels, _, _) = load::<GetChannelStatusRequest>("home-hub", "GetChannelStatus")?;
+    assert_eq!(Some("95270000ABCDEFGH"), channels.status[0].uid.as_deref());
+    assert_eq!(None, channels.status[0].type_info);
+    assert!(channels.status[0].sleep);
+
+    let (users, initial, range) = load::<GetUserRequest>("camera-rlc810a-v3.1", "GetUser")?;
+    assert_eq!(UserLevel::Admin, users.user[0].level);
+    assert_eq!(UserLevel::Guest, initial.user.level);
+    assert_eq!(vec![UserLevel::Guest, UserLevel::Admin], range.user.level);
+
+    let (battery, _, _) = load::<GetDevInfoRequest>("battery-argus3pro", "GetDevInfo")?;
+    assert!(battery.dev_info.wifi);
+
+    // Time zones have an inverted sign
+    let (time, _, _) = load::<GetTimeRequest>("home-hub", "GetTime")?;
+    assert_eq!(18000, time.time.time_zone);
+    assert_eq!((3, 2, 0), (time.dst.start_mon, time.dst.start_week, time.dst.start_weekday));
+    let (time, _, _) = load::<GetTimeRequest>("battery-argus3pro", "GetTime")?;
+    assert!(!time.dst.enable);
+    Ok(())
+}
+
+#[test]
+fn test_net_ports() -> anyhow::Result<()> {
+    // Older firmwares have no `*Enable` fields
+    let (ports, _, _) = load::<GetNetPortRequest>("camera-rlc520-v3.0", "GetNetPort")?;
+    assert!(ports.net_port.rtmp_enable);
+    assert_eq!(554, ports.net_port.rtsp_port);
+
+    let (ports, _, _) = load::<GetNetPortRequest>("camera-rlc810a-v3.1", "GetNetPort")?;
+    assert!(!ports.net_port.rtmp_enable);
+    assert!(ports.net_port.rtsp_enable);
+
+    let (rtsp, _, _) = load::<GetRtspUrlRequest>("nvr-rln8-410", "GetRtspUrl")?;
+    assert_eq!(2, rtsp.rtsp_url.channel);
+    assert_eq!("rtsp://192.168.1.20:8554/Preview_03_sub", rtsp.rtsp_url.sub_stream);
+    Ok(())
+}
+
+#[test]
+fn test_alarm_states() -> anyhow::Result<()> {
+    let (md, _, _) = load::<GetMdStateRequest>("camera-rlc810a-v3.1", "GetMdState")?;
+    assert!(md.state);
+
+    let (ai, _, _) = load::<GetAiStateRequest>("camera-rlc810a-v3.1", "GetAiState")?;
+    assert!(ai.people.unwrap().is_active());
+    assert!(!ai.face.unwrap().support);
+
+    // Older firmwares only know people and vehicles
+    let (ai, _, _) = load::<GetAiStateRequest>("camera-rlc520-v3.0", "GetAiState")?;
+    assert!(ai.dog_cat.is_none());
+    assert!(!ai.vehicle.unwrap().support);
+    Ok(())
+}
+
+#[test]
+fn test_recording_configs() -> anyhow::Result<()> {
+    // Cameras have no pack time
+    let (rec, initial, range) = load::<GetRecordingV20Request>("camera-rlc810a-v3.1", "GetRecV20")?;
+    assert_eq!(None, rec.rec.pack_time);
+    assert_eq!(DeviceDuration::seconds(30), rec.rec.post_rec);
+    assert_eq!(DeviceDuration::minutes(1), initial.rec.post_rec);
+    assert_eq!(None, range.rec.pack_time);
+    assert_eq!(168, rec.rec.schedule.table[&DetectionType::Motion].len());
+
+    let (rec, _, range) = load::<GetRecordingV20Request>("nvr-rln8-410", "GetRecV20")?;
+    assert_eq!(1, rec.rec.schedule.channel);
+    assert_eq!(Some(DeviceDuration::minutes(60)), rec.rec.pack_time);
+    assert_eq!(3, range.rec.pack_time.unwrap().len());
+
+    // Home Hubs also record pets
+    let (rec, _, _) = load::<GetRecordingV20Request>("home-hub", "GetRecV20")?;
+    assert!(rec.rec.schedule.table.contains_key(&DetectionType::Animal));
+
+    // Unmodeled fields are sent back when updating
+    let mut config = GetRecordingV20Request::config(rec);
+    config.overwrite = false;
+    let set = serde_json::to_value(GetRecordingV20Request::set_request(0, config))?;
+    assert_eq!(JsonValue::from(1), set["Rec"]["scheduleEnable"]);
+    assert_eq!(JsonValue::from(0), set["Rec"]["overwrite"]);
+
+    // Older firmware only have GetRec
+    let (rec, _, range) = load::<GetRecordingRequest>("camera-rlc520-v3.0", "GetRec")?;
+    assert_eq!(DeviceDuration::minutes(60), rec.rec.pack_time);
+    assert_eq!(3, range.rec.post_rec.len());
+    Ok(())
+}
+
+#[test]
+fn test_search_results() -> anyhow::Result<()> {
+    // Status only: one entry per month, with a digit per day
+    let (search, _, _) = load::<SearchRequest>("nvr-rln8-410", "Search-status")?;
+    let status = search.search_result.status.unwrap();
+    assert_eq!(None, search.search_result.file.map(|f| f.len()));
+    assert_eq!(vec![(2025, 1, 31), (2025, 2, 28)], status.iter().map(|s| (s.year, s.mon, s.table.len())).collect::<Vec<_>>());
+
+    #[cfg(feature = "chrono")] {
+        use chrono::NaiveDate;
+        let date = |m, d| NaiveDate::from_ymd_opt(2025, m, d).unwrap();
+        let mut calendar = crate::api::record::calendar::RecordingCalendar::new(date(1, 1), date(2, 28));
+        calendar.add_statuses(&status);
+        assert_eq!(5 + 9, calendar.days_with_recordings().count());
+        assert_eq!(Some(date(1, 27)), calendar.days_with_recordings().next());
+        assert_eq!(Some(date(2, 9)), calendar.days_with_recordings().last());
+    }
+
+    let (search, _, _) = load::<SearchRequest>("camera-rlc810a-v3.1", "Search-files")?;
+    let files = search.search_result.file.unwrap();
+    assert_eq!(2, files.len());
+    assert_eq!(StreamType::Main, files[0].stream_type);
+    assert_eq!(1851162, files[0].size);
+    assert_eq!((8, 13, 52), (files[0].start_time.hour, files[0].start_time.min, files[0].start_time.sec));
+
+    // Home Hub file names are absolute paths
+    let (search, _, _) = load::<SearchRequest>("home-hub", "Search-files")?;
+    let files = search.search_result.file.unwrap();
+    assert!(files[0].name.starts_with("/mnt/sda/"));
+
+    let (download, _, _) = load::<NvrDownloadRequest>("nvr-rln8-410", "NvrDownload")?;
+    assert_eq!(download.file_count, download.file_list.len());
+    assert_eq!(81896243, download.file_list[0].size);
+
+    // Names of all files can be parsed, and encode their size
+    for device in ["camera-rlc810a-v3.1", "camera-rlc520-v3.0", "home-hub"] {
+        let (search, _, _) = load::<SearchRequest>(device, "Search-files")?;
+        for file in search.search_result.file.unwrap() {
+            assert_eq!(file.size as u64, file.file_name()?.size, "{}", file.name);
+        }
+    }
+    for file in &download.file_list {
+        assert_eq!(file.size, file.file_name()?.size, "{}", file.name);
+    }
+    Ok(())
+}
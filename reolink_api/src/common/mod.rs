@@ -9,6 +9,8 @@ use serde_json::Value as JsonValue;
 use crate::api::{AuthenticationType, BinaryEndpoint, JsonEndpoint};
 
 mod url;
+#[cfg(test)]
+mod fixtures;
 
 pub struct Credentials {
     pub login: String,
@@ -86,6 +88,49 @@ pub fn get_api_url(mut url: &str) -> anyhow::Result<Url> {
     Ok(url)
 }
 
+/// Is this device address a full URL, or only a host with an optional port?
+pub fn has_scheme(addr: &str) -> bool {
+    let addr = addr.to_ascii_lowercase();
+    addr.starts_with("http://") || addr.starts_with("https://")
+}
+
+/// Returns the API urls to probe for a device address that has no scheme, e.g. `192.168.0.42`
+/// or `camera.local:8443`: HTTPS then HTTP on the port given in the address, if any, then on
+/// the standard ports.
+pub fn candidate_api_urls(addr: &str) -> anyhow::Result<Vec<Url>> {
+    let addr = addr.strip_suffix("/").unwrap_or(addr);
+    // Let the url parser validate the host and port. Use https so that an explicit port 80
+    // isn't dropped as being the scheme's default port.
+    let parsed = Url::parse(&format!("https://{}", addr))?;
+    if parsed.path() != "/" || parsed.query().is_some() || !parsed.username().is_empty() {
+        return Err(anyhow::anyhow!("Invalid device address '{}', expecting a host and optional port", addr));
+    }
+    let Some(host) = parsed.host_str() else {
+        return Err(anyhow::anyhow!("Invalid device address '{}', expecting a host and optional port", addr));
+    };
+
+    let mut candidates = Vec::new();
+    let mut add = |scheme: &str, port: Option<u16>| -> anyhow::Result<()> {
+        let url = match port {
+            Some(port) => get_api_url(&format!("{}://{}:{}", scheme, host, port))?,
+            None => get_api_url(&format!("{}://{}", scheme, host))?,
+        };
+        if !candidates.contains(&url) {
+            candidates.push(url);
+        }
+        Ok(())
+    };
+
+    if let Some(port) = parsed.port() {
+        add("https", Some(port))?;
+        add("http", Some(port))?;
+    }
+    add("https", None)?;
+    add("http", None)?;
+
+    Ok(candidates)
+}
+
 // Section independent of the request type (limit code bloat)
 fn prepare_request<HC:HttpClient>(
     client: &HC, url: reqwest::Url, cmd: &str, auth: AuthenticationType, creds: &Credentials
@@ -159,15 +204,33 @@ pub fn prepare_json_request<HC: HttpClient, APIReq: JsonEndpoint>(
 
 /// Prepare a request for an endpoint that returns binary data. If an auth token is needed,
 /// it must be available in `creds`. This function does no token creation or refresh.
+///
+/// If `streamed` is true, the request has no total timeout, so that long responses can be
+/// read as they arrive. Each read is then only limited by the HTTP client's timeout.
 pub fn prepare_download_request<HC: HttpClient, Req: BinaryEndpoint>(
-    client: &HC, url: &reqwest::Url, req: &Req, creds: &Credentials
+    client: &HC, url: &reqwest::Url, req: &Req, creds: &Credentials, streamed: bool
 ) -> anyhow::Result<HC::Request> {
     let rb = prepare_request(client, url.clone(), Req::CMD, Req::AUTH, creds)?;
-    let rb = rb.timeout(Duration::from_secs(5*60));
+    let rb = if streamed { rb } else { rb.timeout(Req::TIMEOUT) };
     let rb = rb.query(req);
     Ok(finalize_request(rb)?)
 }
 
+/// Prepare an unauthenticated request used to check that `url` is the API endpoint of a device.
+pub fn prepare_probe_request<HC: HttpClient>(client: &HC, url: &reqwest::Url) -> anyhow::Result<HC::Request> {
+    use crate::api::system::get_dev_info::GetDevInfoRequest;
+
+    let creds = Credentials::new(String::new(), String::new());
+    let rb = prepare_request(client, url.clone(), GetDevInfoRequest::CMD, AuthenticationType::None, &creds)?;
+    let rb = rb.timeout(Duration::from_secs(5));
+    let rb = rb.json(&[ApiRequestEnvelope {
+        cmd: GetDevInfoRequest::CMD,
+        action: None,
+        param: &GetDevInfoRequest,
+    }]);
+    Ok(finalize_request(rb)?)
+}
+
 #[derive(Debug, Serialize)]
 struct ApiRequestEnvelope<'a, Req: Serialize> {
     cmd: &'static str,
@@ -199,6 +262,20 @@ pub (crate) fn parse_json_detailed_response<APIReq: JsonEndpoint>(bytes: &Bytes)
     }
 }
 
+/// Binary endpoints return a JSON response in case of error: turn it into an error.
+pub(crate) fn parse_binary_error(bytes: &Bytes) -> anyhow::Error {
+    match serde_json::from_slice::<[ApiResponse<JsonValue>;1]>(bytes) {
+        Ok([ApiResponse::Error(err)]) => err.into(),
+        _ => anyhow::anyhow!("Unexpected JSON response: {}", String::from_utf8_lossy(bytes)),
+    }
+}
+
+/// Is this the response of a Reolink API endpoint? Authentication errors are expected
+/// since probe requests have no credentials.
+pub(crate) fn is_api_response(bytes: &Bytes) -> bool {
+    serde_json::from_slice::<[ApiResponse<JsonValue>;1]>(bytes).is_ok()
+}
+
 #[derive(Debug)]
 enum ApiResponse<Value> {
     Success(Value),
@@ -260,3 +337,51 @@ impl <'de, T: DeserializeOwned> Deserialize<'de> for ApiResponse<T> {
     }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_candidate_urls() -> anyhow::Result<()> {
+        let urls = |addr| -> anyhow::Result<Vec<String>> {
+            Ok(candidate_api_urls(addr)?.into_iter().map(|u| u.to_string()).collect())
+        };
+
+        assert_eq!(
+            vec!["https://192.168.0.42/cgi-bin/api.cgi", "http://192.168.0.42/cgi-bin/api.cgi"],
+            urls("192.168.0.42")?
+        );
+        assert_eq!(
+            vec![
+                "https://camera.local:8000/cgi-bin/api.cgi",
+                "http://camera.local:8000/cgi-bin/api.cgi",
+                "https://camera.local/cgi-bin/api.cgi",
+                "http://camera.local/cgi-bin/api.cgi",
+            ],
+            urls("camera.local:8000/")?
+        );
+        assert_eq!(
+            vec![
+                "https://192.168.0.42:80/cgi-bin/api.cgi",
+                "http://192.168.0.42/cgi-bin/api.cgi",
+                "https://192.168.0.42/cgi-bin/api.cgi",
+            ],
+            urls("192.168.0.42:80")?
+        );
+        assert!(candidate_api_urls("192.168.0.42/foo").is_err());
+
+        assert!(has_scheme("HTTPS://192.168.0.42"));
+        assert!(!has_scheme("192.168.0.42:443"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_is_api_response() {
+        let error = r#"[{"cmd":"GetDevInfo","code":1,"error":{"detail":"please login first","rspCode":-6}}]"#;
+        assert!(is_api_response(&Bytes::from(error)));
+
+        assert!(!is_api_response(&Bytes::from("<html>Not found</html>")));
+        assert!(!is_api_response(&Bytes::from(r#"{"foo": 1}"#)));
+    }
+}
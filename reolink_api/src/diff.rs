@@ -0,0 +1,95 @@
+//! Field-by-field comparison of configuration objects
+
+use std::fmt::{Display, Formatter};
+use serde::Serialize;
+use serde_json::Value as JsonValue;
+
+/// A field whose value differs between two configuration objects.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldDiff {
+    /// Path of the field, as dot-separated JSON field names (e.g. `schedule.table.MD`).
+    /// Array items are noted `[index]`.
+    pub path: String,
+    /// Current value of the field, `Null` if missing
+    pub current: JsonValue,
+    /// Default value of the field, `Null` if missing
+    pub default: JsonValue,
+}
+
+impl Display for FieldDiff {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {} (default: {})", self.path, self.current, self.default)
+    }
+}
+
+/// Compares the JSON representations of `current` and `default` and returns the fields that differ.
+pub fn diff<T: Serialize>(current: &T, default: &T) -> anyhow::Result<Vec<FieldDiff>> {
+    let current = serde_json::to_value(current)?;
+    let default = serde_json::to_value(default)?;
+
+    let mut result = Vec::new();
+    diff_values(&mut String::new(), &current, &default, &mut result);
+    Ok(result)
+}
+
+fn diff_values(path: &mut String, current: &JsonValue, default: &JsonValue, result: &mut Vec<FieldDiff>) {
+    let len = path.len();
+    match (current, default) {
+        (JsonValue::Object(current), JsonValue::Object(default)) => {
+            let mut keys = current.keys().chain(default.keys()).collect::<Vec<_>>();
+            keys.sort();
+            keys.dedup();
+            for key in keys {
+                if !path.is_empty() {
+                    path.push('.');
+                }
+                path.push_str(key);
+                diff_values(path, current.get(key).unwrap_or(&JsonValue::Null), default.get(key).unwrap_or(&JsonValue::Null), result);
+                path.truncate(len);
+            }
+        },
+
+        (JsonValue::Array(current), JsonValue::Array(default)) => {
+            for i in 0..current.len().max(default.len()) {
+                path.push_str(&format!("[{}]", i));
+                diff_values(path, current.get(i).unwrap_or(&JsonValue::Null), default.get(i).unwrap_or(&JsonValue::Null), result);
+                path.truncate(len);
+            }
+        },
+
+        _ => if current != default {
+            result.push(FieldDiff {
+                path: path.clone(),
+                current: current.clone(),
+                default: default.clone(),
+            });
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_diff() -> anyhow::Result<()> {
+        let current = json!({"overwrite": 1, "postRec": "1 Minute", "schedule": {"channel": 0, "table": {"MD": "0110"}}, "list": [1, 2]});
+        let default = json!({"overwrite": 1, "postRec": "30 Seconds", "schedule": {"channel": 0, "table": {"MD": "1111", "AI_PEOPLE": "1111"}}, "list": [1]});
+
+        let diffs = diff(&current, &default)?;
+        let paths = diffs.iter().map(|d| d.path.as_str()).collect::<Vec<_>>();
+        assert_eq!(vec!["list[1]", "postRec", "schedule.table.AI_PEOPLE", "schedule.table.MD"], paths);
+
+        assert_eq!(r#"postRec: "1 Minute" (default: "30 Seconds")"#, diffs[1].to_string());
+        assert_eq!(JsonValue::Null, diffs[2].current);
+        Ok(())
+    }
+
+    #[test]
+    fn test_no_diff() -> anyhow::Result<()> {
+        let value = json!({"overwrite": 1, "schedule": {"channel": 0}});
+        assert!(diff(&value, &value)?.is_empty());
+        Ok(())
+    }
+}
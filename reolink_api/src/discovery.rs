@@ -0,0 +1,315 @@
+//! Discovery of Reolink devices on the local network.
+//!
+//! Devices answer UDP broadcast probes using the "BcUdp" framing of Reolink's native protocol:
+//! a 20-byte little-endian header (magic, payload length, a constant `1`, transaction id and
+//! CRC-32 of the payload) followed by an XML payload that is XOR-encrypted with a key derived
+//! from the transaction id. This protocol is undocumented, and is implemented after
+//! community reverse-engineering efforts.
+
+use std::collections::HashSet;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, UdpSocket};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// A device that answered a discovery probe.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiscoveredDevice {
+    /// Address the reply was received from
+    pub ip: IpAddr,
+    /// Unique id of the device
+    pub uid: Option<String>,
+    /// Device model, e.g. `RLC-810A`
+    pub model: Option<String>,
+    /// Device name, as configured by the user
+    pub name: Option<String>,
+    pub ports: DevicePorts,
+}
+
+/// Service ports advertised by a device. Missing values mean the service is disabled or was
+/// not advertised.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DevicePorts {
+    pub http: Option<u16>,
+    pub https: Option<u16>,
+    pub rtsp: Option<u16>,
+    pub rtmp: Option<u16>,
+    /// Port of the native (Baichuan) protocol, usually 9000
+    pub media: Option<u16>,
+}
+
+impl DiscoveredDevice {
+    /// Base URL of the device's HTTP API, preferring HTTPS if available.
+    pub fn url(&self) -> String {
+        let host = match self.ip {
+            IpAddr::V4(ip) => ip.to_string(),
+            IpAddr::V6(ip) => format!("[{}]", ip),
+        };
+        match (self.ports.https, self.ports.http) {
+            (Some(443), _) => format!("https://{}/", host),
+            (Some(port), _) => format!("https://{}:{}/", host, port),
+            (None, Some(80) | None) => format!("http://{}/", host),
+            (None, Some(port)) => format!("http://{}:{}/", host, port),
+        }
+    }
+
+    /// Creates a client for this device with default settings.
+    #[cfg(feature = "blocking")]
+    pub fn client(&self, login: String, password: String) -> anyhow::Result<crate::blocking::ReolinkClient> {
+        crate::blocking::ReolinkClient::new(&self.url(), login, password)
+    }
+}
+
+/// Broadcasts discovery probes with default settings and returns the devices that replied.
+pub fn discover() -> anyhow::Result<Vec<DiscoveredDevice>> {
+    Discovery::new().run()
+}
+
+/// Discovery settings.
+#[derive(Debug, Clone)]
+pub struct Discovery {
+    targets: Vec<SocketAddr>,
+    timeout: Duration,
+}
+
+impl Default for Discovery {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Discovery {
+    /// UDP ports devices listen on for discovery probes.
+    pub const PORTS: [u16; 2] = [2015, 2018];
+
+    /// Creates discovery settings that broadcast probes on the local network and wait 2 seconds for replies.
+    pub fn new() -> Self {
+        Discovery {
+            targets: Self::PORTS.iter()
+                .map(|port| SocketAddr::new(IpAddr::V4(Ipv4Addr::BROADCAST), *port))
+                .collect(),
+            timeout: Duration::from_secs(2),
+        }
+    }
+
+    /// Sets the addresses probes are sent to (e.g. a subnet broadcast address or a single device).
+    pub fn with_targets(mut self, targets: Vec<SocketAddr>) -> Self {
+        self.targets = targets;
+        self
+    }
+
+    /// Sets how long to wait for replies.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Sends the probes and collects replies until the timeout expires. Devices are returned
+    /// in the order of their first reply.
+    pub fn run(&self) -> anyhow::Result<Vec<DiscoveredDevice>> {
+        let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))?;
+        socket.set_broadcast(true)?;
+        let local_port = socket.local_addr()?.port();
+
+        let tid = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.subsec_nanos()).unwrap_or(0);
+        let probe = encode_packet(tid, &format!(
+            "<P2P><C2D_S><to><port>{}</port></to></C2D_S></P2P>", local_port
+        ));
+        for target in &self.targets {
+            socket.send_to(&probe, target)?;
+        }
+
+        let mut result = Vec::new();
+        let mut seen = HashSet::new();
+        let deadline = Instant::now() + self.timeout;
+        let mut buf = [0u8; 4096];
+
+        loop {
+            let now = Instant::now();
+            if now >= deadline {
+                break;
+            }
+            socket.set_read_timeout(Some(deadline - now))?;
+            let (len, from) = match socket.recv_from(&mut buf) {
+                Ok(r) => r,
+                Err(e) if matches!(e.kind(), std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut) => break,
+                Err(e) => return Err(e.into()),
+            };
+
+            // Ignore unrelated traffic and our own broadcast
+            let Some((_, xml)) = decode_packet(&buf[..len]) else {
+                continue;
+            };
+            let Some(device) = parse_reply(from.ip(), &xml) else {
+                continue;
+            };
+            if seen.insert(device.ip) {
+                result.push(device);
+            }
+        }
+
+        Ok(result)
+    }
+}
+
+fn parse_reply(ip: IpAddr, xml: &str) -> Option<DiscoveredDevice> {
+    let reply = xml_tag(xml, "D2C_C_R")?;
+    let port = |tag| xml_tag(reply, tag).and_then(|p| p.trim().parse::<u16>().ok()).filter(|p| *p != 0);
+    let text = |tag| xml_tag(reply, tag).map(|s| s.trim().to_string()).filter(|s| !s.is_empty());
+
+    Some(DiscoveredDevice {
+        ip,
+        uid: text("uid"),
+        model: text("model"),
+        name: text("devName"),
+        ports: DevicePorts {
+            http: port("http"),
+            https: port("https"),
+            rtsp: port("rtsp"),
+            rtmp: port("rtmp"),
+            media: port("port"),
+        },
+    })
+}
+
+/// Returns the content of the first `<tag>` element. Good enough for the flat documents
+/// exchanged during discovery.
+fn xml_tag<'a>(xml: &'a str, tag: &str) -> Option<&'a str> {
+    let start = xml.find(&format!("<{}>", tag))? + tag.len() + 2;
+    let end = xml[start..].find(&format!("</{}>", tag))? + start;
+    Some(&xml[start..end])
+}
+
+//-------------------------------------------------------------------------------------------------
+// Packet encoding
+
+const MAGIC: u32 = 0x2a87cf3a;
+const HEADER_LEN: usize = 20;
+const KEY: [u32; 8] = [
+    0x1f2d3c4b, 0x5a6c7f8d, 0x38172e4b, 0x8271635a, 0x863f1a2b, 0xa5c6f7d8, 0x8371e1b4, 0x17f2d3a5,
+];
+
+/// XOR-encrypts (or decrypts) a payload with the key derived from the transaction id.
+fn crypt(tid: u32, data: &[u8]) -> Vec<u8> {
+    let key = KEY.iter()
+        .flat_map(|k| k.wrapping_add(tid).to_le_bytes())
+        .collect::<Vec<_>>();
+    data.iter().zip(key.iter().cycle()).map(|(b, k)| b ^ k).collect()
+}
+
+fn encode_packet(tid: u32, xml: &str) -> Vec<u8> {
+    let payload = crypt(tid, xml.as_bytes());
+    let mut packet = Vec::with_capacity(HEADER_LEN + payload.len());
+    packet.extend(MAGIC.to_le_bytes());
+    packet.extend((payload.len() as u32).to_le_bytes());
+    packet.extend(1u32.to_le_bytes());
+    packet.extend(tid.to_le_bytes());
+    packet.extend(crc32(&payload).to_le_bytes());
+    packet.extend(payload);
+    packet
+}
+
+fn decode_packet(packet: &[u8]) -> Option<(u32, String)> {
+    let u32_at = |pos: usize| u32::from_le_bytes(packet[pos..pos + 4].try_into().unwrap());
+
+    if packet.len() < HEADER_LEN || u32_at(0) != MAGIC {
+        return None;
+    }
+    let len = u32_at(4) as usize;
+    let tid = u32_at(12);
+    let payload = packet.get(HEADER_LEN..HEADER_LEN + len)?;
+    if crc32(payload) != u32_at(16) {
+        return None;
+    }
+    String::from_utf8(crypt(tid, payload)).ok().map(|xml| (tid, xml))
+}
+
+/// CRC-32 (IEEE 802.3)
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for b in data {
+        crc ^= *b as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xedb88320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_crc32() {
+        assert_eq!(0xcbf43926, crc32(b"123456789"));
+    }
+
+    #[test]
+    fn test_packet_round_trip() {
+        let xml = "<P2P><C2D_S><to><port>1234</port></to></C2D_S></P2P>";
+        let packet = encode_packet(42, xml);
+        assert_ne!(xml.as_bytes(), &packet[HEADER_LEN..]);
+        assert_eq!(Some((42, xml.to_string())), decode_packet(&packet));
+
+        // Corrupted payload
+        let mut packet = packet;
+        packet[HEADER_LEN] ^= 1;
+        assert_eq!(None, decode_packet(&packet));
+    }
+
+    #[test]
+    fn test_url() {
+        let mut device = DiscoveredDevice {
+            ip: IpAddr::V4(Ipv4Addr::new(192, 168, 0, 42)),
+            uid: None,
+            model: None,
+            name: None,
+            ports: DevicePorts::default(),
+        };
+        assert_eq!("http://192.168.0.42/", device.url());
+        device.ports.http = Some(8080);
+        assert_eq!("http://192.168.0.42:8080/", device.url());
+        device.ports.https = Some(443);
+        assert_eq!("https://192.168.0.42/", device.url());
+    }
+
+    #[test]
+    fn test_discovery() -> anyhow::Result<()> {
+        // A device stand-in that answers probes
+        let responder = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0))?;
+        let responder_addr = responder.local_addr()?;
+        let handle = std::thread::spawn(move || -> anyhow::Result<()> {
+            let mut buf = [0u8; 1024];
+            let (len, from) = responder.recv_from(&mut buf)?;
+            let (tid, xml) = decode_packet(&buf[..len]).expect("invalid probe");
+            let port = xml_tag(&xml, "port").expect("missing port").parse::<u16>()?;
+
+            // Garbage is ignored
+            responder.send_to(b"garbage", (Ipv4Addr::LOCALHOST, port))?;
+
+            let reply = encode_packet(tid, concat!(
+                "<P2P><D2C_C_R><devName>Garden</devName><uid>95270000ABCDEFGH</uid><model>RLC-810A</model>",
+                "<http>80</http><https>443</https><rtsp>554</rtsp><rtmp>0</rtmp><port>9000</port></D2C_C_R></P2P>"
+            ));
+            // Duplicate replies are reported once
+            responder.send_to(&reply, (from.ip(), port))?;
+            responder.send_to(&reply, (from.ip(), port))?;
+            Ok(())
+        });
+
+        let devices = Discovery::new()
+            .with_targets(vec![responder_addr])
+            .with_timeout(Duration::from_millis(500))
+            .run()?;
+        handle.join().unwrap()?;
+
+        assert_eq!(1, devices.len());
+        let device = &devices[0];
+        assert_eq!(IpAddr::V4(Ipv4Addr::LOCALHOST), device.ip);
+        assert_eq!(Some("95270000ABCDEFGH"), device.uid.as_deref());
+        assert_eq!(Some("RLC-810A"), device.model.as_deref());
+        assert_eq!(Some("Garden"), device.name.as_deref());
+        assert_eq!(DevicePorts { http: Some(80), https: Some(443), rtsp: Some(554), rtmp: None, media: Some(9000) }, device.ports);
+        assert_eq!("https://127.0.0.1/", device.url());
+        Ok(())
+    }
+}
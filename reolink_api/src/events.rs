@@ -0,0 +1,159 @@
+//! Motion and AI detection events, built by polling detection states and detecting their
+//! changes. See `blocking::events::EventWatcher` for the poller.
+
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::time::SystemTime;
+use crate::api::Channel;
+use crate::api::ai::get_ai_state::{AiState, GetAiStateResponse};
+use crate::api::alarm::get_md_state::GetMdStateResponse;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum EventKind {
+    Motion,
+    Person,
+    Vehicle,
+    Animal,
+}
+
+impl EventKind {
+    pub const ALL: [EventKind; 4] = [EventKind::Motion, EventKind::Person, EventKind::Vehicle, EventKind::Animal];
+}
+
+impl Display for EventKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            EventKind::Motion => "motion",
+            EventKind::Person => "person",
+            EventKind::Vehicle => "vehicle",
+            EventKind::Animal => "animal",
+        })
+    }
+}
+
+/// A detection on a channel. An event is emitted twice: when the detection starts, with no
+/// `end`, and when it ends. Times are those of the polls that observed the changes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Event {
+    pub channel: Channel,
+    pub kind: EventKind,
+    pub start: SystemTime,
+    pub end: Option<SystemTime>,
+}
+
+impl Event {
+    pub fn is_start(&self) -> bool {
+        self.end.is_none()
+    }
+}
+
+/// Active detections of a channel, as read from `GetMdState` and `GetAiState`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DetectionState {
+    pub motion: bool,
+    pub person: bool,
+    pub vehicle: bool,
+    pub animal: bool,
+}
+
+impl DetectionState {
+    /// Combines the motion state with the AI state, if the device supports AI detection.
+    pub fn new(md: &GetMdStateResponse, ai: Option<&GetAiStateResponse>) -> Self {
+        let active = |state: Option<&AiState>| state.is_some_and(|s| s.is_active());
+        DetectionState {
+            motion: md.state,
+            person: ai.is_some_and(|ai| active(ai.people.as_ref())),
+            vehicle: ai.is_some_and(|ai| active(ai.vehicle.as_ref())),
+            animal: ai.is_some_and(|ai| active(ai.dog_cat.as_ref())),
+        }
+    }
+
+    pub fn is_active(&self, kind: EventKind) -> bool {
+        match kind {
+            EventKind::Motion => self.motion,
+            EventKind::Person => self.person,
+            EventKind::Vehicle => self.vehicle,
+            EventKind::Animal => self.animal,
+        }
+    }
+}
+
+/// Detects the rising and falling edges of detection states.
+#[derive(Debug, Default)]
+pub struct EdgeDetector {
+    /// Start time of active detections
+    active: BTreeMap<(Channel, EventKind), SystemTime>,
+}
+
+impl EdgeDetector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Updates the state of a channel observed at `now`, and returns the events that started
+    /// or ended.
+    pub fn update(&mut self, channel: Channel, state: &DetectionState, now: SystemTime) -> Vec<Event> {
+        let mut events = Vec::new();
+        for kind in EventKind::ALL {
+            let key = (channel, kind);
+            match (state.is_active(kind), self.active.get(&key)) {
+                (true, None) => {
+                    self.active.insert(key, now);
+                    events.push(Event { channel, kind, start: now, end: None });
+                },
+                (false, Some(&start)) => {
+                    self.active.remove(&key);
+                    events.push(Event { channel, kind, start, end: Some(now) });
+                },
+                _ => (),
+            }
+        }
+        events
+    }
+
+    /// Ends all active events of a channel, e.g. when it goes to sleep.
+    pub fn end_all(&mut self, channel: Channel, now: SystemTime) -> Vec<Event> {
+        self.update(channel, &DetectionState::default(), now)
+    }
+
+    /// Active detections, with their start time.
+    pub fn active(&self) -> impl Iterator<Item = (Channel, EventKind, SystemTime)> + '_ {
+        self.active.iter().map(|((channel, kind), start)| (*channel, *kind, *start))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_edges() {
+        let t0 = SystemTime::UNIX_EPOCH;
+        let at = |secs| t0 + Duration::from_secs(secs);
+        let mut detector = EdgeDetector::new();
+
+        let motion = DetectionState { motion: true, ..Default::default() };
+        let person = DetectionState { motion: true, person: true, ..Default::default() };
+
+        assert_eq!(vec![Event { channel: 0, kind: EventKind::Motion, start: at(0), end: None }], detector.update(0, &motion, at(0)));
+        assert!(detector.update(0, &motion, at(1)).is_empty());
+
+        let events = detector.update(0, &person, at(2));
+        assert_eq!(1, events.len());
+        assert_eq!((EventKind::Person, at(2)), (events[0].kind, events[0].start));
+
+        // Channels are independent
+        assert_eq!(1, detector.update(1, &motion, at(2)).len());
+
+        let events = detector.update(0, &DetectionState::default(), at(5));
+        assert_eq!(vec![
+            Event { channel: 0, kind: EventKind::Motion, start: at(0), end: Some(at(5)) },
+            Event { channel: 0, kind: EventKind::Person, start: at(2), end: Some(at(5)) },
+        ], events);
+
+        assert_eq!(1, detector.active().count());
+        assert_eq!(1, detector.end_all(1, at(6)).len());
+        assert_eq!(0, detector.active().count());
+    }
+}
@@ -1,5 +1,11 @@
 mod common;
 pub mod api;
+#[cfg(feature = "baichuan")]
+pub mod baichuan;
+pub mod diff;
+pub mod discovery;
+pub mod events;
+pub mod live;
 
 // Re-export dependencies that are part of our public API
 pub use reqwest;
@@ -7,6 +13,10 @@ pub use anyhow;
 pub use bytes;
 #[cfg(feature = "chrono")]
 pub use chrono;
+#[cfg(feature = "time")]
+pub use time;
+#[cfg(feature = "jiff")]
+pub use jiff;
 
 #[cfg(feature = "blocking")]
 pub mod blocking;
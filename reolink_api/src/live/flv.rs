@@ -0,0 +1,376 @@
+//! A parser for the FLV container used by HTTP-FLV live streams and `Playback`.
+//!
+//! Only the parts needed to extract media are decoded: H.264 and H.265 video, with NAL units
+//! split according to the decoder configuration, and AAC audio. Other codecs and script data
+//! are returned as raw bytes.
+
+use std::fmt::{Display, Formatter};
+use std::io::Read;
+use bytes::Bytes;
+
+const TAG_AUDIO: u8 = 8;
+const TAG_VIDEO: u8 = 9;
+const TAG_SCRIPT: u8 = 18;
+
+const CODEC_H264: u8 = 7;
+const CODEC_H265: u8 = 12;
+const SOUND_AAC: u8 = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FlvHeader {
+    pub has_audio: bool,
+    pub has_video: bool,
+}
+
+/// A tag of an FLV stream. Timestamps are in milliseconds from the start of the stream.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FlvTag {
+    Video(VideoTag),
+    Audio(AudioTag),
+    /// Script data, e.g. the AMF0-encoded `onMetaData`
+    Script { timestamp: u32, data: Bytes },
+}
+
+impl FlvTag {
+    pub fn timestamp(&self) -> u32 {
+        match self {
+            FlvTag::Video(tag) => tag.timestamp,
+            FlvTag::Audio(tag) => tag.timestamp,
+            FlvTag::Script { timestamp, .. } => *timestamp,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VideoCodec {
+    H264,
+    H265,
+    Other(u8),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct VideoTag {
+    /// Decoding timestamp
+    pub timestamp: u32,
+    /// Offset of the presentation timestamp from the decoding timestamp
+    pub composition_time: i32,
+    pub codec: VideoCodec,
+    pub keyframe: bool,
+    pub data: VideoData,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum VideoData {
+    /// Decoder configuration: `AVCDecoderConfigurationRecord` or `HEVCDecoderConfigurationRecord`
+    Config(Bytes),
+    /// NAL units of a frame, without their length prefix
+    NalUnits(Vec<Bytes>),
+    EndOfSequence,
+    /// Data of other codecs
+    Raw(Bytes),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AudioTag {
+    pub timestamp: u32,
+    pub data: AudioData,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AudioData {
+    /// `AudioSpecificConfig`
+    AacConfig(Bytes),
+    /// A raw AAC frame, without ADTS header
+    AacFrame(Bytes),
+    /// Data of other codecs, with the FLV sound format
+    Other { format: u8, data: Bytes },
+}
+
+/// Error returned when reading an FLV stream.
+#[derive(Debug)]
+pub enum FlvError {
+    Io(std::io::Error),
+    /// The stream doesn't start with an FLV header
+    NotFlv,
+    /// The stream ended in the middle of a tag
+    Truncated,
+    /// A tag has invalid content
+    Invalid(&'static str),
+}
+
+impl Display for FlvError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FlvError::Io(err) => write!(f, "FLV read error: {err}"),
+            FlvError::NotFlv => f.write_str("not an FLV stream"),
+            FlvError::Truncated => f.write_str("truncated FLV stream"),
+            FlvError::Invalid(msg) => write!(f, "invalid FLV stream: {msg}"),
+        }
+    }
+}
+
+impl std::error::Error for FlvError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            FlvError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for FlvError {
+    fn from(err: std::io::Error) -> Self {
+        if err.kind() == std::io::ErrorKind::UnexpectedEof {
+            FlvError::Truncated
+        } else {
+            FlvError::Io(err)
+        }
+    }
+}
+
+/// Reads the tags of an FLV stream. Iteration ends at the end of the stream or after the
+/// first error.
+pub struct FlvReader<R: Read> {
+    reader: R,
+    header: FlvHeader,
+    /// Size of NAL unit length prefixes, from the decoder configuration
+    nal_length_size: usize,
+    done: bool,
+}
+
+impl <R: Read> FlvReader<R> {
+    /// Reads the FLV header of a stream.
+    pub fn new(mut reader: R) -> Result<Self, FlvError> {
+        let mut header = [0; 9];
+        reader.read_exact(&mut header).map_err(|_| FlvError::NotFlv)?;
+        if &header[..3] != b"FLV" {
+            return Err(FlvError::NotFlv);
+        }
+        let offset = u32::from_be_bytes([header[5], header[6], header[7], header[8]]) as u64;
+        // Skip any extra header bytes, and the first (zero) previous tag size
+        std::io::copy(&mut (&mut reader).take(offset.saturating_sub(9) + 4), &mut std::io::sink())?;
+
+        Ok(FlvReader {
+            reader,
+            header: FlvHeader {
+                has_audio: header[4] & 0x04 != 0,
+                has_video: header[4] & 0x01 != 0,
+            },
+            nal_length_size: 4,
+            done: false,
+        })
+    }
+
+    pub fn header(&self) -> FlvHeader {
+        self.header
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    fn read_tag(&mut self) -> Result<Option<FlvTag>, FlvError> {
+        let mut header = [0; 11];
+        // A clean end of stream is only allowed between tags
+        let n = read_full(&mut self.reader, &mut header)?;
+        if n == 0 {
+            return Ok(None);
+        } else if n < header.len() {
+            return Err(FlvError::Truncated);
+        }
+
+        let tag_type = header[0] & 0x1F;
+        let size = u32::from_be_bytes([0, header[1], header[2], header[3]]) as usize;
+        let timestamp = u32::from_be_bytes([header[7], header[4], header[5], header[6]]);
+
+        let mut data = vec![0; size];
+        self.reader.read_exact(&mut data)?;
+        let mut prev_size = [0; 4];
+        self.reader.read_exact(&mut prev_size)?;
+        let data = Bytes::from(data);
+
+        let tag = match tag_type {
+            TAG_VIDEO => FlvTag::Video(self.video_tag(timestamp, data)?),
+            TAG_AUDIO => FlvTag::Audio(audio_tag(timestamp, data)?),
+            TAG_SCRIPT => FlvTag::Script { timestamp, data },
+            _ => return Err(FlvError::Invalid("unknown tag type")),
+        };
+        Ok(Some(tag))
+    }
+
+    fn video_tag(&mut self, timestamp: u32, data: Bytes) -> Result<VideoTag, FlvError> {
+        let first = *data.first().ok_or(FlvError::Invalid("empty video tag"))?;
+        let keyframe = first >> 4 == 1;
+        let codec = match first & 0x0F {
+            CODEC_H264 => VideoCodec::H264,
+            CODEC_H265 => VideoCodec::H265,
+            other => VideoCodec::Other(other),
+        };
+        if let VideoCodec::Other(_) = codec {
+            return Ok(VideoTag { timestamp, composition_time: 0, codec, keyframe, data: VideoData::Raw(data.slice(1..)) });
+        }
+
+        if data.len() < 5 {
+            return Err(FlvError::Invalid("video tag too short"));
+        }
+        // Sign-extend the 24 bits composition time
+        let composition_time = i32::from_be_bytes([data[2], data[3], data[4], 0]) >> 8;
+        let payload = data.slice(5..);
+
+        let data = match data[1] {
+            0 => {
+                // lengthSizeMinusOne is at byte 4 of AVC records, and byte 21 of HEVC records
+                let pos = if codec == VideoCodec::H264 { 4 } else { 21 };
+                let length_size = payload.get(pos).ok_or(FlvError::Invalid("decoder configuration too short"))?;
+                self.nal_length_size = (length_size & 0x03) as usize + 1;
+                VideoData::Config(payload)
+            },
+            1 => VideoData::NalUnits(split_nal_units(&payload, self.nal_length_size)?),
+            2 => VideoData::EndOfSequence,
+            _ => return Err(FlvError::Invalid("unknown video packet type")),
+        };
+
+        Ok(VideoTag { timestamp, composition_time, codec, keyframe, data })
+    }
+}
+
+fn audio_tag(timestamp: u32, data: Bytes) -> Result<AudioTag, FlvError> {
+    let first = *data.first().ok_or(FlvError::Invalid("empty audio tag"))?;
+    let format = first >> 4;
+    let data = if format == SOUND_AAC {
+        match data.get(1) {
+            Some(0) => AudioData::AacConfig(data.slice(2..)),
+            Some(1) => AudioData::AacFrame(data.slice(2..)),
+            _ => return Err(FlvError::Invalid("unknown AAC packet type")),
+        }
+    } else {
+        AudioData::Other { format, data: data.slice(1..) }
+    };
+    Ok(AudioTag { timestamp, data })
+}
+
+/// Splits length-prefixed NAL units.
+fn split_nal_units(data: &Bytes, length_size: usize) -> Result<Vec<Bytes>, FlvError> {
+    let mut result = Vec::new();
+    let mut pos = 0;
+    while pos < data.len() {
+        let len_bytes = data.get(pos..pos + length_size).ok_or(FlvError::Invalid("truncated NAL unit length"))?;
+        let len = len_bytes.iter().fold(0usize, |acc, b| (acc << 8) | *b as usize);
+        pos += length_size;
+        if pos + len > data.len() {
+            return Err(FlvError::Invalid("truncated NAL unit"));
+        }
+        result.push(data.slice(pos..pos + len));
+        pos += len;
+    }
+    Ok(result)
+}
+
+/// Reads until `buf` is full or the end of the stream, returning the number of bytes read.
+fn read_full(reader: &mut impl Read, buf: &mut [u8]) -> Result<usize, FlvError> {
+    let mut n = 0;
+    while n < buf.len() {
+        match reader.read(&mut buf[n..]) {
+            Ok(0) => break,
+            Ok(k) => n += k,
+            Err(err) if err.kind() == std::io::ErrorKind::Interrupted => (),
+            Err(err) => return Err(err.into()),
+        }
+    }
+    Ok(n)
+}
+
+impl <R: Read> Iterator for FlvReader<R> {
+    type Item = Result<FlvTag, FlvError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        match self.read_tag() {
+            Ok(Some(tag)) => Some(Ok(tag)),
+            Ok(None) => {
+                self.done = true;
+                None
+            },
+            Err(err) => {
+                self.done = true;
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tag(tag_type: u8, timestamp: u32, data: &[u8]) -> Vec<u8> {
+        let mut tag = vec![tag_type];
+        tag.extend(&(data.len() as u32).to_be_bytes()[1..]);
+        tag.extend(&timestamp.to_be_bytes()[1..]);
+        tag.push((timestamp >> 24) as u8);
+        tag.extend([0, 0, 0]);
+        tag.extend(data);
+        tag.extend((data.len() as u32 + 11).to_be_bytes());
+        tag
+    }
+
+    fn stream() -> Vec<u8> {
+        let mut flv = b"FLV\x01\x05\x00\x00\x00\x09\x00\x00\x00\x00".to_vec();
+        flv.extend(tag(TAG_SCRIPT, 0, b"\x02\x00\x0AonMetaData"));
+        // AVC config, with 4 bytes NAL unit lengths
+        flv.extend(tag(TAG_VIDEO, 0, &[0x17, 0, 0, 0, 0, 1, 0x64, 0, 0x1F, 0xFF]));
+        flv.extend(tag(TAG_AUDIO, 0, &[0xAF, 0, 0x12, 0x10]));
+        // Keyframe with two NAL units, composition time 40
+        flv.extend(tag(TAG_VIDEO, 40, &[0x17, 1, 0, 0, 40, 0, 0, 0, 2, 0x67, 1, 0, 0, 0, 1, 0x65]));
+        flv.extend(tag(TAG_AUDIO, 45, &[0xAF, 1, 0x21, 0x22]));
+        // H.265 inter frame, after 2^24 ms
+        flv.extend(tag(TAG_VIDEO, 0x01000010, &[0x2C, 1, 0xFF, 0xFF, 0xF6, 0, 0, 0, 1, 0x02]));
+        flv
+    }
+
+    #[test]
+    fn test_read() -> Result<(), FlvError> {
+        let data = stream();
+        let mut reader = FlvReader::new(data.as_slice())?;
+        assert_eq!(FlvHeader { has_audio: true, has_video: true }, reader.header());
+
+        let tags = (&mut reader).collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(6, tags.len());
+        assert!(matches!(&tags[0], FlvTag::Script { data, .. } if data.ends_with(b"onMetaData")));
+
+        let FlvTag::Video(config) = &tags[1] else { panic!() };
+        assert_eq!(VideoCodec::H264, config.codec);
+        assert!(matches!(config.data, VideoData::Config(_)));
+
+        assert_eq!(FlvTag::Audio(AudioTag { timestamp: 0, data: AudioData::AacConfig(Bytes::from_static(&[0x12, 0x10])) }), tags[2]);
+
+        let FlvTag::Video(frame) = &tags[3] else { panic!() };
+        assert!(frame.keyframe);
+        assert_eq!(40, frame.timestamp);
+        assert_eq!(40, frame.composition_time);
+        assert_eq!(VideoData::NalUnits(vec![Bytes::from_static(&[0x67, 1]), Bytes::from_static(&[0x65])]), frame.data);
+
+        assert_eq!(45, tags[4].timestamp());
+
+        let FlvTag::Video(frame) = &tags[5] else { panic!() };
+        assert_eq!(VideoCodec::H265, frame.codec);
+        assert!(!frame.keyframe);
+        assert_eq!(-10, frame.composition_time);
+        assert_eq!(0x01000010, frame.timestamp);
+        Ok(())
+    }
+
+    #[test]
+    fn test_errors() {
+        assert!(matches!(FlvReader::new(&b"<html>"[..]), Err(FlvError::NotFlv)));
+
+        let data = stream();
+        let mut reader = FlvReader::new(&data[..data.len() - 3]).unwrap();
+        let results = (&mut reader).collect::<Vec<_>>();
+        assert_eq!(6, results.len());
+        assert!(matches!(results[5], Err(FlvError::Truncated)));
+        assert!(reader.next().is_none());
+    }
+}
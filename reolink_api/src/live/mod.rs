@@ -0,0 +1,36 @@
+//! Live streams, served by devices over HTTP-FLV, and the urls of the RTSP and RTMP streams.
+
+use reqwest::Url;
+use crate::api::Channel;
+use crate::api::record::StreamType;
+use crate::common::Credentials;
+
+pub mod flv;
+pub mod mp4;
+pub mod urls;
+
+/// The HTTP-FLV url of a channel's live stream, e.g.
+/// `flv?port=1935&app=bcs&stream=channel0_main.bcs&token=...`, where `rtmp_port` is the
+/// `rtmpPort` of `GetNetPort`. The credentials must have a valid token.
+pub(crate) fn flv_url(api_url: &Url, rtmp_port: u16, channel: Channel, stream_type: &StreamType, creds: &Credentials) -> anyhow::Result<Url> {
+    urls::flv(api_url, rtmp_port, channel, stream_type, &urls::Auth::Token(urls::token(creds)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use crate::common::Token;
+
+    #[test]
+    fn test_flv_url() -> anyhow::Result<()> {
+        let api_url = Url::parse("https://192.168.0.42/cgi-bin/api.cgi")?;
+        let creds = Credentials::new("admin".to_string(), "secret".to_string());
+        assert!(flv_url(&api_url, 1935, 0, &StreamType::Main, &creds).is_err());
+
+        *creds.token.write().unwrap() = Some(Token::new("abc".to_string(), Duration::from_secs(3600)));
+        let url = flv_url(&api_url, 1936, 1, &StreamType::Sub, &creds)?;
+        assert_eq!("https://192.168.0.42/flv?port=1936&app=bcs&stream=channel1_sub.bcs&token=abc", url.as_str());
+        Ok(())
+    }
+}
@@ -0,0 +1,938 @@
+//! A fragmented MP4 muxer for the H.264/H.265 and AAC streams read from FLV.
+//!
+//! The output starts with an initialization segment (`ftyp` and `moov`), followed by a fragment
+//! (`moof` and `mdat`) for each group of pictures. Tags preceding the first keyframe are
+//! dropped, and timestamps are shifted so that the file starts at zero. Video decode times are
+//! computed from the differences between frame timestamps, so that timestamp resets in the
+//! stream don't break the file, and audio is aligned on video at each fragment.
+
+use std::io::{Error, ErrorKind, Write};
+use bytes::Bytes;
+use crate::live::flv::{AudioData, AudioTag, FlvTag, VideoCodec, VideoData, VideoTag};
+
+const VIDEO_TRACK: u32 = 1;
+const AUDIO_TRACK: u32 = 2;
+const VIDEO_TIMESCALE: u32 = 90_000;
+/// Samples in an AAC frame
+const AAC_FRAME_SAMPLES: u32 = 1024;
+/// Duration of the last frame, if it can't be computed from the next frame
+const DEFAULT_FRAME_DURATION: u32 = VIDEO_TIMESCALE / 25;
+
+const AAC_SAMPLE_RATES: [u32; 13] = [96000, 88200, 64000, 48000, 44100, 32000, 24000, 22050, 16000, 12000, 11025, 8000, 7350];
+
+/// Writes FLV tags to a fragmented MP4 file. `finish` must be called to write the last fragment.
+pub struct Mp4Writer<W: Write> {
+    out: W,
+    video_config: Option<(VideoCodec, Bytes)>,
+    audio_config: Option<Bytes>,
+    /// Tracks of the initialization segment, once written
+    tracks: Option<Tracks>,
+    /// FLV timestamp of the first keyframe
+    start: u32,
+    sequence: u32,
+    video_samples: Vec<Sample>,
+    audio_samples: Vec<Sample>,
+    /// Decode time of the next fragment's first sample, in track timescale
+    video_time: u64,
+    /// Decode time following the last audio sample written, in track timescale
+    audio_time: Option<u64>,
+    last_video_duration: u32,
+}
+
+struct Tracks {
+    audio_rate: Option<u32>,
+}
+
+struct Sample {
+    /// FLV timestamp
+    timestamp: u32,
+    composition_time: i32,
+    keyframe: bool,
+    data: Vec<u8>,
+}
+
+impl <W: Write> Mp4Writer<W> {
+    pub fn new(out: W) -> Self {
+        Mp4Writer {
+            out,
+            video_config: None,
+            audio_config: None,
+            tracks: None,
+            start: 0,
+            sequence: 0,
+            video_samples: Vec::new(),
+            audio_samples: Vec::new(),
+            video_time: 0,
+            audio_time: None,
+            last_video_duration: DEFAULT_FRAME_DURATION,
+        }
+    }
+
+    /// Adds a tag to the file. Codec configurations must precede the frames that use them,
+    /// which is always the case in streams sent by devices.
+    pub fn write_tag(&mut self, tag: &FlvTag) -> std::io::Result<()> {
+        match tag {
+            FlvTag::Video(tag) => self.write_video(tag),
+            FlvTag::Audio(tag) => self.write_audio(tag),
+            FlvTag::Script { .. } => Ok(()),
+        }
+    }
+
+    /// Duration written so far, in milliseconds.
+    pub fn duration(&self) -> u32 {
+        let pending = self.video_samples.windows(2)
+            .map(|s| self.frame_duration(s[0].timestamp, s[1].timestamp))
+            .sum::<u32>();
+        (self.video_time * 1000 / VIDEO_TIMESCALE as u64) as u32 + pending
+    }
+
+    /// Duration the file would have once a video frame with this timestamp is added, in
+    /// milliseconds.
+    pub fn duration_at(&self, timestamp: u32) -> u32 {
+        match self.video_samples.last() {
+            Some(last) => self.duration() + self.frame_duration(last.timestamp, timestamp),
+            None => self.duration(),
+        }
+    }
+
+    /// Duration of a video frame in milliseconds, from its timestamp and the next one's. Like in
+    /// fragments, the previous duration is used if timestamps don't increase.
+    fn frame_duration(&self, timestamp: u32, next: u32) -> u32 {
+        if next > timestamp {
+            next - timestamp
+        } else {
+            self.last_video_duration * 1000 / VIDEO_TIMESCALE
+        }
+    }
+
+    /// Writes the last fragment and returns the underlying writer.
+    pub fn finish(mut self) -> std::io::Result<W> {
+        if self.tracks.is_some() {
+            self.write_fragment(None)?;
+        }
+        self.out.flush()?;
+        Ok(self.out)
+    }
+
+    fn write_video(&mut self, tag: &VideoTag) -> std::io::Result<()> {
+        match &tag.data {
+            VideoData::Config(config) => {
+                if self.tracks.is_none() {
+                    self.video_config = Some((tag.codec, config.clone()));
+                }
+                Ok(())
+            },
+            VideoData::NalUnits(nal_units) => {
+                if self.tracks.is_none() {
+                    if !tag.keyframe {
+                        return Ok(());
+                    }
+                    self.write_init(tag.timestamp)?;
+                } else if tag.keyframe {
+                    self.write_fragment(Some(tag.timestamp))?;
+                }
+
+                let mut data = Vec::with_capacity(nal_units.iter().map(|n| n.len() + 4).sum());
+                for nal in nal_units {
+                    data.extend((nal.len() as u32).to_be_bytes());
+                    data.extend(nal);
+                }
+                self.video_samples.push(Sample {
+                    timestamp: tag.timestamp,
+                    composition_time: tag.composition_time,
+                    keyframe: tag.keyframe,
+                    data,
+                });
+                Ok(())
+            },
+            VideoData::EndOfSequence => Ok(()),
+            VideoData::Raw(_) => Err(Error::new(ErrorKind::InvalidData, format!("unsupported video codec {:?}", tag.codec))),
+        }
+    }
+
+    fn write_audio(&mut self, tag: &AudioTag) -> std::io::Result<()> {
+        match &tag.data {
+            AudioData::AacConfig(config) => {
+                if self.tracks.is_none() {
+                    self.audio_config = Some(config.clone());
+                }
+            },
+            AudioData::AacFrame(frame) => {
+                let Some(tracks) = &self.tracks else { return Ok(()) };
+                if tracks.audio_rate.is_none() {
+                    return Ok(());
+                }
+                // Audio preceding the first keyframe
+                if self.sequence == 0 && tag.timestamp < self.start {
+                    return Ok(());
+                }
+                self.audio_samples.push(Sample {
+                    timestamp: tag.timestamp,
+                    composition_time: 0,
+                    keyframe: true,
+                    data: frame.to_vec(),
+                });
+            },
+            // Other audio codecs are dropped
+            AudioData::Other { .. } => (),
+        }
+        Ok(())
+    }
+
+    /// Writes `ftyp` and `moov`, once the configuration of each track is known.
+    fn write_init(&mut self, start: u32) -> std::io::Result<()> {
+        let (codec, config) = self.video_config.clone()
+            .ok_or_else(|| Error::new(ErrorKind::InvalidData, "keyframe without decoder configuration"))?;
+        let audio = self.audio_config.as_ref().and_then(|c| AacConfig::parse(c).map(|a| (a, c.clone())));
+
+        let mut init = Vec::new();
+        mp4_box(&mut init, b"ftyp", |b| {
+            b.extend(b"isom");
+            b.extend(0x200u32.to_be_bytes());
+            b.extend(b"isomiso6mp41");
+        });
+        mp4_box(&mut init, b"moov", |b| {
+            let next_track = if audio.is_some() { AUDIO_TRACK + 1 } else { VIDEO_TRACK + 1 };
+            full_box(b, b"mvhd", 0, 0, |b| {
+                b.extend([0; 8]); // Creation and modification times
+                b.extend(1000u32.to_be_bytes());
+                b.extend(0u32.to_be_bytes()); // Duration
+                b.extend(0x00010000u32.to_be_bytes()); // Rate
+                b.extend(0x0100u16.to_be_bytes()); // Volume
+                b.extend([0; 10]);
+                matrix(b);
+                b.extend([0; 24]);
+                b.extend(next_track.to_be_bytes());
+            });
+            video_trak(b, codec, &config)?;
+            if let Some((aac, config)) = &audio {
+                audio_trak(b, aac, config);
+            }
+            mp4_box(b, b"mvex", |b| {
+                trex(b, VIDEO_TRACK);
+                if audio.is_some() {
+                    trex(b, AUDIO_TRACK);
+                }
+            });
+            Ok::<_, Error>(())
+        })?;
+
+        self.out.write_all(&init)?;
+        self.start = start;
+        self.tracks = Some(Tracks { audio_rate: audio.map(|(a, _)| a.sample_rate) });
+        Ok(())
+    }
+
+    /// Writes the pending samples. `next` is the timestamp of the following video frame.
+    fn write_fragment(&mut self, next: Option<u32>) -> std::io::Result<()> {
+        if self.video_samples.is_empty() {
+            return Ok(());
+        }
+        self.sequence += 1;
+
+        // Video durations, from timestamp differences
+        let mut video = Vec::with_capacity(self.video_samples.len());
+        for (i, sample) in self.video_samples.iter().enumerate() {
+            let next = self.video_samples.get(i + 1).map(|s| s.timestamp).or(next);
+            let duration = match next {
+                Some(next) if next > sample.timestamp => (next - sample.timestamp) * (VIDEO_TIMESCALE / 1000),
+                _ => self.last_video_duration,
+            };
+            self.last_video_duration = duration;
+            let flags = if sample.keyframe { 0x02000000 } else { 0x01010000 };
+            let cts = sample.composition_time * (VIDEO_TIMESCALE / 1000) as i32;
+            video.push((duration, sample.data.len() as u32, flags, cts));
+        }
+        let audio = self.audio_samples.iter()
+            .map(|s| (AAC_FRAME_SAMPLES, s.data.len() as u32, 0x02000000, 0))
+            .collect::<Vec<_>>();
+        let audio_time = self.sync_audio();
+
+        let video_size = video.iter().map(|s| s.1 as usize).sum::<usize>();
+        let mut moof = Vec::new();
+        let mut offsets = Vec::new();
+        mp4_box(&mut moof, b"moof", |b| {
+            full_box(b, b"mfhd", 0, 0, |b| b.extend(self.sequence.to_be_bytes()));
+            traf(b, VIDEO_TRACK, self.video_time, &video, &mut offsets);
+            if let (Some(time), false) = (audio_time, audio.is_empty()) {
+                traf(b, AUDIO_TRACK, time, &audio, &mut offsets);
+            }
+        });
+
+        // Data offsets are relative to the start of moof
+        let data_start = moof.len() as u32 + 8;
+        let data_offsets = [data_start, data_start + video_size as u32];
+        for (pos, offset) in offsets.iter().zip(data_offsets) {
+            moof[*pos..*pos + 4].copy_from_slice(&offset.to_be_bytes());
+        }
+
+        let mdat_size = 8 + video_size + self.audio_samples.iter().map(|s| s.data.len()).sum::<usize>();
+        self.out.write_all(&moof)?;
+        self.out.write_all(&(mdat_size as u32).to_be_bytes())?;
+        self.out.write_all(b"mdat")?;
+        for sample in self.video_samples.drain(..).chain(self.audio_samples.drain(..)) {
+            self.out.write_all(&sample.data)?;
+        }
+
+        self.video_time += video.iter().map(|s| s.0 as u64).sum::<u64>();
+        if let Some(time) = audio_time {
+            self.audio_time = Some(time + audio.iter().map(|s| s.0 as u64).sum::<u64>());
+        }
+        Ok(())
+    }
+
+    /// Decode time of the pending audio samples. Audio continues the previous fragment, unless
+    /// it drifted from the video by more than a frame according to the FLV timestamps, e.g.
+    /// after a timestamp reset or lost frames.
+    fn sync_audio(&self) -> Option<u64> {
+        let rate = self.tracks.as_ref()?.audio_rate? as i64;
+        let audio = self.audio_samples.first()?;
+        let video = self.video_samples.first()?;
+
+        // Position of the first audio sample relative to the first video sample of the fragment
+        let video_time = self.video_time as i64 * rate / VIDEO_TIMESCALE as i64;
+        let offset = (audio.timestamp as i64 - video.timestamp as i64) * rate / 1000;
+        let expected = (video_time + offset).max(0) as u64;
+
+        match self.audio_time {
+            Some(time) if time.abs_diff(expected) <= AAC_FRAME_SAMPLES as u64 => Some(time),
+            _ => Some(expected),
+        }
+    }
+}
+
+//----- Boxes
+
+fn mp4_box<T>(out: &mut Vec<u8>, kind: &[u8; 4], f: impl FnOnce(&mut Vec<u8>) -> T) -> T {
+    let start = out.len();
+    out.extend([0; 4]);
+    out.extend(kind);
+    let result = f(out);
+    let size = (out.len() - start) as u32;
+    out[start..start + 4].copy_from_slice(&size.to_be_bytes());
+    result
+}
+
+fn full_box<T>(out: &mut Vec<u8>, kind: &[u8; 4], version: u8, flags: u32, f: impl FnOnce(&mut Vec<u8>) -> T) -> T {
+    mp4_box(out, kind, |b| {
+        b.push(version);
+        b.extend(&flags.to_be_bytes()[1..]);
+        f(b)
+    })
+}
+
+fn matrix(b: &mut Vec<u8>) {
+    for v in [0x00010000u32, 0, 0, 0, 0x00010000, 0, 0, 0, 0x40000000] {
+        b.extend(v.to_be_bytes());
+    }
+}
+
+fn tkhd(b: &mut Vec<u8>, track: u32, volume: u16, width: u16, height: u16) {
+    full_box(b, b"tkhd", 0, 3, |b| {
+        b.extend([0; 8]); // Creation and modification times
+        b.extend(track.to_be_bytes());
+        b.extend([0; 4]);
+        b.extend(0u32.to_be_bytes()); // Duration
+        b.extend([0; 8]);
+        b.extend([0; 4]); // Layer and alternate group
+        b.extend(volume.to_be_bytes());
+        b.extend([0; 2]);
+        matrix(b);
+        b.extend(((width as u32) << 16).to_be_bytes());
+        b.extend(((height as u32) << 16).to_be_bytes());
+    });
+}
+
+fn mdhd_hdlr(b: &mut Vec<u8>, timescale: u32, handler: &[u8; 4], name: &str) {
+    full_box(b, b"mdhd", 0, 0, |b| {
+        b.extend([0; 8]);
+        b.extend(timescale.to_be_bytes());
+        b.extend(0u32.to_be_bytes());
+        b.extend(0x55C4u16.to_be_bytes()); // "und"
+        b.extend([0; 2]);
+    });
+    full_box(b, b"hdlr", 0, 0, |b| {
+        b.extend([0; 4]);
+        b.extend(handler);
+        b.extend([0; 12]);
+        b.extend(name.as_bytes());
+        b.push(0);
+    });
+}
+
+/// `dinf` and `stbl` boxes, with a single sample entry and no samples.
+fn dinf_stbl(b: &mut Vec<u8>, sample_entry: impl FnOnce(&mut Vec<u8>)) {
+    mp4_box(b, b"dinf", |b| {
+        full_box(b, b"dref", 0, 0, |b| {
+            b.extend(1u32.to_be_bytes());
+            full_box(b, b"url ", 0, 1, |_| ());
+        });
+    });
+    mp4_box(b, b"stbl", |b| {
+        full_box(b, b"stsd", 0, 0, |b| {
+            b.extend(1u32.to_be_bytes());
+            sample_entry(b);
+        });
+        full_box(b, b"stts", 0, 0, |b| b.extend([0; 4]));
+        full_box(b, b"stsc", 0, 0, |b| b.extend([0; 4]));
+        full_box(b, b"stsz", 0, 0, |b| b.extend([0; 8]));
+        full_box(b, b"stco", 0, 0, |b| b.extend([0; 4]));
+    });
+}
+
+fn video_trak(b: &mut Vec<u8>, codec: VideoCodec, config: &Bytes) -> std::io::Result<()> {
+    let (entry, config_box, length_size_pos) = match codec {
+        VideoCodec::H264 => (b"avc1", b"avcC", 4),
+        VideoCodec::H265 => (b"hvc1", b"hvcC", 21),
+        VideoCodec::Other(_) => return Err(Error::new(ErrorKind::InvalidData, format!("unsupported video codec {:?}", codec))),
+    };
+    let (width, height) = sps::dimensions(codec, config).unwrap_or((0, 0));
+
+    // Samples are written with 4 bytes NAL unit lengths
+    let mut config = config.to_vec();
+    if let Some(length_size) = config.get_mut(length_size_pos) {
+        *length_size |= 0x03;
+    }
+
+    mp4_box(b, b"trak", |b| {
+        tkhd(b, VIDEO_TRACK, 0, width, height);
+        mp4_box(b, b"mdia", |b| {
+            mdhd_hdlr(b, VIDEO_TIMESCALE, b"vide", "Video");
+            mp4_box(b, b"minf", |b| {
+                full_box(b, b"vmhd", 0, 1, |b| b.extend([0; 8]));
+                dinf_stbl(b, |b| {
+                    mp4_box(b, entry, |b| {
+                        b.extend([0; 6]);
+                        b.extend(1u16.to_be_bytes()); // Data reference index
+                        b.extend([0; 16]);
+                        b.extend(width.to_be_bytes());
+                        b.extend(height.to_be_bytes());
+                        b.extend(0x00480000u32.to_be_bytes()); // 72 dpi
+                        b.extend(0x00480000u32.to_be_bytes());
+                        b.extend([0; 4]);
+                        b.extend(1u16.to_be_bytes()); // Frame count
+                        b.extend([0; 32]); // Compressor name
+                        b.extend(0x0018u16.to_be_bytes()); // Depth
+                        b.extend((-1i16).to_be_bytes());
+                        mp4_box(b, config_box, |b| b.extend(&config));
+                    });
+                });
+            });
+        });
+    });
+    Ok(())
+}
+
+fn audio_trak(b: &mut Vec<u8>, aac: &AacConfig, config: &Bytes) {
+    mp4_box(b, b"trak", |b| {
+        tkhd(b, AUDIO_TRACK, 0x0100, 0, 0);
+        mp4_box(b, b"mdia", |b| {
+            mdhd_hdlr(b, aac.sample_rate, b"soun", "Audio");
+            mp4_box(b, b"minf", |b| {
+                full_box(b, b"smhd", 0, 0, |b| b.extend([0; 4]));
+                dinf_stbl(b, |b| {
+                    mp4_box(b, b"mp4a", |b| {
+                        b.extend([0; 6]);
+                        b.extend(1u16.to_be_bytes());
+                        b.extend([0; 8]);
+                        b.extend((aac.channels as u16).to_be_bytes());
+                        b.extend(16u16.to_be_bytes()); // Sample size
+                        b.extend([0; 4]);
+                        // 16.16 fixed point, only the integer part fits
+                        b.extend(((aac.sample_rate.min(0xFFFF)) << 16).to_be_bytes());
+                        full_box(b, b"esds", 0, 0, |b| esds(b, config));
+                    });
+                });
+            });
+        });
+    });
+}
+
+/// MPEG-4 elementary stream descriptor for an AAC `AudioSpecificConfig`.
+fn esds(b: &mut Vec<u8>, config: &[u8]) {
+    let descriptor = |b: &mut Vec<u8>, tag: u8, len: usize| {
+        b.push(tag);
+        b.push(len as u8);
+    };
+    let decoder_specific_len = 2 + config.len();
+    let decoder_config_len = 13 + decoder_specific_len;
+    descriptor(b, 0x03, 3 + 2 + decoder_config_len + 3);
+    b.extend(AUDIO_TRACK.to_be_bytes()[2..].iter()); // ES_ID
+    b.push(0); // Flags
+    descriptor(b, 0x04, decoder_config_len);
+    b.push(0x40); // MPEG-4 audio
+    b.push(0x15); // Audio stream
+    b.extend([0; 3]); // Buffer size
+    b.extend([0; 8]); // Max and average bitrate
+    descriptor(b, 0x05, config.len());
+    b.extend(config);
+    descriptor(b, 0x06, 1);
+    b.push(0x02);
+}
+
+fn trex(b: &mut Vec<u8>, track: u32) {
+    full_box(b, b"trex", 0, 0, |b| {
+        b.extend(track.to_be_bytes());
+        b.extend(1u32.to_be_bytes()); // Sample description index
+        b.extend([0; 12]);
+    });
+}
+
+/// A track fragment. The position of the data offset field is added to `offsets`.
+fn traf(b: &mut Vec<u8>, track: u32, decode_time: u64, samples: &[(u32, u32, u32, i32)], offsets: &mut Vec<usize>) {
+    mp4_box(b, b"traf", |b| {
+        // default-base-is-moof
+        full_box(b, b"tfhd", 0, 0x020000, |b| b.extend(track.to_be_bytes()));
+        full_box(b, b"tfdt", 1, 0, |b| b.extend(decode_time.to_be_bytes()));
+        // Data offset, and per-sample duration, size, flags and composition time offset
+        full_box(b, b"trun", 1, 0x000F01, |b| {
+            b.extend((samples.len() as u32).to_be_bytes());
+            offsets.push(b.len());
+            b.extend([0; 4]);
+            for (duration, size, flags, cts) in samples {
+                b.extend(duration.to_be_bytes());
+                b.extend(size.to_be_bytes());
+                b.extend(flags.to_be_bytes());
+                b.extend(cts.to_be_bytes());
+            }
+        });
+    });
+}
+
+//----- Codec configurations
+
+struct AacConfig {
+    sample_rate: u32,
+    channels: u8,
+}
+
+impl AacConfig {
+    /// Reads the sample rate and channels of an `AudioSpecificConfig`.
+    fn parse(config: &[u8]) -> Option<AacConfig> {
+        let mut bits = sps::BitReader::new(config);
+        let object_type = bits.bits(5)?;
+        if object_type == 31 {
+            bits.bits(6)?;
+        }
+        let sample_rate = match bits.bits(4)? {
+            15 => bits.bits(24)?,
+            index => *AAC_SAMPLE_RATES.get(index as usize)?,
+        };
+        let channels = bits.bits(4)? as u8;
+        Some(AacConfig { sample_rate, channels })
+    }
+}
+
+/// Reads picture dimensions from sequence parameter sets.
+mod sps {
+    use crate::live::flv::VideoCodec;
+
+    /// Reads bits from a NAL unit payload, skipping emulation prevention bytes.
+    pub(super) struct BitReader {
+        data: Vec<u8>,
+        pos: usize,
+    }
+
+    impl BitReader {
+        pub fn new(data: &[u8]) -> Self {
+            BitReader { data: data.to_vec(), pos: 0 }
+        }
+
+        fn from_nal(nal: &[u8]) -> Self {
+            let mut data = Vec::with_capacity(nal.len());
+            let mut zeros = 0;
+            for &b in nal {
+                if zeros >= 2 && b == 3 {
+                    zeros = 0;
+                    continue;
+                }
+                zeros = if b == 0 { zeros + 1 } else { 0 };
+                data.push(b);
+            }
+            BitReader { data, pos: 0 }
+        }
+
+        pub fn bits(&mut self, n: usize) -> Option<u32> {
+            let mut v = 0u32;
+            for _ in 0..n {
+                let byte = *self.data.get(self.pos / 8)?;
+                v = (v << 1) | ((byte >> (7 - self.pos % 8)) & 1) as u32;
+                self.pos += 1;
+            }
+            Some(v)
+        }
+
+        fn flag(&mut self) -> Option<bool> {
+            Some(self.bits(1)? == 1)
+        }
+
+        fn skip(&mut self, n: usize) -> Option<()> {
+            self.bits(n).map(|_| ())
+        }
+
+        /// Unsigned Exp-Golomb code
+        fn ue(&mut self) -> Option<u32> {
+            let mut zeros = 0;
+            while !self.flag()? {
+                zeros += 1;
+                if zeros > 31 {
+                    return None;
+                }
+            }
+            Some((1u32 << zeros) - 1 + self.bits(zeros)?)
+        }
+
+        /// Signed Exp-Golomb code
+        fn se(&mut self) -> Option<i32> {
+            let v = self.ue()?;
+            Some(if v % 2 == 1 { v.div_ceil(2) as i32 } else { -((v / 2) as i32) })
+        }
+    }
+
+    /// Dimensions from the SPS of a decoder configuration record.
+    pub(super) fn dimensions(codec: VideoCodec, config: &[u8]) -> Option<(u16, u16)> {
+        match codec {
+            VideoCodec::H264 => {
+                // One SPS is expected, after the 6 bytes header
+                let len = u16::from_be_bytes([*config.get(6)?, *config.get(7)?]) as usize;
+                h264(config.get(8..8 + len)?)
+            },
+            VideoCodec::H265 => {
+                let mut pos = 23;
+                for _ in 0..*config.get(22)? {
+                    let nal_type = config.get(pos)? & 0x3F;
+                    let count = u16::from_be_bytes([*config.get(pos + 1)?, *config.get(pos + 2)?]);
+                    pos += 3;
+                    for _ in 0..count {
+                        let len = u16::from_be_bytes([*config.get(pos)?, *config.get(pos + 1)?]) as usize;
+                        if nal_type == 33 {
+                            return h265(config.get(pos + 2..pos + 2 + len)?);
+                        }
+                        pos += 2 + len;
+                    }
+                }
+                None
+            },
+            VideoCodec::Other(_) => None,
+        }
+    }
+
+    /// Frame cropping units for a chroma format: horizontal, and vertical for a frame.
+    fn crop_units(chroma_format: u32) -> (u32, u32) {
+        match chroma_format {
+            1 => (2, 2),
+            2 => (2, 1),
+            _ => (1, 1),
+        }
+    }
+
+    pub(super) fn h264(sps: &[u8]) -> Option<(u16, u16)> {
+        let mut r = BitReader::from_nal(sps);
+        r.skip(8)?; // NAL header
+        let profile = r.bits(8)?;
+        r.skip(16)?; // Constraints and level
+        r.ue()?; // SPS id
+
+        let mut chroma_format = 1;
+        if matches!(profile, 100 | 110 | 122 | 244 | 44 | 83 | 86 | 118 | 128 | 138 | 139 | 134 | 135) {
+            chroma_format = r.ue()?;
+            if chroma_format == 3 {
+                r.skip(1)?; // Separate colour planes
+            }
+            r.ue()?; // Luma bit depth
+            r.ue()?; // Chroma bit depth
+            r.skip(1)?;
+            if r.flag()? {
+                // Scaling matrices
+                for i in 0..if chroma_format == 3 { 12 } else { 8 } {
+                    if r.flag()? {
+                        let size = if i < 6 { 16 } else { 64 };
+                        let (mut last, mut next) = (8i32, 8i32);
+                        for _ in 0..size {
+                            if next != 0 {
+                                next = (last + r.se()? + 256) % 256;
+                            }
+                            last = if next == 0 { last } else { next };
+                        }
+                    }
+                }
+            }
+        }
+
+        r.ue()?; // log2_max_frame_num
+        match r.ue()? {
+            0 => { r.ue()?; },
+            1 => {
+                r.skip(1)?;
+                r.se()?;
+                r.se()?;
+                for _ in 0..r.ue()? {
+                    r.se()?;
+                }
+            },
+            _ => (),
+        }
+        r.ue()?; // Reference frames
+        r.skip(1)?;
+        let width_mbs = r.ue()? + 1;
+        let height_units = r.ue()? + 1;
+        let frame_mbs_only = r.flag()?;
+        if !frame_mbs_only {
+            r.skip(1)?;
+        }
+        r.skip(1)?;
+
+        let field_factor = if frame_mbs_only { 1 } else { 2 };
+        let mut width = width_mbs * 16;
+        let mut height = height_units * 16 * field_factor;
+        if r.flag()? {
+            let (left, right, top, bottom) = (r.ue()?, r.ue()?, r.ue()?, r.ue()?);
+            let (unit_x, unit_y) = if chroma_format == 0 { (1, 1) } else { crop_units(chroma_format) };
+            width = width.checked_sub(unit_x * (left + right))?;
+            height = height.checked_sub(unit_y * field_factor * (top + bottom))?;
+        }
+        Some((width.try_into().ok()?, height.try_into().ok()?))
+    }
+
+    pub(super) fn h265(sps: &[u8]) -> Option<(u16, u16)> {
+        let mut r = BitReader::from_nal(sps);
+        r.skip(16)?; // NAL header
+        r.skip(4)?; // VPS id
+        let sub_layers = r.bits(3)? as usize;
+        r.skip(1)?;
+
+        // profile_tier_level
+        r.skip(96)?;
+        let mut profile_present = [false; 8];
+        let mut level_present = [false; 8];
+        for i in 0..sub_layers {
+            profile_present[i] = r.flag()?;
+            level_present[i] = r.flag()?;
+        }
+        if sub_layers > 0 {
+            for _ in sub_layers..8 {
+                r.skip(2)?;
+            }
+        }
+        for i in 0..sub_layers {
+            if profile_present[i] {
+                r.skip(88)?;
+            }
+            if level_present[i] {
+                r.skip(8)?;
+            }
+        }
+
+        r.ue()?; // SPS id
+        let chroma_format = r.ue()?;
+        if chroma_format == 3 {
+            r.skip(1)?;
+        }
+        let mut width = r.ue()?;
+        let mut height = r.ue()?;
+        if r.flag()? {
+            let (left, right, top, bottom) = (r.ue()?, r.ue()?, r.ue()?, r.ue()?);
+            let (unit_x, unit_y) = crop_units(chroma_format);
+            width = width.checked_sub(unit_x * (left + right))?;
+            height = height.checked_sub(unit_y * (top + bottom))?;
+        }
+        Some((width.try_into().ok()?, height.try_into().ok()?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Writes Exp-Golomb encoded values, to build parameter sets.
+    #[derive(Default)]
+    struct BitWriter {
+        bits: Vec<bool>,
+    }
+
+    impl BitWriter {
+        fn bits(&mut self, n: usize, v: u32) -> &mut Self {
+            for i in (0..n).rev() {
+                self.bits.push((v >> i) & 1 == 1);
+            }
+            self
+        }
+
+        fn ue(&mut self, v: u32) -> &mut Self {
+            let len = 32 - (v + 1).leading_zeros() as usize;
+            self.bits(len - 1, 0).bits(len, v + 1)
+        }
+
+        fn bytes(&mut self) -> Vec<u8> {
+            self.bits(1, 1); // Stop bit
+            self.bits.chunks(8).map(|c| c.iter().enumerate().fold(0, |acc, (i, b)| acc | ((*b as u8) << (7 - i)))).collect()
+        }
+    }
+
+    /// H.264 high profile SPS, 1920x1080 coded as 1088 lines with cropping
+    fn h264_sps() -> Vec<u8> {
+        let mut w = BitWriter::default();
+        w.bits(8, 0x67).bits(8, 100).bits(16, 0x0028).ue(0);
+        w.ue(1).ue(0).ue(0).bits(1, 0).bits(1, 0); // 4:2:0, 8 bits, no scaling matrix
+        w.ue(0).ue(0).ue(0); // Frame num, POC type 0, POC lsb
+        w.ue(1).bits(1, 0); // Reference frames
+        w.ue(119).ue(67).bits(1, 1).bits(1, 1); // 120x68 macroblocks, frames only
+        w.bits(1, 1).ue(0).ue(0).ue(0).ue(4); // Crop 8 lines at the bottom
+        w.bits(1, 0); // No VUI
+        w.bytes()
+    }
+
+    /// H.265 SPS, 2560x1440, one sub-layer
+    fn h265_sps() -> Vec<u8> {
+        let mut w = BitWriter::default();
+        w.bits(16, 0x4201).bits(4, 0).bits(3, 0).bits(1, 1);
+        w.bits(32, 0x01600000).bits(32, 0).bits(24, 0).bits(8, 153); // profile_tier_level
+        w.ue(0).ue(1).ue(2560).ue(1440).bits(1, 0);
+        w.bytes()
+    }
+
+    #[test]
+    fn test_sps() {
+        assert_eq!(Some((1920, 1080)), sps::h264(&h264_sps()));
+        assert_eq!(Some((2560, 1440)), sps::h265(&h265_sps()));
+        assert_eq!(None, sps::h264(&[0x67, 100]));
+    }
+
+    fn avc_config(sps: &[u8]) -> Bytes {
+        let mut config = vec![1, sps[1], sps[2], sps[3], 0xFC | 3, 0xE0 | 1];
+        config.extend((sps.len() as u16).to_be_bytes());
+        config.extend(sps);
+        config.extend([1, 0, 2, 0x68, 0xEE]);
+        config.into()
+    }
+
+    fn video(timestamp: u32, keyframe: bool, data: VideoData) -> FlvTag {
+        FlvTag::Video(VideoTag { timestamp, composition_time: 0, codec: VideoCodec::H264, keyframe, data })
+    }
+
+    fn audio(timestamp: u32, data: AudioData) -> FlvTag {
+        FlvTag::Audio(AudioTag { timestamp, data })
+    }
+
+    /// Top-level boxes, as (type, content)
+    fn boxes(data: &[u8]) -> Vec<(String, &[u8])> {
+        let mut result = Vec::new();
+        let mut pos = 0;
+        while pos < data.len() {
+            let size = u32::from_be_bytes(data[pos..pos + 4].try_into().unwrap()) as usize;
+            result.push((String::from_utf8_lossy(&data[pos + 4..pos + 8]).to_string(), &data[pos + 8..pos + size]));
+            pos += size;
+        }
+        result
+    }
+
+    fn find<'a>(data: &'a [u8], path: &[&str]) -> Option<&'a [u8]> {
+        let (content, rest) = match path {
+            [] => return Some(data),
+            [first, rest @ ..] => (boxes(data).into_iter().find(|(kind, _)| kind == first)?.1, rest),
+        };
+        // Skip the version and flags of full boxes, and the fixed part of sample entries
+        let skip = match path[0] { "stsd" | "dref" => 8, "avc1" => 78, _ => 0 };
+        find(&content[skip..], rest)
+    }
+
+    #[test]
+    fn test_mux() -> std::io::Result<()> {
+        let mut writer = Mp4Writer::new(Vec::new());
+        writer.write_tag(&FlvTag::Script { timestamp: 0, data: Bytes::new() })?;
+        writer.write_tag(&video(0, true, VideoData::Config(avc_config(&h264_sps()))))?;
+        writer.write_tag(&audio(0, AudioData::AacConfig(Bytes::from_static(&[0x14, 0x08]))))?;
+        // Dropped: before the first keyframe
+        writer.write_tag(&video(960, false, VideoData::NalUnits(vec![Bytes::from_static(&[0x41])])))?;
+        writer.write_tag(&audio(980, AudioData::AacFrame(Bytes::from_static(&[0xAA]))))?;
+
+        for i in 0..50 {
+            let ts = 1000 + i * 40;
+            writer.write_tag(&video(ts, i % 25 == 0, VideoData::NalUnits(vec![Bytes::from_static(&[0x65, 1, 2])])))?;
+            writer.write_tag(&audio(ts, AudioData::AacFrame(Bytes::from_static(&[0x21, 0x10]))))?;
+        }
+        assert_eq!(1960, writer.duration());
+        let data = writer.finish()?;
+
+        let top = boxes(&data).into_iter().map(|(kind, _)| kind).collect::<Vec<_>>();
+        assert_eq!(vec!["ftyp", "moov", "moof", "mdat", "moof", "mdat"], top);
+
+        let avcc = find(&data, &["moov", "trak", "mdia", "minf", "stbl", "stsd", "avc1", "avcC"]).unwrap();
+        assert_eq!(avc_config(&h264_sps()).as_ref(), avcc);
+        let tkhd = find(&data, &["moov", "trak", "tkhd"]).unwrap();
+        assert_eq!(&[0x07, 0x80, 0, 0, 0x04, 0x38, 0, 0], &tkhd[76..84]);
+
+        // Second fragment: 25 video frames starting at 1 second, at 90 kHz
+        let moofs = boxes(&data).into_iter().filter(|(kind, _)| kind == "moof").map(|(_, c)| c).collect::<Vec<_>>();
+        let tfdt = find(moofs[1], &["traf", "tfdt"]).unwrap();
+        assert_eq!(90_000u64.to_be_bytes(), tfdt[4..12]);
+        let trun = find(moofs[1], &["traf", "trun"]).unwrap();
+        assert_eq!(25u32.to_be_bytes(), trun[4..8]);
+        assert_eq!(3600u32.to_be_bytes(), trun[12..16]); // Duration of the first sample
+
+        // Audio track, 16 kHz
+        let mdhd = boxes(find(&data, &["moov"]).unwrap()).into_iter().filter(|(kind, _)| kind == "trak").nth(1)
+            .and_then(|(_, trak)| find(trak, &["mdia", "mdhd"])).unwrap();
+        assert_eq!(16000u32.to_be_bytes(), mdhd[12..16]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_timestamp_reset() -> std::io::Result<()> {
+        let mut writer = Mp4Writer::new(Vec::new());
+        writer.write_tag(&video(0, true, VideoData::Config(avc_config(&h264_sps()))))?;
+        writer.write_tag(&audio(0, AudioData::AacConfig(Bytes::from_static(&[0x14, 0x08]))))?;
+
+        // Timestamps restart from zero after one second
+        for ts in (1000..2000).step_by(40).chain((0..1000).step_by(40)) {
+            writer.write_tag(&video(ts, ts % 1000 == 0, VideoData::NalUnits(vec![Bytes::from_static(&[0x65, 1])])))?;
+            writer.write_tag(&audio(ts, AudioData::AacFrame(Bytes::from_static(&[0x21, 0x10]))))?;
+        }
+        assert_eq!(1960, writer.duration());
+        let data = writer.finish()?;
+
+        // Both tracks continue at one second, at 90 kHz and 16 kHz
+        let moofs = boxes(&data).into_iter().filter(|(kind, _)| kind == "moof").map(|(_, c)| c).collect::<Vec<_>>();
+        let tfdts = boxes(moofs[1]).into_iter().filter(|(kind, _)| kind == "traf")
+            .map(|(_, traf)| u64::from_be_bytes(find(traf, &["tfdt"]).unwrap()[4..12].try_into().unwrap()))
+            .collect::<Vec<_>>();
+        assert_eq!(vec![90_000, 16_000], tfdts);
+        Ok(())
+    }
+
+    #[test]
+    fn test_duration_reset() -> std::io::Result<()> {
+        let mut writer = Mp4Writer::new(Vec::new());
+        writer.write_tag(&video(0, true, VideoData::Config(avc_config(&h264_sps()))))?;
+
+        // Timestamps restart from zero within a fragment
+        for (i, ts) in (0..500).step_by(40).chain((0..240).step_by(40)).enumerate() {
+            writer.write_tag(&video(ts, i == 0, VideoData::NalUnits(vec![Bytes::from_static(&[0x65, 1])])))?;
+        }
+        assert_eq!(720, writer.duration());
+        assert_eq!(760, writer.duration_at(240));
+        Ok(())
+    }
+
+    #[test]
+    fn test_data_offsets() -> std::io::Result<()> {
+        let mut writer = Mp4Writer::new(Vec::new());
+        writer.write_tag(&video(0, true, VideoData::Config(avc_config(&h264_sps()))))?;
+        writer.write_tag(&audio(0, AudioData::AacConfig(Bytes::from_static(&[0x14, 0x08]))))?;
+        writer.write_tag(&video(0, true, VideoData::NalUnits(vec![Bytes::from_static(&[0x65, 0x11])])))?;
+        writer.write_tag(&audio(0, AudioData::AacFrame(Bytes::from_static(&[0x22, 0x33]))))?;
+        let data = writer.finish()?;
+
+        // Offsets are relative to the start of moof, and point to each track's data in mdat
+        let moof_start = boxes(&data)[..2].iter().map(|(_, c)| c.len() + 8).sum::<usize>();
+        let moof = boxes(&data)[2].1;
+        let trafs = boxes(moof).into_iter().filter(|(kind, _)| kind == "traf").collect::<Vec<_>>();
+        let expected = [[0, 0, 0, 2, 0x65, 0x11].as_slice(), [0x22, 0x33].as_slice()];
+        for ((_, traf), expected) in trafs.iter().zip(expected) {
+            let trun = find(traf, &["trun"]).unwrap();
+            let offset = u32::from_be_bytes(trun[8..12].try_into().unwrap()) as usize;
+            assert_eq!(expected, &data[moof_start + offset..moof_start + offset + expected.len()]);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,59 @@
+use reolink_api::blocking::ReolinkClient;
+use reolink_api::blocking::replay::ReplayTransport;
+
+fn replay_client(fixture: &str) -> anyhow::Result<ReolinkClient> {
+    let path = format!("{}/tests/fixtures/replay/{}", env!("CARGO_MANIFEST_DIR"), fixture);
+    ReolinkClient::new_with_transport(
+        reolink_api::reqwest::blocking::Client::new(),
+        ReplayTransport::load(path)?,
+        "https://192.168.0.42/",
+        "admin".to_string(),
+        "s3cr3t-p4ssw0rd".to_string(),
+    )
+}
+
+#[test]
+fn test_replay_search() -> anyhow::Result<()> {
+    use reolink_api::api::record::download::*;
+    use reolink_api::api::record::search::*;
+    use reolink_api::api::record::StreamType;
+    use reolink_api::chrono::{NaiveDate, TimeDelta};
+
+    let api = replay_client("search.json")?;
+
+    let date_time = NaiveDate::from_ymd_opt(2024, 12, 25).unwrap()
+        .and_hms_opt(0, 0, 0).unwrap();
+
+    let resp = api.exec(&SearchRequest {
+        search: Search {
+            channel: 0,
+            only_status: false,
+            stream_type: StreamType::Main,
+            start_time: date_time.try_into()?,
+            end_time: (date_time + TimeDelta::days(1) - TimeDelta::seconds(1)).try_into()?,
+        }
+    })?;
+
+    let files = resp.search_result.file.unwrap();
+    assert_eq!(2, files.len());
+    assert_eq!(1851162, files[0].size);
+
+    // Needs a token: replays Login, then Download
+    let bytes = api.download(&DownloadRequest {
+        source: files[0].name.clone(),
+        output: Some("RecM02_20241225_081352_081441_0_6D28808000_1C3F1A.mp4".to_string()),
+    })?;
+    assert_eq!(b"ftypmp42", &bytes[4..]);
+
+    Ok(())
+}
+
+#[test]
+fn test_replay_unknown_request() -> anyhow::Result<()> {
+    use reolink_api::api::system::get_dev_info::*;
+
+    let api = replay_client("search.json")?;
+    let err = api.exec(&GetDevInfoRequest).unwrap_err();
+    assert!(err.to_string().contains("No recorded exchange for 'GetDevinfo'"));
+    Ok(())
+}
@@ -60,7 +60,7 @@ fn test_add_user() -> anyhow::Result<()> {
         user: AddUser {
             username: "newuser".to_string(),
             password: "zeechohya5ie8daeLaiy".to_string(),
-            level: "admin".to_string(),
+            level: crate::api::security::UserLevel::Admin,
         }
     })?;
 
@@ -93,8 +93,8 @@ fn test_search() -> anyhow::Result<()> {
         search: Search {
             channel: 0,
             only_status: false,
-            stream_type: "main".to_string(),
-            start_time: date_time.into(),
+            stream_type: crate::api::record::StreamType::Main,
+            start_time: date_time.try_into()?,
             // start_time: Time {
             //     year: 2024,
             //     mon: 12,
@@ -103,7 +103,7 @@ fn test_search() -> anyhow::Result<()> {
             //     min: 00,
             //     sec: 00,
             // },
-            end_time: (date_time + TimeDelta::days(1) - TimeDelta::seconds(1)).into(),
+            end_time: (date_time + TimeDelta::days(1) - TimeDelta::seconds(1)).try_into()?,
             // end_time: Time {
             //     year: 2024,
             //     mon: 12,
@@ -130,9 +130,9 @@ fn test_nvr_download() -> anyhow::Result<()> {
     let resp = api.exec(&NvrDownloadRequest {
         nvr_download: NvrDownload {
             channel: 1,
-            stream_type: "main".to_string(),
-            start_time: date_time.into(),
-            end_time: (date_time + TimeDelta::days(1) - TimeDelta::seconds(1)).into(),
+            stream_type: crate::api::record::StreamType::Main,
+            start_time: date_time.try_into()?,
+            end_time: (date_time + TimeDelta::days(1) - TimeDelta::seconds(1)).try_into()?,
         }
     })?;
 
@@ -192,10 +192,8 @@ fn test_snapshot() -> anyhow::Result<()> {
     use crate::api::record::snapshot::*;
     let api = get_client()?;
 
-    let _resp = api.download(&SnapshotRequest {
-        channel: 0,
-        rs: "0123456789012345".to_string(),
-    })?;
+    let snapshot = api.snapshot(&SnapshotRequest::new(0))?;
+    assert!(snapshot.width > 0 && snapshot.height > 0);
 
     Ok(())
 }
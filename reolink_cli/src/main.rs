@@ -3,8 +3,8 @@ use std::str::FromStr;
 use bytesize::ByteSize;
 use clap::{Parser, Subcommand};
 use reolink_api::api::record::download::DownloadRequest;
-use reolink_api::api::record::search::{Search, SearchRequest};
 use reolink_api::api::record::snapshot::SnapshotRequest;
+use reolink_api::api::record::StreamType;
 use reolink_api::{chrono, ReolinkBlockingClient};
 use reolink_api::api::Channel;
 use reolink_api::chrono::{NaiveDateTime, NaiveTime, TimeDelta};
@@ -17,34 +17,23 @@ fn main() -> anyhow::Result<()> {
         Commands::Recordings { start, end, channel, quiet } => {
 
             let client = get_client()?;
+            let end = end.unwrap_or_else(|| end_of_day(start));
             let mut files_found = false;
 
-            for (start, end) in day_intervals(start, end) {
-                let result = client.exec(&SearchRequest {
-                    search: Search {
-                        channel: channel,
-                        only_status: false,
-                        stream_type: "main".to_string(),
-                        start_time: start.into(),
-                        end_time: end.into(),
-                    },
-                })?;
-
-                let files = result.search_result.file.unwrap_or(Vec::new());
-                files_found |= !files.is_empty();
-                for file in files {
-                    if quiet {
-                        println!("{}", file.name);
-                    } else {
-                        let start = NaiveDateTime::from(file.start_time);
-                        let duration = (NaiveDateTime::from(file.end_time) - start).num_seconds();
-                        println!("{} - {} s - {}: {}", start, duration, ByteSize(file.size as u64), file.name);
-                    }
-                }
-                if !files_found {
-                    eprintln!("No files found");
+            for file in client.search_files(channel, StreamType::Main, start, end) {
+                let file = file?;
+                files_found = true;
+                if quiet {
+                    println!("{}", file.name);
+                } else {
+                    let start = NaiveDateTime::try_from(&file.start_time)?;
+                    let duration = (NaiveDateTime::try_from(&file.end_time)? - start).num_seconds();
+                    println!("{} - {} s - {}: {}", start, duration, ByteSize(file.size as u64), file.name);
                 }
             }
+            if !files_found {
+                eprintln!("No files found");
+            }
         }
 
         Commands::Download { file } => {
@@ -57,15 +46,16 @@ fn main() -> anyhow::Result<()> {
             std::io::stdout().write_all(&bytes)?;
         }
 
-        Commands::Snapshot { channel } => {
+        Commands::Snapshot { channel, width, height } => {
             let client = get_client()?;
 
-            let bytes = client.download(&SnapshotRequest {
-                channel: channel,
-                rs: "xx".to_string(),
-            })?;
+            let mut request = SnapshotRequest::new(channel);
+            if let (Some(width), Some(height)) = (width, height) {
+                request = request.with_size(width, height);
+            }
+            let snapshot = client.snapshot(&request)?;
 
-            std::io::stdout().write_all(&bytes)?;
+            std::io::stdout().write_all(&snapshot.jpeg)?;
         }
     }
 
@@ -115,7 +105,13 @@ enum Commands {
     /// Take a snapshot
     Snapshot {
         #[arg(short, long, default_value = "0")]
-        channel: u8
+        channel: u8,
+        /// Width of the image, scaled by the device
+        #[arg(long, requires = "height")]
+        width: Option<u32>,
+        /// Height of the image, scaled by the device
+        #[arg(long, requires = "width")]
+        height: Option<u32>,
     }
 }
 
@@ -135,21 +131,6 @@ fn start_of_next_day(datetime: NaiveDateTime) -> NaiveDateTime {
     start_of_day(datetime) + ONE_DAY
 }
 
-fn day_intervals(mut start: NaiveDateTime, end: Option<NaiveDateTime>) -> Vec<(NaiveDateTime, NaiveDateTime)> {
-    let end = end.unwrap_or_else(|| end_of_day(start));
-
-    let mut current_end;
-    let mut result = Vec::new();
-
-    while { current_end = end_of_day(start); current_end } < end {
-        result.push((start, current_end));
-        start = start_of_next_day(start);
-    }
-
-    result.push((start, end));
-    result
-}
-
 fn parse_start_date(date: &str) -> chrono::format::ParseResult<NaiveDateTime> {
     if let Ok(days) = str::parse::<i64>(date) {
         let today = chrono::offset::Local::now().naive_local().date();
@@ -173,7 +154,7 @@ fn parse_end_date(date: &str) -> chrono::format::ParseResult<NaiveDateTime> {
 mod tests {
     use super::*;
     use std::str::FromStr;
-    use reolink_api::chrono::Datelike;
+    use reolink_api::chrono::{Datelike, Timelike};
 
     #[test]
     fn test_end_of_day() -> anyhow::Result<()> {
@@ -0,0 +1,141 @@
+//! A scripted stand-in for the native "Baichuan" TCP service of a device, to test the alarm
+//! listener.
+
+use std::io::Read;
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::thread::JoinHandle;
+use std::time::Duration;
+use reolink_api::baichuan::AlarmEvent;
+use reolink_api::baichuan::codec::*;
+
+/// The script played for the first connection.
+#[derive(Debug, Clone)]
+pub struct BaichuanScript {
+    pub login: String,
+    pub password: String,
+    /// Encryption chosen in the reply to the legacy login. The script ends after this reply
+    /// unless it's `ENCRYPTION_BC`.
+    pub encryption: u16,
+    /// Alarms pushed after subscription, one message each
+    pub alarms: Vec<AlarmEvent>,
+    /// Wait for a ping from the client before closing the connection
+    pub wait_for_ping: bool,
+}
+
+impl BaichuanScript {
+    /// A script with the default mock credentials.
+    pub fn new(alarms: Vec<AlarmEvent>) -> Self {
+        BaichuanScript {
+            login: "admin".to_string(),
+            password: "s3cr3t-p4ssw0rd".to_string(),
+            encryption: ENCRYPTION_BC,
+            alarms,
+            wait_for_ping: false,
+        }
+    }
+}
+
+/// A Baichuan service listening on a local port, serving a single connection.
+pub struct BaichuanDevice {
+    addr: SocketAddr,
+    handle: JoinHandle<anyhow::Result<Vec<u32>>>,
+}
+
+const NONCE: &str = "0123456789ABCDEF";
+
+impl BaichuanDevice {
+    pub fn start(script: BaichuanScript) -> anyhow::Result<BaichuanDevice> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let addr = listener.local_addr()?;
+        let handle = std::thread::spawn(move || {
+            let (stream, _) = listener.accept()?;
+            stream.set_read_timeout(Some(Duration::from_secs(10)))?;
+            play(stream, &script)
+        });
+        Ok(BaichuanDevice { addr, handle })
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Waits for the end of the script, and returns the ids of the messages received.
+    pub fn join(self) -> anyhow::Result<Vec<u32>> {
+        self.handle.join().map_err(|_| anyhow::anyhow!("Baichuan stand-in panicked"))?
+    }
+}
+
+fn play(mut stream: TcpStream, script: &BaichuanScript) -> anyhow::Result<Vec<u32>> {
+    let mut buffer = Vec::new();
+    let mut received = Vec::new();
+
+    // Legacy login, replied with the nonce
+    let login = read(&mut stream, &mut buffer, &mut received)?;
+    anyhow::ensure!(login.header.msg_id == MSG_LOGIN && login.header.class == CLASS_LEGACY, "expected legacy login");
+    anyhow::ensure!(login.body.starts_with(md5_hex(&script.login).as_bytes()), "wrong user name hash");
+    let xml = format!("<?xml version=\"1.0\" encoding=\"UTF-8\" ?>\n<body>\n<Encryption version=\"1.1\">\n<type>md5</type>\n<nonce>{NONCE}</nonce>\n</Encryption>\n</body>\n");
+    reply(&mut stream, &login, CLASS_MODERN, script.encryption, &xml)?;
+    if script.encryption != ENCRYPTION_BC {
+        return Ok(received);
+    }
+
+    // Modern login
+    let login = read(&mut stream, &mut buffer, &mut received)?;
+    let xml = login.xml_body();
+    let valid = xml_value(&xml, "userName") == Some(&md5_hex(&format!("{}{NONCE}", script.login)))
+        && xml_value(&xml, "password") == Some(&md5_hex(&format!("{}{NONCE}", script.password)));
+    if !valid {
+        reply(&mut stream, &login, CLASS_MODERN_OFFSET, 400, "")?;
+        return Ok(received);
+    }
+    reply(&mut stream, &login, CLASS_MODERN_OFFSET, RESPONSE_OK, "<?xml version=\"1.0\" encoding=\"UTF-8\" ?>\n<body>\n<DeviceInfo version=\"1.1\">\n</DeviceInfo>\n</body>\n")?;
+
+    let subscribe = read(&mut stream, &mut buffer, &mut received)?;
+    anyhow::ensure!(subscribe.header.msg_id == MSG_ALARM_SUBSCRIBE, "expected alarm subscription");
+    reply(&mut stream, &subscribe, CLASS_MODERN_OFFSET, RESPONSE_OK, "")?;
+
+    for alarm in &script.alarms {
+        let header = Header::new(MSG_ALARM_EVENT, 0, CLASS_MODERN_OFFSET, 0);
+        Message::xml(header, &alarm_xml(alarm)).write_to(&mut stream)?;
+    }
+
+    if script.wait_for_ping {
+        while read(&mut stream, &mut buffer, &mut received)?.header.msg_id != MSG_PING {}
+    }
+    Ok(received)
+}
+
+fn read(stream: &mut TcpStream, buffer: &mut Vec<u8>, received: &mut Vec<u32>) -> anyhow::Result<Message> {
+    let mut chunk = [0; 4096];
+    loop {
+        if let Some((message, len)) = Message::parse(buffer)? {
+            buffer.drain(..len);
+            received.push(message.header.msg_id);
+            return Ok(message);
+        }
+        let len = stream.read(&mut chunk)?;
+        anyhow::ensure!(len > 0, "connection closed by the client");
+        buffer.extend(&chunk[..len]);
+    }
+}
+
+fn reply(stream: &mut TcpStream, request: &Message, class: u16, code: u16, xml: &str) -> anyhow::Result<()> {
+    let header = Header::new(request.header.msg_id, request.header.msg_num, class, code);
+    Message::xml(header, xml).write_to(stream)?;
+    Ok(())
+}
+
+fn alarm_xml(alarm: &AlarmEvent) -> String {
+    let state = &alarm.state;
+    let ai_types = [(state.person, "people"), (state.vehicle, "vehicle"), (state.animal, "dog_cat")]
+        .iter().filter(|(active, _)| *active).map(|(_, name)| *name)
+        .collect::<Vec<_>>();
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\" ?>\n<body>\n<AlarmEventList version=\"1.1\">\n\
+        <AlarmEvent version=\"1.1\">\n<channelId>{}</channelId>\n<status>{}</status>\n<AItype>{}</AItype>\n\
+        <recording>0</recording>\n<timeStamp>0</timeStamp>\n</AlarmEvent>\n</AlarmEventList>\n</body>\n",
+        alarm.channel,
+        if state.motion { "MD" } else { "none" },
+        if ai_types.is_empty() { "none".to_string() } else { ai_types.join(",") },
+    )
+}
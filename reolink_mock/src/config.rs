@@ -0,0 +1,151 @@
+use std::time::Duration;
+use reolink_api::api::record::StreamType;
+use reolink_api::api::system::get_time::Dst;
+use reolink_api::chrono::NaiveDateTime;
+
+/// The kind of device emulated by the mock. It changes the device information and channel
+/// statuses returned, and some protocol quirks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeviceKind {
+    Camera,
+    Nvr,
+    /// A Home Hub: battery cameras may be sleeping, and query strings must not contain
+    /// url-encoded `/` or `+` as space.
+    HomeHub,
+}
+
+/// Configuration of a mock device.
+#[derive(Debug, Clone)]
+pub struct MockConfig {
+    pub kind: DeviceKind,
+    pub model: String,
+    /// Number of channels (1 for cameras)
+    pub channels: u8,
+    /// Login of the admin user
+    pub login: String,
+    /// Password of the admin user
+    pub password: String,
+    /// Lease time of the tokens returned by `Login`
+    pub lease_time: Duration,
+    /// Maximum number of live tokens
+    pub max_sessions: usize,
+    /// Reject query strings containing `%2F` or `+` (Home Hub behavior)
+    pub strict_url_encoding: bool,
+    /// Channels whose camera is sleeping (battery cameras)
+    pub sleeping_channels: Vec<u8>,
+    /// Recordings returned by `Search` and `NvrDownload`, and served by `Download`
+    pub files: Vec<MockFile>,
+    /// Image served by `Snap`
+    pub snapshot: Vec<u8>,
+    /// Time zone returned by `GetTime`, in seconds with the API's inverted sign (UTC+1 is `-3600`)
+    pub time_zone: i32,
+    /// DST rules returned by `GetTime`
+    pub dst: Dst,
+    /// Port of the native protocol returned by `GetNetPort`, e.g. of a `BaichuanDevice`
+    pub media_port: u16,
+    /// Port of the RTMP service returned by `GetNetPort`, expected in live stream urls
+    pub rtmp_port: u16,
+    /// Commands answered with a "not support" error, like models or firmwares that lack them
+    pub unsupported_commands: Vec<String>,
+}
+
+/// A recording file stored on a mock device.
+#[derive(Debug, Clone)]
+pub struct MockFile {
+    pub channel: u8,
+    pub stream_type: StreamType,
+    pub start: NaiveDateTime,
+    pub end: NaiveDateTime,
+    pub name: String,
+    pub data: Vec<u8>,
+}
+
+/// Faults injected in the mock's responses.
+#[derive(Debug, Clone, Default)]
+pub struct Faults {
+    /// Delay before each response
+    pub latency: Duration,
+    /// Respond to JSON commands with a truncated body
+    pub malformed_json: bool,
+    /// Overrides the device's maximum number of live tokens
+    pub max_sessions: Option<usize>,
+    /// Number of subsequent `Download` responses whose body is truncated
+    pub truncated_downloads: usize,
+    /// Frame of the live stream at which timestamps restart from zero
+    pub live_timestamp_reset: Option<u32>,
+}
+
+impl MockConfig {
+    /// A single-channel camera
+    pub fn camera() -> Self {
+        MockConfig {
+            kind: DeviceKind::Camera,
+            model: "RLC-810A".to_string(),
+            channels: 1,
+            login: "admin".to_string(),
+            password: "s3cr3t-p4ssw0rd".to_string(),
+            lease_time: Duration::from_secs(3600),
+            max_sessions: 20,
+            strict_url_encoding: false,
+            sleeping_channels: Vec::new(),
+            files: Vec::new(),
+            snapshot: jpeg(640, 360),
+            time_zone: 0,
+            media_port: 9000,
+            rtmp_port: 1935,
+            unsupported_commands: Vec::new(),
+            dst: Dst {
+                enable: false, offset: 1,
+                start_mon: 3, start_week: 5, start_weekday: 0, start_hour: 2, start_min: 0, start_sec: 0,
+                end_mon: 10, end_week: 5, end_weekday: 0, end_hour: 3, end_min: 0, end_sec: 0,
+            },
+        }
+    }
+
+    /// An NVR with `channels` channels
+    pub fn nvr(channels: u8) -> Self {
+        MockConfig {
+            kind: DeviceKind::Nvr,
+            model: "RLN8-410".to_string(),
+            channels,
+            ..Self::camera()
+        }
+    }
+
+    /// A Home Hub with `channels` channels
+    pub fn home_hub(channels: u8) -> Self {
+        MockConfig {
+            kind: DeviceKind::HomeHub,
+            model: "Reolink Home Hub".to_string(),
+            channels,
+            strict_url_encoding: true,
+            ..Self::camera()
+        }
+    }
+
+    /// Sets the time zone to Central European Time, with DST.
+    pub fn with_cet(mut self) -> Self {
+        self.time_zone = -3600;
+        self.dst.enable = true;
+        self
+    }
+
+    pub fn with_files(mut self, files: Vec<MockFile>) -> Self {
+        self.files = files;
+        self
+    }
+}
+
+/// A minimal baseline JPEG image of the given dimensions, with no actual image data.
+pub fn jpeg(width: u16, height: u16) -> Vec<u8> {
+    let mut data = vec![
+        0xFF, 0xD8, // SOI
+        0xFF, 0xE0, 0x00, 0x10, b'J', b'F', b'I', b'F', 0x00, 0x01, 0x01, 0x00, 0x00, 0x01, 0x00, 0x01, 0x00, 0x00, // APP0
+        0xFF, 0xC0, 0x00, 0x11, 0x08, // SOF0, length, precision
+    ];
+    data.extend(height.to_be_bytes());
+    data.extend(width.to_be_bytes());
+    data.extend([0x03, 0x01, 0x22, 0x00, 0x02, 0x11, 0x01, 0x03, 0x11, 0x01]);
+    data.extend([0xFF, 0xD9]); // EOI
+    data
+}
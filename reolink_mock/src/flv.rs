@@ -0,0 +1,56 @@
+//! Generation of the HTTP-FLV live stream.
+
+const TAG_AUDIO: u8 = 8;
+const TAG_VIDEO: u8 = 9;
+const TAG_SCRIPT: u8 = 18;
+
+/// Frames per second of the generated video.
+pub(crate) const FRAME_RATE: u32 = 25;
+
+/// An H.264 and AAC stream of `frames` video frames, with a keyframe every second and an audio
+/// frame after each video frame. NAL units have no actual image data. Timestamps restart from
+/// zero at frame `reset`, if any.
+pub(crate) fn live_stream(frames: u32, reset: Option<u32>) -> Vec<u8> {
+    let mut flv = b"FLV\x01\x05\x00\x00\x00\x09\x00\x00\x00\x00".to_vec();
+    tag(&mut flv, TAG_SCRIPT, 0, b"\x02\x00\x0AonMetaData\x08\x00\x00\x00\x00\x00\x00\x09");
+
+    // AVCDecoderConfigurationRecord with one SPS and one PPS, 4 bytes NAL unit lengths
+    // Main profile SPS, 640x360 (40x23 macroblocks, cropped)
+    let sps = [0x67, 0x4D, 0x00, 0x1E, 0xF4, 0x05, 0x01, 0x7F, 0xCA, 0x80];
+    let pps = [0x68, 0xEE, 0x3C, 0x80];
+    let mut config = vec![0x17, 0, 0, 0, 0, 1, sps[1], sps[2], sps[3], 0xFF, 0xE1];
+    config.extend((sps.len() as u16).to_be_bytes());
+    config.extend(sps);
+    config.push(1);
+    config.extend((pps.len() as u16).to_be_bytes());
+    config.extend(pps);
+    tag(&mut flv, TAG_VIDEO, 0, &config);
+
+    // AudioSpecificConfig: AAC LC, 16 kHz, mono
+    tag(&mut flv, TAG_AUDIO, 0, &[0xAE, 0, 0x14, 0x08]);
+
+    for i in 0..frames {
+        let timestamp = match reset {
+            Some(reset) if i >= reset => (i - reset) * 1000 / FRAME_RATE,
+            _ => i * 1000 / FRAME_RATE,
+        };
+        let keyframe = i % FRAME_RATE == 0;
+        let nal = if keyframe { [0x65, 0x88, 0x84] } else { [0x41, 0x9A, 0x02] };
+        let mut video = vec![if keyframe { 0x17 } else { 0x27 }, 1, 0, 0, 0];
+        video.extend((nal.len() as u32).to_be_bytes());
+        video.extend(nal);
+        tag(&mut flv, TAG_VIDEO, timestamp, &video);
+        tag(&mut flv, TAG_AUDIO, timestamp, &[0xAE, 1, 0x21, 0x10, 0x04]);
+    }
+    flv
+}
+
+fn tag(flv: &mut Vec<u8>, tag_type: u8, timestamp: u32, data: &[u8]) {
+    flv.push(tag_type);
+    flv.extend(&(data.len() as u32).to_be_bytes()[1..]);
+    flv.extend(&timestamp.to_be_bytes()[1..]);
+    flv.push((timestamp >> 24) as u8);
+    flv.extend([0, 0, 0]);
+    flv.extend(data);
+    flv.extend((data.len() as u32 + 11).to_be_bytes());
+}
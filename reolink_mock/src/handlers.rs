@@ -0,0 +1,647 @@
+use std::collections::HashMap;
+use serde_json::{json, Value};
+use reolink_api::api::{AuthenticationType, BinaryEndpoint, JsonEndpoint};
+use reolink_api::api::{ai, alarm, network, record, security, system};
+use reolink_api::api::system::get_time::{DeviceTimeZone, Time};
+use reolink_api::chrono::{Datelike, Months, NaiveDate, NaiveDateTime, NaiveTime, Timelike, Utc};
+use crate::{DeviceKind, State};
+
+/// An http request to the `api.cgi` endpoint.
+pub(crate) struct MockRequest {
+    pub query: HashMap<String, String>,
+    pub raw_query: String,
+    pub body: Vec<u8>,
+}
+
+pub(crate) enum MockResponse {
+    /// A JSON response body
+    Json(String),
+    Binary {
+        content_type: &'static str,
+        data: Vec<u8>,
+        filename: Option<String>,
+    },
+}
+
+/// An error envelope: `rspCode` and `detail`.
+type ApiError = (isize, &'static str);
+
+const NOT_EXIST: ApiError = (-1, "not exist");
+const PARAM_ERROR: ApiError = (-4, "param error");
+const MAX_SESSION: ApiError = (-5, "max session");
+const PLEASE_LOGIN: ApiError = (-6, "please login first");
+const LOGIN_FAILED: ApiError = (-7, "login failed");
+const NOT_SUPPORTED: ApiError = (-9, "not support");
+
+/// Authentication type expected by a command, taken from the client library's endpoints.
+fn auth_type(cmd: &str) -> Option<AuthenticationType> {
+    Some(match cmd {
+        "Login" => <security::login::LoginRequest<'static> as JsonEndpoint>::AUTH,
+        "Logout" => security::logout::LogoutRequest::AUTH,
+        "GetUser" => security::get_user::GetUserRequest::AUTH,
+        "AddUser" => security::add_user::AddUserRequest::AUTH,
+        "GetAbility" => system::get_ability::GetAbilityRequest::AUTH,
+        "GetDevinfo" => system::get_dev_info::GetDevInfoRequest::AUTH,
+        "GetChannelstatus" => system::get_channel_status::GetChannelStatusRequest::AUTH,
+        "GetTime" => system::get_time::GetTimeRequest::AUTH,
+        "GetNetPort" => network::get_net_port::GetNetPortRequest::AUTH,
+        "GetMdState" => alarm::get_md_state::GetMdStateRequest::AUTH,
+        "GetAiState" => ai::get_ai_state::GetAiStateRequest::AUTH,
+        "GetRtspUrl" => network::get_rtsp_url::GetRtspUrlRequest::AUTH,
+        "GetRec" => record::get_recording::GetRecordingRequest::AUTH,
+        "SetRec" => record::set_recording::SetRecordingRequest::AUTH,
+        "GetRecV20" => record::get_recording_v20::GetRecordingRequest::AUTH,
+        "SetRecV20" => record::set_recording_v20::SetRecordingRequest::AUTH,
+        "Search" => record::search::SearchRequest::AUTH,
+        "NvrDownload" => record::nvr_download::NvrDownloadRequest::AUTH,
+        "Download" => <record::download::DownloadRequest as BinaryEndpoint>::AUTH,
+        "Playback" => <record::playback::PlaybackRequest as BinaryEndpoint>::AUTH,
+        "Snap" => <record::snapshot::SnapshotRequest as BinaryEndpoint>::AUTH,
+        _ => return None,
+    })
+}
+
+pub(crate) fn handle(state: &mut State, req: &MockRequest) -> MockResponse {
+    let cmd = req.query.get("cmd").map(String::as_str).unwrap_or("");
+    state.commands.push(cmd.to_string());
+
+    if state.config.strict_url_encoding && (req.raw_query.contains("%2F") || req.raw_query.contains("%2f") || req.raw_query.contains('+')) {
+        return json_error(cmd, PARAM_ERROR);
+    }
+
+    let Some(auth) = auth_type(cmd).filter(|_| !state.config.unsupported_commands.iter().any(|c| c == cmd)) else {
+        return json_error(cmd, NOT_SUPPORTED);
+    };
+    if let Err(err) = check_auth(state, &auth, &req.query) {
+        return json_error(cmd, err);
+    }
+
+    match cmd {
+        "Download" => download(state, &req.query).unwrap_or_else(|err| json_error(cmd, err)),
+        "Playback" => playback(state, &req.query).unwrap_or_else(|err| json_error(cmd, err)),
+        "Snap" => snap(state, &req.query).unwrap_or_else(|err| json_error(cmd, err)),
+        _ => exec_json(state, req),
+    }
+}
+
+fn check_auth(state: &mut State, auth: &AuthenticationType, query: &HashMap<String, String>) -> Result<(), ApiError> {
+    let token = query.get("token");
+    let credentials = query.get("user").zip(query.get("password"));
+    let check_credentials = |(user, password): (&String, &String)| {
+        if *user == state.config.login && *password == state.config.password { Ok(()) } else { Err(LOGIN_FAILED) }
+    };
+
+    match auth {
+        AuthenticationType::None => Ok(()),
+        AuthenticationType::LoginPassword => credentials.map(check_credentials).unwrap_or(Err(PLEASE_LOGIN)),
+        AuthenticationType::Token => match token {
+            Some(token) if state.sessions.is_valid(token) => Ok(()),
+            _ => Err(PLEASE_LOGIN),
+        },
+        AuthenticationType::Any => match (token, credentials) {
+            (Some(token), _) if state.sessions.is_valid(token) => Ok(()),
+            (_, Some(credentials)) => check_credentials(credentials),
+            _ => Err(PLEASE_LOGIN),
+        },
+    }
+}
+
+//-------------------------------------------------------------------------------------------------
+// JSON commands
+
+fn exec_json(state: &mut State, req: &MockRequest) -> MockResponse {
+    let cmd = req.query.get("cmd").map(String::as_str).unwrap_or("");
+    let Ok(Value::Array(items)) = serde_json::from_slice::<Value>(&req.body) else {
+        return json_error(cmd, PARAM_ERROR);
+    };
+
+    let results = items.iter().map(|item| {
+        let item_cmd = item["cmd"].as_str().unwrap_or("");
+        if item_cmd != cmd {
+            return error_value(item_cmd, PARAM_ERROR);
+        }
+        let details = item["action"].as_u64() == Some(1);
+        match exec_command(state, cmd, &item["param"], &req.query) {
+            Ok(Response { value, initial, range }) => {
+                let mut result = json!({"cmd": cmd, "code": 0, "value": value});
+                if details {
+                    result["initial"] = initial.unwrap_or(Value::Null);
+                    result["range"] = range.unwrap_or(Value::Null);
+                }
+                result
+            },
+            Err(err) => error_value(cmd, err),
+        }
+    }).collect::<Vec<_>>();
+
+    let mut body = Value::Array(results).to_string();
+    if state.faults.malformed_json {
+        body.truncate(body.len() / 2);
+    }
+    MockResponse::Json(body)
+}
+
+struct Response {
+    value: Value,
+    initial: Option<Value>,
+    range: Option<Value>,
+}
+
+impl From<Value> for Response {
+    fn from(value: Value) -> Self {
+        Response { value, initial: None, range: None }
+    }
+}
+
+fn exec_command(state: &mut State, cmd: &str, param: &Value, query: &HashMap<String, String>) -> Result<Response, ApiError> {
+    match cmd {
+        "Login" => login(state, param, query),
+        "Logout" => {
+            state.sessions.remove(query.get("token").map(String::as_str).unwrap_or(""));
+            Ok(json!({"rspCode": 200}).into())
+        },
+        "GetUser" => Ok(get_user(state)),
+        "AddUser" => add_user(state, param),
+        "GetAbility" => Ok(get_ability(state)),
+        "GetDevinfo" => Ok(get_dev_info(state)),
+        "GetChannelstatus" => Ok(get_channel_status(state)),
+        "GetTime" => Ok(get_time(state)),
+        "GetNetPort" => Ok(get_net_port(state)),
+        "GetMdState" => get_md_state(state, param),
+        "GetAiState" => get_ai_state(state, param),
+        "GetRtspUrl" => get_rtsp_url(state, param),
+        "GetRec" => get_rec(state, param),
+        "SetRec" => set_rec(state, param),
+        "GetRecV20" => get_rec_v20(state, param),
+        "SetRecV20" => set_rec_v20(state, param),
+        "Search" => search(state, param),
+        "NvrDownload" => nvr_download(state, param),
+        _ => Err(NOT_SUPPORTED),
+    }
+}
+
+fn login(state: &mut State, param: &Value, query: &HashMap<String, String>) -> Result<Response, ApiError> {
+    let user = &param["User"];
+    if user["userName"].as_str() != Some(&state.config.login) || user["password"].as_str() != Some(&state.config.password) {
+        return Err(LOGIN_FAILED);
+    }
+
+    // The Home Hub accepts credentials in the url, but returns an invalid token
+    let valid = state.config.kind != DeviceKind::HomeHub || !(query.contains_key("user") || query.contains_key("password"));
+    let max_sessions = state.faults.max_sessions.unwrap_or(state.config.max_sessions);
+    let token = state.sessions.create(state.config.lease_time, max_sessions, valid).ok_or(MAX_SESSION)?;
+
+    Ok(json!({"Token": {"name": token, "leaseTime": state.config.lease_time.as_secs()}}).into())
+}
+
+fn get_user(state: &State) -> Response {
+    let users = state.users.iter()
+        .map(|(name, _, level)| json!({"userName": name, "level": level}))
+        .collect::<Vec<_>>();
+    Response {
+        value: json!({"User": users}),
+        initial: Some(json!({"User": {"level": "guest"}})),
+        range: Some(user_range()),
+    }
+}
+
+fn user_range() -> Value {
+    json!({"User": {
+        "level": ["guest", "admin"],
+        "userName": {"minLen": 1, "maxLen": 31},
+        "password": {"minLen": 6, "maxLen": 31},
+    }})
+}
+
+fn add_user(state: &mut State, param: &Value) -> Result<Response, ApiError> {
+    let user = &param["User"];
+    let (Some(name), Some(password), Some(level)) = (user["userName"].as_str(), user["password"].as_str(), user["level"].as_str()) else {
+        return Err(PARAM_ERROR);
+    };
+    let range = user_range();
+    let len_ok = |field: &str, v: &str| {
+        let len = v.chars().count() as u64;
+        range["User"][field]["minLen"].as_u64().unwrap() <= len && len <= range["User"][field]["maxLen"].as_u64().unwrap()
+    };
+    if !len_ok("userName", name) || !len_ok("password", password) || !is_allowed(&range["User"]["level"], level) {
+        return Err(PARAM_ERROR);
+    }
+    if state.users.iter().any(|(n, _, _)| n == name) {
+        return Err(PARAM_ERROR);
+    }
+    state.users.push((name.to_string(), password.to_string(), level.to_string()));
+    Ok(json!({"rspCode": 200}).into())
+}
+
+fn get_ability(state: &State) -> Response {
+    let channel = json!({
+        "mainEncType": {"permit": 0, "ver": 1},
+        "snap": {"permit": 6, "ver": 1},
+        "supportAiPeople": {"permit": 0, "ver": 1},
+        "supportAiVehicle": {"permit": 0, "ver": 1},
+    });
+    json!({"Ability": {
+        "abilityChn": vec![channel; state.config.channels as usize],
+        "scheduleVersion": {"permit": 0, "ver": 1},
+        "supportRecordEnable": {"permit": 0, "ver": 1},
+        "mp4": {"permit": 0, "ver": 1},
+    }}).into()
+}
+
+fn get_dev_info(state: &State) -> Response {
+    let config = &state.config;
+    let type_ = match config.kind {
+        DeviceKind::Camera => "IPC",
+        DeviceKind::Nvr => "NVR",
+        DeviceKind::HomeHub => "HOMEHUB",
+    };
+    json!({"DevInfo": {
+        "B485": 0,
+        "IOInputNum": 0,
+        "IOOutputNum": 0,
+        "audioNum": if config.kind == DeviceKind::Camera { 1 } else { 0 },
+        "buildDay": "build 2403",
+        "cfgVer": "v3.1.0.0",
+        "channelNum": config.channels,
+        "detail": format!("{}_MOCK", config.model),
+        "diskNum": 1,
+        "exactType": type_,
+        "firmVer": "v3.1.0.2347_23061923",
+        "frameworkVer": 1,
+        "hardVer": "IPC_523128M8MP",
+        "model": config.model,
+        "name": format!("Mock {}", config.model),
+        "pakSuffix": "pak,paks",
+        "serial": "00000000000000",
+        "type": type_,
+        "wifi": 0,
+    }}).into()
+}
+
+fn get_channel_status(state: &State) -> Response {
+    let config = &state.config;
+    let status = (0..config.channels).map(|channel| {
+        let mut status = json!({
+            "channel": channel,
+            "name": format!("Camera {}", channel + 1),
+            "online": 1,
+        });
+        match config.kind {
+            DeviceKind::HomeHub => {
+                status["uid"] = json!(format!("95270000MOCK{:04}", channel));
+                status["sleep"] = json!(if config.sleeping_channels.contains(&channel) { 1 } else { 0 });
+            },
+            _ => {
+                status["typeInfo"] = json!("RLC-810A");
+            }
+        }
+        status
+    }).collect::<Vec<_>>();
+
+    json!({"count": config.channels, "status": status}).into()
+}
+
+fn get_time(state: &State) -> Response {
+    let config = &state.config;
+    let tz = DeviceTimeZone::new(config.time_zone, &config.dst);
+    let now = tz.from_utc(&Utc::now()).expect("current year is in range");
+    let time = Time {
+        year: now.year, mon: now.mon, day: now.day, hour: now.hour, min: now.min, sec: now.sec,
+        hour_fmt: 0,
+        time_fmt: "DD/MM/YYYY".to_string(),
+        time_zone: config.time_zone,
+    };
+    json!({"Dst": config.dst, "Time": time}).into()
+}
+
+fn get_net_port(state: &State) -> Response {
+    json!({"NetPort": {
+        "httpEnable": 1, "httpPort": 80,
+        "httpsEnable": 0, "httpsPort": 443,
+        "mediaPort": state.config.media_port,
+        "onvifEnable": 1, "onvifPort": 8000,
+        "rtmpEnable": 1, "rtmpPort": state.config.rtmp_port,
+        "rtspEnable": 1, "rtspPort": 554,
+    }}).into()
+}
+
+/// Only NVRs support `GetRtspUrl`, and return their LAN address.
+fn get_rtsp_url(state: &State, param: &Value) -> Result<Response, ApiError> {
+    if state.config.kind != DeviceKind::Nvr {
+        return Err(NOT_SUPPORTED);
+    }
+    let channel = get_channel(state, param)?;
+    Ok(json!({"rtspUrl": {
+        "channel": channel,
+        "mainStream": format!("rtsp://192.168.1.20:554/Preview_{:02}_main", channel + 1),
+        "subStream": format!("rtsp://192.168.1.20:554/Preview_{:02}_sub", channel + 1),
+    }}).into())
+}
+
+fn get_md_state(state: &State, param: &Value) -> Result<Response, ApiError> {
+    let channel = get_channel(state, param)?;
+    Ok(json!({"state": state.detections[channel as usize].motion as u8}).into())
+}
+
+/// All channels support person, vehicle and pet detection, but not face detection.
+fn get_ai_state(state: &State, param: &Value) -> Result<Response, ApiError> {
+    let channel = get_channel(state, param)?;
+    let detections = &state.detections[channel as usize];
+    let ai_state = |active: bool| json!({"alarm_state": active as u8, "support": 1});
+    Ok(json!({
+        "channel": channel,
+        "dog_cat": ai_state(detections.animal),
+        "face": {"alarm_state": 0, "support": 0},
+        "people": ai_state(detections.person),
+        "vehicle": ai_state(detections.vehicle),
+    }).into())
+}
+
+fn get_channel(state: &State, param: &Value) -> Result<u8, ApiError> {
+    match param["channel"].as_u64() {
+        Some(channel) if channel < state.config.channels as u64 => Ok(channel as u8),
+        _ => Err(PARAM_ERROR),
+    }
+}
+
+fn is_allowed(allowed: &Value, value: &str) -> bool {
+    allowed.as_array().map(|a| a.iter().any(|v| v.as_str() == Some(value))).unwrap_or(false)
+}
+
+const POST_REC: [&str; 6] = ["15 Seconds", "30 Seconds", "1 Minute", "2 Minutes", "5 Minutes", "10 Minutes"];
+const PACK_TIME: [&str; 3] = ["30 Minutes", "45 Minutes", "60 Minutes"];
+
+pub(crate) fn default_rec(kind: DeviceKind, channel: u8) -> Value {
+    let table = "1".repeat(7 * 24);
+    let mut rec = json!({
+        "enable": 1,
+        "overwrite": 1,
+        "postRec": "1 Minute",
+        "preRec": 1,
+        "saveDay": 30,
+        "schedule": {"channel": channel, "table": {"MD": table, "AI_PEOPLE": table, "AI_VEHICLE": table}},
+    });
+    if kind == DeviceKind::Nvr {
+        rec["packTime"] = json!("60 Minutes");
+    }
+    rec
+}
+
+pub(crate) fn default_rec_v1(channel: u8) -> Value {
+    json!({
+        "channel": channel,
+        "overwrite": 1,
+        "packTime": "60 Minutes",
+        "postRec": "1 Minute",
+        "preRec": 1,
+        "schedule": {"enable": 1, "table": "1".repeat(7 * 24)},
+    })
+}
+
+fn get_rec(state: &State, param: &Value) -> Result<Response, ApiError> {
+    let channel = get_channel(state, param)?;
+    Ok(Response {
+        value: json!({"Rec": state.rec_v1[channel as usize]}),
+        // Like devices, defaults are those of channel 0 whatever the channel requested
+        initial: Some(json!({"Rec": default_rec_v1(0)})),
+        range: Some(json!({"rec": {
+            "channel": channel,
+            "overwrite": "boolean",
+            "packTime": PACK_TIME,
+            "postRec": POST_REC,
+            "preRec": "boolean",
+            "schedule": "1".repeat(7 * 24),
+        }})),
+    })
+}
+
+fn set_rec(state: &mut State, param: &Value) -> Result<Response, ApiError> {
+    let rec = &param["Rec"];
+    let channel = get_channel(state, rec)?;
+    if !is_allowed(&json!(POST_REC), rec["postRec"].as_str().unwrap_or("")) ||
+        !is_allowed(&json!(PACK_TIME), rec["packTime"].as_str().unwrap_or("")) {
+        return Err(PARAM_ERROR);
+    }
+    merge(&mut state.rec_v1[channel as usize], rec);
+    Ok(json!({"rspCode": 200}).into())
+}
+
+fn get_rec_v20(state: &State, param: &Value) -> Result<Response, ApiError> {
+    let channel = get_channel(state, param)?;
+    let mut range = json!({
+        "enable": "boolean",
+        "overwrite": "boolean",
+        "postRec": POST_REC,
+        "preRec": "boolean",
+        "schedule": {"channel": channel, "table": {"MD": "boolean", "AI_PEOPLE": "boolean", "AI_VEHICLE": "boolean"}},
+    });
+    if state.config.kind == DeviceKind::Nvr {
+        range["packTime"] = json!(PACK_TIME);
+    }
+    Ok(Response {
+        value: json!({"Rec": state.rec[channel as usize]}),
+        // Like devices, defaults are those of channel 0 whatever the channel requested
+        initial: Some(json!({"Rec": default_rec(state.config.kind, 0)})),
+        range: Some(json!({"Rec": range})),
+    })
+}
+
+fn set_rec_v20(state: &mut State, param: &Value) -> Result<Response, ApiError> {
+    let rec = &param["Rec"];
+    let channel = get_channel(state, &rec["schedule"])?;
+    if let Some(post_rec) = rec.get("postRec") {
+        if !is_allowed(&json!(POST_REC), post_rec.as_str().unwrap_or("")) {
+            return Err(PARAM_ERROR);
+        }
+    }
+    if let Some(pack_time) = rec.get("packTime") {
+        if state.config.kind != DeviceKind::Nvr || !is_allowed(&json!(PACK_TIME), pack_time.as_str().unwrap_or("")) {
+            return Err(PARAM_ERROR);
+        }
+    }
+    merge(&mut state.rec[channel as usize], rec);
+    Ok(json!({"rspCode": 200}).into())
+}
+
+/// Merges the fields of `update` into `target`, recursively for objects.
+fn merge(target: &mut Value, update: &Value) {
+    match (target, update) {
+        (Value::Object(target), Value::Object(update)) => {
+            for (k, v) in update {
+                merge(target.entry(k.clone()).or_insert(Value::Null), v);
+            }
+        },
+        (target, update) => *target = update.clone(),
+    }
+}
+
+//----- Recordings
+
+fn parse_date_time(v: &Value) -> Option<NaiveDateTime> {
+    let field = |name: &str| v[name].as_u64().map(|n| n as u32);
+    let date = NaiveDate::from_ymd_opt(field("year")? as i32, field("mon")?, field("day")?)?;
+    let time = NaiveTime::from_hms_opt(field("hour")?, field("min")?, field("sec")?)?;
+    Some(NaiveDateTime::new(date, time))
+}
+
+fn date_time_value(dt: &NaiveDateTime) -> Value {
+    json!({
+        "year": dt.year(), "mon": dt.month(), "day": dt.day(),
+        "hour": dt.hour(), "min": dt.minute(), "sec": dt.second(),
+    })
+}
+
+fn search(state: &State, param: &Value) -> Result<Response, ApiError> {
+    let search = &param["Search"];
+    let channel = get_channel(state, search)?;
+    let stream_type = search["streamType"].as_str().ok_or(PARAM_ERROR)?;
+    let start = parse_date_time(&search["StartTime"]).ok_or(PARAM_ERROR)?;
+    let end = parse_date_time(&search["EndTime"]).ok_or(PARAM_ERROR)?;
+    let only_status = search["onlyStatus"].as_u64().ok_or(PARAM_ERROR)? != 0;
+
+    let files = state.config.files.iter()
+        .filter(|f| f.channel == channel && f.stream_type.as_str() == stream_type)
+        .collect::<Vec<_>>();
+
+    // Per-day status for each month of the range
+    let mut status = Vec::new();
+    let mut month = start.date().with_day(1).unwrap();
+    while month <= end.date() {
+        let next_month = month + Months::new(1);
+        let table = month.iter_days().take_while(|d| *d < next_month)
+            .map(|day| if files.iter().any(|f| f.start.date() <= day && day <= f.end.date()) { '1' } else { '0' })
+            .collect::<String>();
+        status.push(json!({"year": month.year(), "mon": month.month(), "table": table}));
+        month = next_month;
+    }
+
+    let mut result = json!({"channel": channel, "Status": status});
+
+    // File list only if the range is within a single day
+    if !only_status && start.date() == end.date() {
+        let files = files.iter()
+            .filter(|f| f.start <= end && f.end >= start)
+            .map(|f| json!({
+                "type": f.stream_type,
+                "StartTime": date_time_value(&f.start),
+                "EndTime": date_time_value(&f.end),
+                "frameRate": 0,
+                "height": 0,
+                "width": 0,
+                "size": f.data.len().to_string(),
+                "name": f.name,
+            }))
+            .collect::<Vec<_>>();
+        result["File"] = json!(files);
+    }
+
+    Ok(json!({"SearchResult": result}).into())
+}
+
+fn nvr_download(state: &State, param: &Value) -> Result<Response, ApiError> {
+    let search = &param["NvrDownload"];
+    let channel = get_channel(state, search)?;
+    let stream_type = search["streamType"].as_str().ok_or(PARAM_ERROR)?;
+    let start = parse_date_time(&search["StartTime"]).ok_or(PARAM_ERROR)?;
+    let end = parse_date_time(&search["EndTime"]).ok_or(PARAM_ERROR)?;
+
+    let files = state.config.files.iter()
+        .filter(|f| f.channel == channel && f.stream_type.as_str() == stream_type && f.start <= end && f.end >= start)
+        .map(|f| json!({"fileName": f.name, "fileSize": f.data.len().to_string()}))
+        .collect::<Vec<_>>();
+
+    Ok(json!({"fileCount": files.len(), "fileList": files}).into())
+}
+
+//-------------------------------------------------------------------------------------------------
+// Binary commands
+
+fn download(state: &mut State, query: &HashMap<String, String>) -> Result<MockResponse, ApiError> {
+    let source = query.get("source").ok_or(PARAM_ERROR)?;
+    let file = state.config.files.iter().find(|f| f.name == *source).ok_or(NOT_EXIST)?;
+    let mut data = file.data.clone();
+    if state.faults.truncated_downloads > 0 {
+        state.faults.truncated_downloads -= 1;
+        data.truncate(data.len() / 2);
+    }
+    Ok(MockResponse::Binary {
+        content_type: "video/mp4",
+        data,
+        filename: Some(query.get("output").unwrap_or(source).clone()),
+    })
+}
+
+/// Serves the file's data after an FLV header, which is enough for clients that only stream it.
+fn playback(state: &State, query: &HashMap<String, String>) -> Result<MockResponse, ApiError> {
+    let channel = query.get("channel").and_then(|c| c.parse::<u8>().ok()).ok_or(PARAM_ERROR)?;
+    let source = query.get("source").ok_or(PARAM_ERROR)?;
+    let start = query.get("start")
+        .and_then(|s| NaiveDateTime::parse_from_str(s, "%Y%m%d%H%M%S").ok())
+        .ok_or(PARAM_ERROR)?;
+    let file = state.config.files.iter()
+        .find(|f| f.name == *source && f.channel == channel && f.start == start)
+        .ok_or(NOT_EXIST)?;
+
+    let mut data = b"FLV\x01\x05\x00\x00\x00\x09\x00\x00\x00\x00".to_vec();
+    data.extend(&file.data);
+    Ok(MockResponse::Binary {
+        content_type: "video/x-flv",
+        data,
+        filename: query.get("output").cloned(),
+    })
+}
+
+fn snap(state: &State, query: &HashMap<String, String>) -> Result<MockResponse, ApiError> {
+    match query.get("channel").and_then(|c| c.parse::<u8>().ok()) {
+        Some(channel) if channel < state.config.channels => (),
+        _ => return Err(PARAM_ERROR),
+    }
+    // Scaled images are generated
+    let size = |name: &str| query.get(name).map(|v| v.parse::<u16>().map_err(|_| PARAM_ERROR)).transpose();
+    let data = match (size("width")?, size("height")?) {
+        (Some(width), Some(height)) => crate::jpeg(width, height),
+        _ => state.config.snapshot.clone(),
+    };
+    Ok(MockResponse::Binary {
+        content_type: "image/jpeg",
+        data,
+        filename: None,
+    })
+}
+
+//-------------------------------------------------------------------------------------------------
+// Live stream
+
+/// Serves a 2 seconds live stream for `flv?port=1935&app=bcs&stream=channel0_main.bcs&token=...`
+pub(crate) fn live(state: &mut State, req: &MockRequest) -> MockResponse {
+    state.commands.push("flv".to_string());
+    if req.query.get("port").and_then(|p| p.parse::<u16>().ok()) != Some(state.config.rtmp_port) {
+        return json_error("flv", PARAM_ERROR);
+    }
+    let channel = req.query.get("stream")
+        .and_then(|s| s.strip_prefix("channel"))
+        .and_then(|s| s.split_once('_'))
+        .and_then(|(channel, stream)| Some((channel.parse::<u8>().ok()?, stream)));
+    match channel {
+        Some((channel, "main.bcs" | "sub.bcs" | "ext.bcs")) if channel < state.config.channels => (),
+        _ => return json_error("flv", PARAM_ERROR),
+    }
+    if !req.query.get("token").is_some_and(|t| state.sessions.is_valid(t)) {
+        return json_error("flv", PLEASE_LOGIN);
+    }
+    MockResponse::Binary {
+        content_type: "video/x-flv",
+        data: crate::flv::live_stream(2 * crate::flv::FRAME_RATE, state.faults.live_timestamp_reset),
+        filename: None,
+    }
+}
+
+//-------------------------------------------------------------------------------------------------
+
+fn error_value(cmd: &str, (rsp_code, detail): ApiError) -> Value {
+    json!({"cmd": cmd, "code": 1, "error": {"rspCode": rsp_code, "detail": detail}})
+}
+
+fn json_error(cmd: &str, err: ApiError) -> MockResponse {
+    MockResponse::Json(json!([error_value(cmd, err)]).to_string())
+}
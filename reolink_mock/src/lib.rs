@@ -0,0 +1,219 @@
+//! An in-process mock of a Reolink device's HTTP API, to test clients without a real device.
+//!
+//! The mock implements the `api.cgi` endpoint for the commands implemented by `reolink_api`,
+//! with token sessions, the authentication rules of each command, and the url-encoding
+//! strictness of the Home Hub. It also serves the HTTP-FLV live stream. Faults can be injected
+//! to test error handling.
+//!
+//! `BaichuanDevice` is a scripted stand-in for the native TCP protocol, pushing alarm events.
+//!
+//! ```no_run
+//! use reolink_mock::{MockConfig, MockDevice};
+//!
+//! let device = MockDevice::start(MockConfig::camera())?;
+//! let client = reolink_api::ReolinkBlockingClient::new(&device.url(), "admin".to_string(), "s3cr3t-p4ssw0rd".to_string())?;
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::JoinHandle;
+use reolink_api::events::DetectionState;
+use reolink_api::reqwest::Url;
+use tiny_http::{Header, Response, Server};
+
+mod baichuan;
+mod config;
+mod flv;
+mod handlers;
+mod sessions;
+
+pub use baichuan::{BaichuanDevice, BaichuanScript};
+pub use config::{jpeg, DeviceKind, Faults, MockConfig, MockFile};
+use handlers::{MockRequest, MockResponse};
+use sessions::Sessions;
+
+/// A mock device, listening on a local port until it is dropped.
+pub struct MockDevice {
+    shared: Arc<Shared>,
+    addr: SocketAddr,
+    workers: Vec<JoinHandle<()>>,
+}
+
+struct Shared {
+    server: Server,
+    state: Mutex<State>,
+    shutdown: AtomicBool,
+}
+
+/// Mutable state of a mock device.
+pub(crate) struct State {
+    pub config: MockConfig,
+    pub faults: Faults,
+    pub sessions: Sessions,
+    /// Commands received, in order
+    pub commands: Vec<String>,
+    /// Users: name, password and level
+    pub users: Vec<(String, String, String)>,
+    /// `GetRecV20` configuration of each channel
+    pub rec: Vec<serde_json::Value>,
+    /// `GetRec` configuration of each channel
+    pub rec_v1: Vec<serde_json::Value>,
+    /// Detections returned by `GetMdState` and `GetAiState` for each channel
+    pub detections: Vec<DetectionState>,
+}
+
+impl MockDevice {
+    const WORKERS: usize = 4;
+
+    /// Starts a mock device on a random local port.
+    pub fn start(config: MockConfig) -> anyhow::Result<MockDevice> {
+        let server = Server::http("127.0.0.1:0").map_err(|e| anyhow::anyhow!(e))?;
+        let addr = server.server_addr().to_ip().ok_or_else(|| anyhow::anyhow!("Not an IP address"))?;
+
+        let state = State {
+            users: vec![(config.login.clone(), config.password.clone(), "admin".to_string())],
+            rec: (0..config.channels).map(|c| handlers::default_rec(config.kind, c)).collect(),
+            rec_v1: (0..config.channels).map(handlers::default_rec_v1).collect(),
+            detections: vec![DetectionState::default(); config.channels as usize],
+            config,
+            faults: Faults::default(),
+            sessions: Sessions::default(),
+            commands: Vec::new(),
+        };
+
+        let shared = Arc::new(Shared {
+            server,
+            state: Mutex::new(state),
+            shutdown: AtomicBool::new(false),
+        });
+
+        let workers = (0..Self::WORKERS).map(|_| {
+            let shared = shared.clone();
+            std::thread::spawn(move || shared.serve())
+        }).collect();
+
+        Ok(MockDevice { shared, addr, workers })
+    }
+
+    /// Base url of the device, e.g. `http://127.0.0.1:1234/`
+    pub fn url(&self) -> String {
+        format!("http://{}/", self.addr)
+    }
+
+    /// Address the device listens on.
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Sets the faults injected in subsequent responses.
+    pub fn set_faults(&self, faults: Faults) {
+        self.shared.state.lock().unwrap().faults = faults;
+    }
+
+    /// Expires all live tokens.
+    pub fn expire_tokens(&self) {
+        self.shared.state.lock().unwrap().sessions.expire_all();
+    }
+
+    /// Number of live tokens.
+    pub fn session_count(&self) -> usize {
+        self.shared.state.lock().unwrap().sessions.count()
+    }
+
+    /// Sets the detections of a channel.
+    pub fn set_detections(&self, channel: u8, detections: DetectionState) {
+        self.shared.state.lock().unwrap().detections[channel as usize] = detections;
+    }
+
+    /// Puts the camera of a channel to sleep, or wakes it up.
+    pub fn set_sleeping(&self, channel: u8, sleeping: bool) {
+        let channels = &mut self.shared.state.lock().unwrap().config.sleeping_channels;
+        channels.retain(|c| *c != channel);
+        if sleeping {
+            channels.push(channel);
+        }
+    }
+
+    /// Commands received so far, in order.
+    pub fn commands(&self) -> Vec<String> {
+        self.shared.state.lock().unwrap().commands.clone()
+    }
+}
+
+impl Drop for MockDevice {
+    fn drop(&mut self) {
+        self.shared.shutdown.store(true, Ordering::SeqCst);
+        for _ in &self.workers {
+            self.shared.server.unblock();
+        }
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+impl Shared {
+    fn serve(&self) {
+        while !self.shutdown.load(Ordering::SeqCst) {
+            let Ok(mut request) = self.server.recv() else {
+                break;
+            };
+
+            let Ok(url) = Url::parse(&format!("http://localhost{}", request.url())) else {
+                let _ = request.respond(Response::empty(400));
+                continue;
+            };
+            let is_flv = url.path() == "/flv";
+            if url.path() != "/cgi-bin/api.cgi" && !is_flv {
+                let _ = request.respond(Response::empty(404));
+                continue;
+            }
+
+            let mut body = Vec::new();
+            if request.as_reader().read_to_end(&mut body).is_err() {
+                let _ = request.respond(Response::empty(400));
+                continue;
+            }
+
+            let mock_request = MockRequest {
+                query: url.query_pairs().map(|(k, v)| (k.into_owned(), v.into_owned())).collect::<HashMap<_, _>>(),
+                raw_query: url.query().unwrap_or("").to_string(),
+                body,
+            };
+
+            let (response, latency) = {
+                let mut state = self.state.lock().unwrap();
+                let response = if is_flv {
+                    handlers::live(&mut state, &mock_request)
+                } else {
+                    handlers::handle(&mut state, &mock_request)
+                };
+                (response, state.faults.latency)
+            };
+            if !latency.is_zero() {
+                std::thread::sleep(latency);
+            }
+
+            let response = match response {
+                MockResponse::Json(body) => Response::from_string(body)
+                    .with_header(header("Content-Type", "application/json")),
+                MockResponse::Binary { content_type, data, filename } => {
+                    let mut response = Response::from_data(data)
+                        .with_header(header("Content-Type", content_type));
+                    if let Some(filename) = filename {
+                        response.add_header(header("Content-Disposition", &format!("attachment;filename={}", filename)));
+                    }
+                    response
+                },
+            };
+            let _ = request.respond(response);
+        }
+    }
+}
+
+fn header(name: &str, value: &str) -> Header {
+    Header::from_bytes(name.as_bytes(), value.as_bytes()).unwrap()
+}
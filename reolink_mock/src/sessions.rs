@@ -0,0 +1,57 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// Live tokens of a mock device.
+#[derive(Debug, Default)]
+pub(crate) struct Sessions {
+    tokens: HashMap<String, Session>,
+    counter: u64,
+}
+
+#[derive(Debug)]
+struct Session {
+    expires: Instant,
+    /// Tokens obtained with credentials in the url don't work (Home Hub behavior)
+    valid: bool,
+}
+
+impl Sessions {
+    /// Creates a new token, or returns `None` if the session limit has been reached.
+    pub fn create(&mut self, lease_time: Duration, max_sessions: usize, valid: bool) -> Option<String> {
+        self.purge();
+        if self.tokens.len() >= max_sessions {
+            return None;
+        }
+        self.counter += 1;
+        let token = format!("mock{:08x}{:04x}", std::process::id(), self.counter);
+        self.tokens.insert(token.clone(), Session {
+            expires: Instant::now() + lease_time,
+            valid,
+        });
+        Some(token)
+    }
+
+    pub fn is_valid(&mut self, token: &str) -> bool {
+        self.purge();
+        self.tokens.get(token).map(|s| s.valid).unwrap_or(false)
+    }
+
+    pub fn remove(&mut self, token: &str) -> bool {
+        self.tokens.remove(token).is_some()
+    }
+
+    /// Expires all tokens.
+    pub fn expire_all(&mut self) {
+        self.tokens.clear();
+    }
+
+    pub fn count(&mut self) -> usize {
+        self.purge();
+        self.tokens.len()
+    }
+
+    fn purge(&mut self) {
+        let now = Instant::now();
+        self.tokens.retain(|_, s| s.expires > now);
+    }
+}
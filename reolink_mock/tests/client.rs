@@ -0,0 +1,739 @@
+use std::time::Duration;
+use reolink_api::api::{ApiError, DeviceDuration};
+use reolink_api::api::record::StreamType;
+use reolink_api::api::security::UserLevel;
+use reolink_api::blocking::ReolinkClient;
+use reolink_api::chrono::{NaiveDate, NaiveDateTime};
+use reolink_mock::{Faults, MockConfig, MockDevice, MockFile};
+
+fn client(device: &MockDevice) -> anyhow::Result<ReolinkClient> {
+    ReolinkClient::new(&device.url(), "admin".to_string(), "s3cr3t-p4ssw0rd".to_string())
+}
+
+fn date_time(day: u32, hour: u32, min: u32) -> NaiveDateTime {
+    NaiveDate::from_ymd_opt(2025, 2, day).unwrap().and_hms_opt(hour, min, 0).unwrap()
+}
+
+fn files() -> Vec<MockFile> {
+    vec![
+        MockFile {
+            channel: 0,
+            stream_type: StreamType::Main,
+            start: date_time(9, 10, 0),
+            end: date_time(9, 10, 5),
+            name: "Mp4Record/2025-02-09/RecM01_20250209_100000_100500_6D28808_100.mp4".to_string(),
+            data: vec![1; 100],
+        },
+        MockFile {
+            channel: 0,
+            stream_type: StreamType::Main,
+            start: date_time(11, 8, 0),
+            end: date_time(11, 8, 2),
+            name: "Mp4Record/2025-02-11/RecM01_20250211_080000_080200_6D28808_50.mp4".to_string(),
+            data: vec![2; 50],
+        },
+    ]
+}
+
+fn rsp_code(err: &anyhow::Error) -> Option<isize> {
+    err.downcast_ref::<ApiError>().map(|e| e.error.rsp_code)
+}
+
+#[test]
+fn test_sessions() -> anyhow::Result<()> {
+    let device = MockDevice::start(MockConfig::camera())?;
+    let client = client(&device)?;
+
+    client.login()?;
+    assert_eq!(1, device.session_count());
+    client.logout()?;
+    assert_eq!(0, device.session_count());
+
+    client.login()?;
+    assert_eq!(1, device.session_count());
+    // Dropping the client releases its token
+    drop(client);
+    assert_eq!(0, device.session_count());
+    Ok(())
+}
+
+#[test]
+fn test_authentication() -> anyhow::Result<()> {
+    use reolink_api::api::system::get_ability::*;
+    use reolink_api::api::system::get_dev_info::*;
+
+    let device = MockDevice::start(MockConfig::camera())?;
+    let client = client(&device)?;
+
+    // Login/password in the url
+    let resp = client.exec(&GetDevInfoRequest)?;
+    assert_eq!("RLC-810A", resp.dev_info.model);
+    assert_eq!(vec!["GetDevinfo"], device.commands());
+
+    // Token required: the client logs in first
+    client.exec(&GetAbilityRequest { user: GetAbility { user_name: "NULL".to_string() } })?;
+    assert_eq!(vec!["GetDevinfo", "Login", "GetAbility"], device.commands());
+
+    let bad_client = ReolinkClient::new(&device.url(), "admin".to_string(), "wrong".to_string())?;
+    let err = bad_client.exec(&GetDevInfoRequest).unwrap_err();
+    assert_eq!(Some(-7), rsp_code(&err));
+    Ok(())
+}
+
+#[test]
+fn test_home_hub_url_encoding() -> anyhow::Result<()> {
+    use reolink_api::api::record::download::*;
+
+    let device = MockDevice::start(MockConfig::home_hub(2).with_files(files()))?;
+    let client = client(&device)?;
+
+    let name = "Mp4Record/2025-02-09/RecM01_20250209_100000_100500_6D28808_100.mp4";
+    let bytes = client.download(&DownloadRequest {
+        source: name.to_string(),
+        output: Some("my video.mp4".to_string()),
+    })?;
+    assert_eq!(100, bytes.len());
+
+    // Standard url-encoding is rejected
+    let resp = reolink_api::reqwest::blocking::Client::new()
+        .post(format!("{}cgi-bin/api.cgi", device.url()))
+        .query(&[("cmd", "GetDevinfo"), ("user", "admin"), ("password", "s3cr3t-p4ssw0rd"), ("x", "a/b c")])
+        .body("[]")
+        .send()?
+        .text()?;
+    assert!(resp.contains("param error"));
+    Ok(())
+}
+
+#[test]
+fn test_snapshot() -> anyhow::Result<()> {
+    use reolink_api::api::record::snapshot::*;
+
+    let device = MockDevice::start(MockConfig::camera())?;
+    let client = client(&device)?;
+
+    let snapshot = client.snapshot(&SnapshotRequest::new(0))?;
+    assert_eq!(&[0xFF, 0xD8], &snapshot.jpeg[0..2]);
+    assert_eq!((640, 360), (snapshot.width, snapshot.height));
+
+    let snapshot = client.snapshot(&SnapshotRequest::new(0).with_size(320, 180))?;
+    assert_eq!((320, 180), (snapshot.width, snapshot.height));
+
+    // Not an image
+    let mut config = MockConfig::camera();
+    config.snapshot = b"<html>Not found</html>".to_vec();
+    let other = MockDevice::start(config)?;
+    let err = self::client(&other)?.snapshot(&SnapshotRequest::new(0)).unwrap_err();
+    assert!(matches!(err.downcast_ref::<SnapshotError>(), Some(SnapshotError::NotJpeg(_))));
+    Ok(())
+}
+
+#[test]
+fn test_playback() -> anyhow::Result<()> {
+    use std::io::Read;
+    use reolink_api::api::record::download::DownloadRequest;
+    use reolink_api::api::record::playback::PlaybackRequest;
+
+    let device = MockDevice::start(MockConfig::camera().with_files(files()))?;
+    let client = client(&device)?;
+
+    let files = client.search_files(0, StreamType::Main, date_time(9, 0, 0), date_time(9, 23, 59))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    let mut request = PlaybackRequest::new(0, &files[0]);
+    request.seek = 30;
+
+    let mut reader = client.stream(&request)?;
+    let mut header = [0; 3];
+    reader.read_exact(&mut header)?;
+    assert_eq!(b"FLV", &header);
+    let mut rest = Vec::new();
+    reader.read_to_end(&mut rest)?;
+    assert_eq!(10 + 100, rest.len());
+
+    // Error responses are turned into errors
+    request.source = "missing.mp4".to_string();
+    let err = client.stream(&request).err().unwrap();
+    assert_eq!(Some(-1), rsp_code(&err));
+    let err = client.download(&DownloadRequest { source: "missing.mp4".to_string(), output: None }).unwrap_err();
+    assert_eq!(Some(-1), rsp_code(&err));
+    Ok(())
+}
+
+#[test]
+fn test_live() -> anyhow::Result<()> {
+    use reolink_api::live::flv::{AudioData, FlvTag, VideoData};
+
+    let mut config = MockConfig::nvr(2);
+    config.rtmp_port = 1936;
+    let device = MockDevice::start(config)?;
+    let client = client(&device)?;
+
+    let reader = client.live(1, StreamType::Sub)?;
+    assert!(reader.header().has_video);
+    let tags = reader.collect::<Result<Vec<_>, _>>()?;
+
+    let video = tags.iter().filter_map(|t| match t { FlvTag::Video(v) => Some(v), _ => None }).collect::<Vec<_>>();
+    assert!(matches!(video[0].data, VideoData::Config(_)));
+    assert_eq!(50, video.iter().filter(|v| matches!(v.data, VideoData::NalUnits(_))).count());
+    assert_eq!(2, video.iter().filter(|v| v.keyframe && matches!(v.data, VideoData::NalUnits(_))).count());
+    assert_eq!(1960, video.last().unwrap().timestamp);
+    assert!(tags.iter().any(|t| matches!(t, FlvTag::Audio(a) if matches!(a.data, AudioData::AacConfig(_)))));
+
+    assert!(client.live(2, StreamType::Main).is_err());
+    // Ports are only fetched once
+    assert_eq!(1, device.commands().iter().filter(|c| *c == "GetNetPort").count());
+    Ok(())
+}
+
+#[test]
+fn test_stream_urls() -> anyhow::Result<()> {
+    use reolink_api::live::urls::UrlAuth;
+
+    let device = MockDevice::start(MockConfig::camera())?;
+    let client = client(&device)?;
+    let host = device.addr().ip();
+
+    let urls = client.stream_urls(0, UrlAuth::Password)?;
+    let rtsp = urls.main.rtsp.unwrap();
+    assert_eq!(format!("rtsp://admin:s3cr3t-p4ssw0rd@{host}:554/h264Preview_01_main"), rtsp.as_str());
+    assert_eq!(format!("rtmp://{host}:1935/bcs/channel0_ext.bcs?channel=0&stream=2&user=admin&password=s3cr3t-p4ssw0rd"), urls.ext.rtmp.unwrap().as_str());
+    assert!(device.commands().contains(&"GetRtspUrl".to_string()));
+
+    // NVRs return their RTSP urls, and FLV urls can be used to read the stream
+    let device = MockDevice::start(MockConfig::nvr(2))?;
+    let client = self::client(&device)?;
+    let urls = client.stream_urls(1, UrlAuth::Token)?;
+    assert_eq!(format!("rtsp://{host}:554/Preview_02_sub"), urls.sub.rtsp.unwrap().as_str());
+    let flv = urls.sub.flv.unwrap();
+    assert!(flv.query_pairs().any(|(k, _)| k == "token"));
+
+    let mut response = reolink_api::reqwest::blocking::get(flv)?;
+    assert!(response.status().is_success());
+    let mut header = [0; 3];
+    std::io::Read::read_exact(&mut response, &mut header)?;
+    assert_eq!(b"FLV", &header);
+    Ok(())
+}
+
+#[test]
+fn test_events() -> anyhow::Result<()> {
+    use reolink_api::events::{DetectionState, EventKind};
+
+    let device = MockDevice::start(MockConfig::camera())?;
+    let client = client(&device)?;
+    let mut watcher = client.events([0]).with_interval(Duration::ZERO);
+
+    assert!(watcher.poll()?.is_empty());
+
+    device.set_detections(0, DetectionState { motion: true, ..Default::default() });
+    let event = watcher.next().unwrap()?;
+    assert_eq!((0, EventKind::Motion), (event.channel, event.kind));
+    assert!(event.is_start());
+
+    device.set_detections(0, DetectionState { motion: true, person: true, ..Default::default() });
+    let events = watcher.poll()?;
+    assert_eq!(vec![EventKind::Person], events.iter().map(|e| e.kind).collect::<Vec<_>>());
+
+    device.set_detections(0, DetectionState::default());
+    let mut ended = Vec::new();
+    watcher.run(|event| {
+        ended.push(event);
+        if ended.len() == 2 { std::ops::ControlFlow::Break(()) } else { std::ops::ControlFlow::Continue(()) }
+    })?;
+    assert!(ended.iter().all(|e| e.end.is_some_and(|end| end >= e.start)));
+    assert_eq!(ended[1].start, events[0].start);
+    Ok(())
+}
+
+#[test]
+fn test_events_unsupported() -> anyhow::Result<()> {
+    use reolink_api::events::{DetectionState, EventKind};
+
+    let mut config = MockConfig::camera();
+    config.unsupported_commands = vec!["GetChannelstatus".to_string(), "GetAiState".to_string()];
+    let device = MockDevice::start(config)?;
+    let client = client(&device)?;
+    let count = |cmd: &str| device.commands().iter().filter(|c| *c == cmd).count();
+
+    let mut watcher = client.events([0]).with_interval(Duration::ZERO);
+    device.set_detections(0, DetectionState { motion: true, person: true, ..Default::default() });
+    let event = watcher.next().unwrap()?;
+    assert_eq!(EventKind::Motion, event.kind);
+    for _ in 0..3 {
+        assert!(watcher.poll()?.is_empty());
+    }
+
+    // Unsupported commands are only sent once
+    assert_eq!(4, count("GetMdState"));
+    assert_eq!((1, 1), (count("GetChannelstatus"), count("GetAiState")));
+    Ok(())
+}
+
+#[test]
+fn test_events_sleeping() -> anyhow::Result<()> {
+    use reolink_api::events::DetectionState;
+
+    let mut config = MockConfig::home_hub(2);
+    config.sleeping_channels = vec![1];
+    let device = MockDevice::start(config)?;
+    let client = client(&device)?;
+    let md_states = || device.commands().iter().filter(|c| *c == "GetMdState").count();
+
+    let mut watcher = client.events([0, 1]).with_interval(Duration::from_millis(100));
+    device.set_detections(1, DetectionState { motion: true, ..Default::default() });
+
+    // The sleeping channel isn't polled, and is checked again after twice the interval
+    assert!(watcher.poll()?.is_empty());
+    assert_eq!(1, md_states());
+    assert!(watcher.poll()?.is_empty());
+    assert_eq!(1, md_states());
+
+    device.set_sleeping(1, false);
+    std::thread::sleep(Duration::from_millis(250));
+    let events = watcher.poll()?;
+    assert_eq!(3, md_states());
+    assert_eq!(vec![1], events.iter().map(|e| e.channel).collect::<Vec<_>>());
+
+    // Detections end when the camera goes to sleep
+    device.set_sleeping(1, true);
+    std::thread::sleep(Duration::from_millis(150));
+    let events = watcher.poll()?;
+    assert_eq!(1, events.len());
+    assert!(events[0].end.is_some());
+    Ok(())
+}
+
+#[test]
+fn test_alarm_listener() -> anyhow::Result<()> {
+    use reolink_api::baichuan::{AlarmEvent, AlarmListener};
+    use reolink_api::baichuan::codec::{ENCRYPTION_AES, ENCRYPTION_FULL_AES};
+    use reolink_api::events::{DetectionState, EventKind};
+    use reolink_mock::{BaichuanDevice, BaichuanScript};
+
+    let alarm = |channel, motion, person| AlarmEvent { channel, state: DetectionState { motion, person, ..Default::default() } };
+    let mut script = BaichuanScript::new(vec![
+        alarm(0, true, false),
+        alarm(0, true, true),
+        alarm(1, true, false),
+        alarm(0, false, false),
+    ]);
+    script.wait_for_ping = true;
+    let baichuan = BaichuanDevice::start(script)?;
+
+    // Connected through the HTTP API, which returns the port of the native protocol
+    let mut config = MockConfig::nvr(2);
+    config.media_port = baichuan.addr().port();
+    let device = MockDevice::start(config)?;
+    let mut listener = client(&device)?.alarm_listener()?;
+    listener.set_ping_interval(Duration::from_millis(100))?;
+
+    let events = listener.collect::<Result<Vec<_>, _>>()?;
+    let summary = events.iter().map(|e| (e.channel, e.kind, e.is_start())).collect::<Vec<_>>();
+    assert_eq!(vec![
+        (0, EventKind::Motion, true),
+        (0, EventKind::Person, true),
+        (1, EventKind::Motion, true),
+        (0, EventKind::Motion, false),
+        (0, EventKind::Person, false),
+    ], summary);
+
+    // Legacy and modern logins, subscription and ping
+    assert_eq!(vec![1, 1, 31, 93], baichuan.join()?);
+
+    // Wrong password
+    let baichuan = BaichuanDevice::start(BaichuanScript::new(Vec::new()))?;
+    let err = AlarmListener::connect(baichuan.addr(), "admin", "wrong").err().unwrap();
+    assert!(err.to_string().contains("Login failed"), "{err}");
+    baichuan.join()?;
+
+    // AES isn't supported, whether it covers binary payloads or not
+    for encryption in [ENCRYPTION_AES, ENCRYPTION_FULL_AES] {
+        let mut script = BaichuanScript::new(Vec::new());
+        script.encryption = encryption;
+        let baichuan = BaichuanDevice::start(script)?;
+        let err = AlarmListener::connect(baichuan.addr(), "admin", "s3cr3t-p4ssw0rd").err().unwrap();
+        assert!(err.to_string().contains("AES"), "{err}");
+        baichuan.join()?;
+    }
+    Ok(())
+}
+
+#[test]
+fn test_record_live() -> anyhow::Result<()> {
+    let device = MockDevice::start(MockConfig::camera())?;
+    let client = client(&device)?;
+
+    let path = std::env::temp_dir().join(format!("reolink-live-{}.mp4", std::process::id()));
+
+    // Stops after one second of stream
+    let duration = client.record_live(0, Duration::from_secs(1), &path)?;
+    assert_eq!(Duration::from_millis(960), duration);
+    let data = std::fs::read(&path)?;
+    assert_eq!(b"ftyp", &data[4..8]);
+    assert_eq!(1, data.windows(4).filter(|w| w == b"moof").count());
+    assert!(data.windows(4).any(|w| w == b"avcC"));
+    assert!(data.windows(4).any(|w| w == b"mp4a"));
+
+    // The stream ends before the requested duration
+    let duration = client.record_live(0, Duration::from_secs(10), &path)?;
+    assert_eq!(Duration::from_millis(1960), duration);
+    let data = std::fs::read(&path)?;
+    assert_eq!(2, data.windows(4).filter(|w| w == b"moof").count());
+
+    // Timestamps restart from zero in the middle of the second group of pictures
+    device.set_faults(Faults { live_timestamp_reset: Some(30), ..Faults::default() });
+    let duration = client.record_live(0, Duration::from_millis(1500), &path)?;
+    assert_eq!(Duration::from_millis(1480), duration);
+
+    std::fs::remove_file(&path)?;
+    Ok(())
+}
+
+#[test]
+fn test_search() -> anyhow::Result<()> {
+    use reolink_api::api::record::search::*;
+
+    let device = MockDevice::start(MockConfig::camera().with_files(files()))?;
+    let client = client(&device)?;
+
+    let resp = client.exec(&SearchRequest {
+        search: Search {
+            channel: 0,
+            only_status: false,
+            stream_type: StreamType::Main,
+            start_time: date_time(9, 0, 0).try_into()?,
+            end_time: date_time(9, 23, 59).try_into()?,
+        }
+    })?;
+
+    let status = resp.search_result.status.unwrap();
+    assert_eq!(1, status.len());
+    assert_eq!(2, status[0].mon);
+    assert_eq!(28, status[0].table.len());
+    assert_eq!(1, status[0].table[8]);
+    assert_eq!(0, status[0].table[9]);
+    assert_eq!(1, status[0].table[10]);
+
+    let files = resp.search_result.file.unwrap();
+    assert_eq!(1, files.len());
+    assert_eq!(100, files[0].size);
+    Ok(())
+}
+
+#[test]
+fn test_search_files() -> anyhow::Result<()> {
+    let mut files = files();
+    files.push(MockFile {
+        channel: 0,
+        stream_type: StreamType::Main,
+        start: date_time(11, 23, 58),
+        end: date_time(12, 0, 3),
+        name: "Mp4Record/2025-02-11/RecM01_20250211_235800_000300_6D28808_20.mp4".to_string(),
+        data: vec![3; 20],
+    });
+    let device = MockDevice::start(MockConfig::camera().with_files(files))?;
+    let client = client(&device)?;
+
+    let names = client.search_files(0, StreamType::Main, date_time(1, 0, 0), date_time(28, 23, 59))
+        .map(|f| f.map(|f| f.name))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    assert_eq!(3, names.len());
+    assert!(names[0].contains("20250209_100000"));
+    assert!(names[2].contains("20250211_235800"));
+
+    // One status search, then one search per day with recordings
+    assert_eq!(1 + 3, device.commands().iter().filter(|c| *c == "Search").count());
+
+    // Lazy
+    let mut iter = client.search_files(0, StreamType::Main, date_time(9, 0, 0), date_time(11, 23, 59));
+    assert!(iter.next().is_some());
+    assert_eq!(1 + 3 + 2, device.commands().iter().filter(|c| *c == "Search").count());
+    Ok(())
+}
+
+#[test]
+fn test_timeline() -> anyhow::Result<()> {
+    use reolink_api::api::record::DetectionType;
+    use reolink_api::api::record::get_recording_v20::GetRecordingRequest;
+    use reolink_api::chrono::TimeDelta;
+
+    let mut files = files();
+    // Continuous recording right after the first file
+    files.push(MockFile {
+        channel: 0,
+        stream_type: StreamType::Main,
+        start: date_time(9, 10, 5) + TimeDelta::seconds(1),
+        end: date_time(9, 10, 10),
+        name: "Mp4Record/2025-02-09/RecM01_20250209_100501_101000_6D28808_100.mp4".to_string(),
+        data: vec![1; 100],
+    });
+    let device = MockDevice::start(MockConfig::camera().with_files(files))?;
+    let client = client(&device)?;
+
+    let start = date_time(9, 0, 0);
+    let end = date_time(12, 0, 0);
+    let timeline = client.timeline(0, StreamType::Main, start, end)?;
+    assert_eq!(2, timeline.segments().len());
+    assert_eq!(2, timeline.segments()[0].files);
+    assert_eq!(TimeDelta::minutes(12), timeline.total_duration());
+    assert_eq!(3, timeline.gaps(start, end, TimeDelta::hours(1)).len());
+
+    // Motion detection is scheduled all the time
+    let config = client.exec(&GetRecordingRequest { channel: 0 })?.rec;
+    let schedule = config.schedule.weekly_schedule(&DetectionType::Motion)?;
+    let coverage = timeline.schedule_coverage(&schedule, start, end);
+    assert_eq!(TimeDelta::hours(3 * 24), coverage.scheduled);
+    assert_eq!(TimeDelta::minutes(12), coverage.recorded);
+
+    // No continuous recording table
+    let schedule = config.schedule.weekly_schedule(&DetectionType::Timer)?;
+    assert!(timeline.schedule_coverage(&schedule, start, end).scheduled.is_zero());
+    Ok(())
+}
+
+#[test]
+fn test_recording_calendar() -> anyhow::Result<()> {
+    use reolink_api::chrono::Datelike;
+
+    let mut files = files();
+    files.push(MockFile {
+        channel: 0,
+        stream_type: StreamType::Sub,
+        start: date_time(14, 8, 0),
+        end: date_time(14, 8, 2),
+        name: "Mp4Record/2025-02-14/RecS01_20250214_080000_080200_6D28808_20.mp4".to_string(),
+        data: vec![2; 20],
+    });
+    let device = MockDevice::start(MockConfig::camera().with_files(files))?;
+    let client = client(&device)?;
+
+    let calendar = client.recording_calendar(0, 2025, 2)?;
+    assert_eq!(28, calendar.len());
+    let days = calendar.days_with_recordings().map(|d| d.day()).collect::<Vec<_>>();
+    assert_eq!(vec![9, 11, 14], days);
+
+    // Several months in a single response
+    let calendar = client.recording_calendar_range(0, NaiveDate::from_ymd_opt(2025, 1, 15).unwrap(), NaiveDate::from_ymd_opt(2025, 3, 10).unwrap())?;
+    assert_eq!(17 + 28 + 10, calendar.len());
+    assert_eq!(3, calendar.days_with_recordings().count());
+    // One status search per stream type
+    assert_eq!(2 * 2, device.commands().iter().filter(|c| *c == "Search").count());
+
+    assert!(client.recording_calendar(0, 2025, 13).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_export_range() -> anyhow::Result<()> {
+    use reolink_api::blocking::export::{ExportManifest, ExportStatus};
+
+    let dir = std::env::temp_dir().join(format!("reolink-export-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+
+    let device = MockDevice::start(MockConfig::nvr(1).with_files(files()))?;
+    let client = client(&device)?;
+    let downloads = || device.commands().iter().filter(|c| *c == "Download").count();
+    let export = || client.export_range(0, StreamType::Main, date_time(1, 0, 0), date_time(28, 23, 59), &dir);
+
+    // First download is truncated and retried
+    device.set_faults(Faults { truncated_downloads: 1, ..Faults::default() });
+    let manifest = export()?;
+    assert!(manifest.is_complete());
+    assert_eq!(vec![(ExportStatus::Downloaded, 2), (ExportStatus::Downloaded, 1)],
+        manifest.files.iter().map(|f| (f.status, f.attempts)).collect::<Vec<_>>());
+    assert_eq!(3, downloads());
+    assert_eq!(vec![1; 100], std::fs::read(dir.join("RecM01_20250209_100000_100500_6D28808_100.mp4"))?);
+
+    let written = serde_json::from_slice::<ExportManifest>(&std::fs::read(dir.join("manifest.json"))?)?;
+    assert_eq!(2, written.files.len());
+
+    // Completed files are skipped, incomplete ones downloaded again
+    std::fs::write(dir.join("RecM01_20250211_080000_080200_6D28808_50.mp4"), [2; 10])?;
+    let manifest = export()?;
+    assert_eq!(vec![ExportStatus::Skipped, ExportStatus::Downloaded], manifest.files.iter().map(|f| f.status).collect::<Vec<_>>());
+    assert_eq!(4, downloads());
+
+    // Failures are reported in the manifest
+    std::fs::remove_file(dir.join("RecM01_20250211_080000_080200_6D28808_50.mp4"))?;
+    device.set_faults(Faults { truncated_downloads: 10, ..Faults::default() });
+    let manifest = export()?;
+    assert!(!manifest.is_complete());
+    let failed = manifest.failed().collect::<Vec<_>>();
+    assert_eq!(1, failed.len());
+    assert_eq!(3, failed[0].attempts);
+    assert_eq!(Some("Expected 50 bytes, got 25".to_string()), failed[0].error);
+    assert!(!dir.join("RecM01_20250211_080000_080200_6D28808_50.mp4.part").exists());
+
+    // Invalid names are reported in the manifest, and don't stop the export
+    let mut files = files();
+    files.insert(0, MockFile { name: "Mp4Record/manifest.json".to_string(), ..files[0].clone() });
+    let device = MockDevice::start(MockConfig::nvr(1).with_files(files))?;
+    let client = self::client(&device)?;
+    let manifest = client.export_range(0, StreamType::Main, date_time(1, 0, 0), date_time(28, 23, 59), &dir)?;
+    assert_eq!(vec![ExportStatus::Failed, ExportStatus::Skipped, ExportStatus::Downloaded],
+        manifest.files.iter().map(|f| f.status).collect::<Vec<_>>());
+    assert_eq!(Some("Invalid file name 'Mp4Record/manifest.json'".to_string()), manifest.files[0].error);
+    let written = serde_json::from_slice::<ExportManifest>(&std::fs::read(dir.join("manifest.json"))?)?;
+    assert_eq!(3, written.files.len());
+
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_time_zone() -> anyhow::Result<()> {
+    use reolink_api::chrono::{TimeZone, Utc};
+
+    let device = MockDevice::start(MockConfig::nvr(1).with_cet().with_files(files()))?;
+    let client = client(&device)?;
+
+    let tz = client.time_zone()?;
+    assert_eq!(3600, tz.standard.local_minus_utc());
+    assert!(tz.dst.is_some());
+
+    // The device's day, in UTC
+    let start = Utc.with_ymd_and_hms(2025, 2, 8, 23, 0, 0).unwrap();
+    let end = Utc.with_ymd_and_hms(2025, 2, 9, 22, 59, 59).unwrap();
+    let resp = client.search_utc(0, StreamType::Main, start, end, false)?;
+    let files = resp.search_result.file.unwrap();
+    assert_eq!(1, files.len());
+    assert_eq!(Utc.with_ymd_and_hms(2025, 2, 9, 9, 0, 0).unwrap(), tz.to_utc(&files[0].start_time)?);
+
+    let resp = client.nvr_download_utc(0, StreamType::Main, start, end)?;
+    assert_eq!(1, resp.file_count);
+
+    // Fetched only once
+    client.time_zone()?;
+    assert_eq!(1, device.commands().iter().filter(|c| *c == "GetTime").count());
+    Ok(())
+}
+
+#[test]
+fn test_update_config() -> anyhow::Result<()> {
+    use reolink_api::api::record::get_recording_v20::*;
+    use reolink_api::api::ValidationError;
+
+    let device = MockDevice::start(MockConfig::nvr(4))?;
+    let client = client(&device)?;
+
+    // Defaults are returned for channel 0, which isn't a difference
+    assert!(client.diff_from_default::<GetRecordingRequest>(2)?.is_empty());
+
+    let config = client.update::<GetRecordingRequest>(2, |cfg| {
+        cfg.overwrite = false;
+        cfg.post_rec = DeviceDuration::minutes(5);
+    })?;
+    assert!(!config.overwrite);
+    assert_eq!(2, config.schedule.channel);
+
+    let diffs = client.diff_from_default::<GetRecordingRequest>(2)?;
+    let paths = diffs.iter().map(|d| d.path.as_str()).collect::<Vec<_>>();
+    assert_eq!(vec!["overwrite", "postRec"], paths);
+
+    // Invalid values are rejected before being sent
+    let err = client.update::<GetRecordingRequest>(2, |cfg| cfg.post_rec = DeviceDuration::minutes(3)).unwrap_err();
+    assert!(err.downcast_ref::<ValidationError>().is_some());
+    assert!(err.to_string().contains("allowed values are: 15 Seconds, 30 Seconds, 1 Minute"));
+
+    let config = client.reset_to_default::<GetRecordingRequest>(2)?;
+    assert!(config.overwrite);
+    assert_eq!(2, config.schedule.channel);
+    assert!(client.diff_from_default::<GetRecordingRequest>(2)?.is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_add_user() -> anyhow::Result<()> {
+    use reolink_api::api::security::add_user::*;
+    use reolink_api::api::security::get_user::*;
+
+    let device = MockDevice::start(MockConfig::camera())?;
+    let client = client(&device)?;
+
+    let mut req = AddUserRequest {
+        user: AddUser {
+            username: "newuser".to_string(),
+            password: "zeechohya5ie8daeLaiy".to_string(),
+            level: UserLevel::Guest,
+        }
+    };
+    client.exec_validated(&req)?;
+    assert_eq!(2, client.exec(&GetUserRequest)?.user.len());
+
+    req.user.username = "other".to_string();
+    req.user.password = "short".to_string();
+    assert!(client.exec_validated(&req).is_err());
+    assert_eq!(2, client.exec(&GetUserRequest)?.user.len());
+    Ok(())
+}
+
+#[test]
+fn test_fleet() -> anyhow::Result<()> {
+    use reolink_api::blocking::fleet::Fleet;
+
+    let camera = MockDevice::start(MockConfig::camera())?;
+    let nvr = MockDevice::start(MockConfig::nvr(8))?;
+
+    let mut fleet = Fleet::new();
+    fleet.add("camera", client(&camera)?)?;
+    fleet.add("nvr", client(&nvr)?)?;
+    fleet.add_device("offline", "http://127.0.0.1:9/", "admin".to_string(), "pass".to_string())?;
+
+    let inventory = fleet.inventory();
+    assert_eq!("RLC-810A", inventory[0].result.as_ref().unwrap().model);
+    assert_eq!(8, inventory[1].result.as_ref().unwrap().channel_num);
+    assert!(inventory[2].result.is_err());
+
+    let health = fleet.health();
+    assert_eq!(8, health[1].result.as_ref().unwrap().status.len());
+    Ok(())
+}
+
+#[test]
+fn test_address_detection() -> anyhow::Result<()> {
+    use reolink_api::api::system::get_dev_info::*;
+
+    let device = MockDevice::start(MockConfig::camera())?;
+    let client = ReolinkClient::new(&device.addr().to_string(), "admin".to_string(), "s3cr3t-p4ssw0rd".to_string())?;
+
+    client.exec(&GetDevInfoRequest)?;
+    assert_eq!(format!("{}cgi-bin/api.cgi", device.url()), client.api_url()?.to_string());
+    // Only one probe request reached the device (the https probe fails)
+    assert_eq!(vec!["GetDevinfo", "GetDevinfo"], device.commands());
+    Ok(())
+}
+
+#[test]
+fn test_faults() -> anyhow::Result<()> {
+    use reolink_api::api::system::get_ability::*;
+    use reolink_api::api::system::get_dev_info::*;
+
+    let device = MockDevice::start(MockConfig::camera())?;
+    let client = client(&device)?;
+    let get_ability = GetAbilityRequest { user: GetAbility { user_name: "NULL".to_string() } };
+
+    // Latency
+    device.set_faults(Faults { latency: Duration::from_millis(200), ..Faults::default() });
+    let start = std::time::Instant::now();
+    client.exec(&GetDevInfoRequest)?;
+    assert!(start.elapsed() >= Duration::from_millis(200));
+
+    // Malformed JSON
+    device.set_faults(Faults { malformed_json: true, ..Faults::default() });
+    assert!(client.exec(&GetDevInfoRequest).is_err());
+
+    // Expired tokens
+    device.set_faults(Faults::default());
+    client.exec(&get_ability)?;
+    device.expire_tokens();
+    let err = client.exec(&get_ability).unwrap_err();
+    assert_eq!(Some(-6), rsp_code(&err));
+
+    // Session limit
+    device.set_faults(Faults { max_sessions: Some(1), ..Faults::default() });
+    let other = self::client(&device)?;
+    other.login()?;
+    let err = self::client(&device)?.login().unwrap_err();
+    assert_eq!(Some(-5), rsp_code(&err));
+    Ok(())
+}
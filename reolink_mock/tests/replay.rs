@@ -0,0 +1,49 @@
+use std::sync::Arc;
+use reolink_api::blocking::ReolinkClient;
+use reolink_api::blocking::replay::{RecordingTransport, ReplayTransport};
+use reolink_mock::{MockConfig, MockDevice};
+
+#[test]
+fn test_record_and_replay() -> anyhow::Result<()> {
+    use reolink_api::api::record::snapshot::*;
+    use reolink_api::api::system::get_ability::*;
+    use reolink_api::api::system::get_dev_info::*;
+
+    let path = std::env::temp_dir().join(format!("reolink-replay-{}.json", std::process::id()));
+    let get_ability = GetAbilityRequest { user: GetAbility { user_name: "NULL".to_string() } };
+    let snapshot = SnapshotRequest { channel: 0, rs: "0123456789abcdef".to_string(), width: None, height: None };
+
+    let (dev_info, image, snapshot_jpeg) = {
+        let device = MockDevice::start(MockConfig::camera())?;
+        let http = reolink_api::reqwest::blocking::Client::new();
+        let recorder = Arc::new(RecordingTransport::new(http.clone()));
+        let client = ReolinkClient::new_with_transport(http, recorder.clone(), &device.url(), "admin".to_string(), "s3cr3t-p4ssw0rd".to_string())?;
+
+        let dev_info = client.exec(&GetDevInfoRequest)?.dev_info;
+        client.exec(&get_ability)?;
+        let image = client.download(&snapshot)?;
+        let snapshot_jpeg = client.snapshot(&SnapshotRequest::new(0))?.jpeg;
+        drop(client);
+
+        recorder.save(&path)?;
+        (dev_info, image, snapshot_jpeg)
+    };
+
+    let fixture = std::fs::read_to_string(&path)?;
+    assert!(!fixture.contains("s3cr3t-p4ssw0rd"));
+    assert!(!fixture.contains("mock"), "token should be redacted");
+
+    // Device is gone, replay the recorded exchanges
+    let client = ReolinkClient::new_with_transport(
+        reolink_api::reqwest::blocking::Client::new(), ReplayTransport::load(&path)?,
+        "http://127.0.0.1:9/", "admin".to_string(), "other-password".to_string()
+    )?;
+    assert_eq!(dev_info.serial, client.exec(&GetDevInfoRequest)?.dev_info.serial);
+    assert_eq!(1, client.exec(&get_ability)?.ability.channels.len());
+    assert_eq!(image, client.download(&snapshot)?);
+    // The random `rs` of snapshots differs from the recorded one
+    assert_eq!(snapshot_jpeg, client.snapshot(&SnapshotRequest::new(0))?.jpeg);
+
+    std::fs::remove_file(&path)?;
+    Ok(())
+}